@@ -0,0 +1,156 @@
+use crate::errors::{DataError, Result};
+use regex::Regex;
+
+/// Tags commits as reverts or backports based on their messages, and with the
+/// subsystems they touch based on their changed paths.
+pub struct CommitClassifier {
+    revert: Regex,
+    backport: Regex,
+    // (path prefix, subsystem name), longest prefix first
+    subsystems: Vec<(String, String)>,
+}
+
+impl CommitClassifier {
+    pub fn new(
+        revert_pattern: &str,
+        backport_pattern: &str,
+        subsystem_prefixes: &[String],
+    ) -> Result<Self> {
+        let mut subsystems = subsystem_prefixes
+            .iter()
+            .map(|entry| parse_subsystem(entry))
+            .collect::<Result<Vec<_>>>()?;
+        subsystems.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Ok(Self {
+            revert: compile(revert_pattern, "--revert-pattern")?,
+            backport: compile(backport_pattern, "--backport-pattern")?,
+            subsystems,
+        })
+    }
+
+    pub fn is_revert(&self, message: &str) -> bool {
+        self.revert.is_match(message)
+    }
+
+    pub fn is_backport(&self, message: &str) -> bool {
+        self.backport.is_match(message)
+    }
+
+    /// Distinct subsystems touched by a set of changed paths, in sorted order.
+    /// Each path counts towards the subsystem of its longest matching prefix;
+    /// paths matching no prefix are ignored.
+    pub fn subsystems<'a>(&'a self, paths: &[String]) -> Vec<&'a str> {
+        let mut found: Vec<&str> = paths
+            .iter()
+            .filter_map(|path| {
+                self.subsystems
+                    .iter()
+                    .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+                    .map(|(_, name)| name.as_str())
+            })
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+// Parses a `PREFIX=NAME` subsystem mapping.
+fn parse_subsystem(entry: &str) -> Result<(String, String)> {
+    match entry.split_once('=') {
+        Some((prefix, name)) if !prefix.is_empty() && !name.is_empty() => {
+            Ok((prefix.to_string(), name.to_string()))
+        }
+        _ => Err(DataError::ConfigError(format!(
+            "Invalid --subsystem '{}': expected PREFIX=NAME",
+            entry
+        ))),
+    }
+}
+
+fn compile(pattern: &str, option: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .map_err(|e| DataError::ConfigError(format!("Invalid {} '{}': {}", option, pattern, e)))
+}
+
+/// How a merged pull request's commits reached the base branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrationType {
+    /// A merge commit joined the PR head into the base branch
+    Merge,
+    /// The PR head itself landed on the base branch unchanged
+    FastForward,
+    /// The PR commits were replayed onto the base branch
+    Rebase,
+    /// The PR commits were combined into one new commit
+    Squash,
+}
+
+impl IntegrationType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IntegrationType::Merge => "merge",
+            IntegrationType::FastForward => "fast-forward",
+            IntegrationType::Rebase => "rebase",
+            IntegrationType::Squash => "squash",
+        }
+    }
+
+    /// Classifies a merged PR from its merge commit (SHA, whether it has
+    /// several parents, and message) and its head SHA. A single-parent merge commit that isn't the
+    /// head is a rebase when its subject matches the subject of the PR's last
+    /// commit, which `last_pr_subject` looks up only in that case, and a
+    /// squash otherwise. One-commit PRs rebased with their message intact thus
+    /// count as rebases.
+    pub fn classify(
+        merge_sha: &str,
+        is_merge: bool,
+        merge_message: &str,
+        head_sha: Option<&str>,
+        last_pr_subject: impl FnOnce() -> Result<Option<String>>,
+    ) -> Result<Self> {
+        if is_merge {
+            return Ok(IntegrationType::Merge);
+        }
+        if head_sha == Some(merge_sha) {
+            return Ok(IntegrationType::FastForward);
+        }
+        let subject = merge_message.lines().next().unwrap_or_default().trim();
+        Ok(match last_pr_subject()? {
+            Some(last) if last.trim() == subject => IntegrationType::Rebase,
+            _ => IntegrationType::Squash,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(is_merge: bool, message: &str, head: &str) -> IntegrationType {
+        IntegrationType::classify("m1", is_merge, message, Some(head), || {
+            Ok(Some("Fix the widget".to_string()))
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn classifies_integration_from_the_merge_commit() {
+        assert_eq!(
+            classify(true, "Merge #12: Fix", "h1"),
+            IntegrationType::Merge
+        );
+        assert_eq!(
+            classify(false, "Fix the widget", "m1"),
+            IntegrationType::FastForward
+        );
+        assert_eq!(
+            classify(false, "Fix the widget\n\nbody", "h1"),
+            IntegrationType::Rebase
+        );
+        assert_eq!(
+            classify(false, "Widget fixes (#12)\n\n* Fix the widget", "h1"),
+            IntegrationType::Squash
+        );
+    }
+}
@@ -1,18 +1,72 @@
-use clap::Parser;
+use crate::errors::{DataError, Result as DataResult};
+use crate::github::EndpointOverride;
+use crate::timeparse::parse_date_arg;
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Structure to hold command line arguments
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    after_help = "Exit codes: 0 success, 1 other failure, 2 configuration error, \
+                  3 GitHub rate limit exhausted, 4 network or authentication error, \
+                  5 some repositories failed to load"
+)]
 pub struct Config {
     /// Path to the DuckDB database file
     #[arg(short, long, env = "DUCKDB_PATH", default_value = "repo_data.db")]
     pub db_path: String,
 
-    /// GitHub personal access token (optional, increases rate limit)
+    /// Database as a URL, overriding --db-path: `sqlite://<path>`, or
+    /// `sqlite::memory:` for an in-memory database. Other databases such as
+    /// Postgres are not supported
+    #[arg(long, env = "DATABASE_URL")]
+    pub db_url: Option<String>,
+
+    /// Open the database read-only (for `report`/`export` while another process loads it)
+    #[arg(long, env = "DB_READONLY", default_value_t = false)]
+    pub db_readonly: bool,
+
+    /// How long to wait for another process's lock on the database before
+    /// failing with "database is locked", in milliseconds
+    #[arg(long, env = "BUSY_TIMEOUT_MS", default_value_t = 5000)]
+    pub busy_timeout_ms: u64,
+
+    /// GitHub personal access token; API fetches without one need
+    /// --allow-unauthenticated
     #[arg(short, long, env = "GITHUB_TOKEN")]
     pub github_token: Option<String>,
 
-    /// URL or path for the first repository (e.g., bitcoin/bitcoin)
+    /// Fetch from the GitHub API without a token. Anonymous requests are
+    /// limited to 60 per hour, so fetches of large repositories mostly wait
+    /// for the rate limit to reset; without this flag they are refused
+    #[arg(long, env = "ALLOW_UNAUTHENTICATED", default_value_t = false)]
+    pub allow_unauthenticated: bool,
+
+    /// GitHub App ID; with the installation ID and private key, API requests
+    /// use short-lived installation tokens instead of the personal token
+    #[arg(long, env = "GITHUB_APP_ID", requires_all = ["app_installation_id", "app_private_key"])]
+    pub app_id: Option<String>,
+
+    /// Installation ID of the GitHub App on the organization or account
+    #[arg(long, env = "GITHUB_APP_INSTALLATION_ID", requires = "app_id")]
+    pub app_installation_id: Option<String>,
+
+    /// Path to the GitHub App's private key (PEM)
+    #[arg(long, env = "GITHUB_APP_PRIVATE_KEY_PATH", requires = "app_id")]
+    pub app_private_key: Option<String>,
+
+    /// URL or path for the first repository (e.g., bitcoin/bitcoin). Append
+    /// `@TOKEN_ENV` to authenticate it with the token in that environment variable.
+    /// A local directory (`/path`, `./path`, `../path` or `file://path`) is
+    /// analysed from git alone as `local/<dir name>-<path hash>`
     #[arg(
         long,
         env = "REPO1_PATH",
@@ -20,7 +74,8 @@ pub struct Config {
     )]
     pub repo1_path: String,
 
-    /// URL or path for the second repository (e.g., bitcoinknots/bitcoin)
+    /// URL or path for the second repository (e.g., bitcoinknots/bitcoin); also
+    /// accepts an `@TOKEN_ENV` suffix
     #[arg(
         long,
         env = "REPO2_PATH",
@@ -28,15 +83,970 @@ pub struct Config {
     )]
     pub repo2_path: String,
 
+    /// Repository paths with their own token: (path, environment variable)
+    #[arg(skip)]
+    pub repo_token_envs: Vec<(String, String)>,
+
     /// Local directory to clone/store the repositories
     #[arg(long, env = "CLONE_DIR", default_value = "./repo_clones")]
     pub clone_dir: String,
 
-    /// Force fetching data even if DB exists (useful for updates)
+    /// Base directory for a relative --clone-dir and --db-path (default: the
+    /// current directory). Both are made absolute at startup, so runs started
+    /// from different directories share the same clones and database.
+    #[arg(long, env = "WORKDIR")]
+    pub workdir: Option<String>,
+
+    /// Force fetching data even if DB exists (useful for updates). Also ignores
+    /// --max-clone-age and refetches repositories nobody pushed to since the last run.
     #[arg(long, default_value_t = false)]
     pub force_fetch: bool,
+
+    /// Only fetch data changed since the last successful run (less a safety
+    /// margin); falls back to the full window when there is no prior run
+    #[arg(long, default_value_t = false)]
+    pub since_last_run: bool,
+
+    /// Extend a loaded window backward: fetch only API commits older than the
+    /// earliest one already stored for each repository, back to --since
+    #[arg(long, default_value_t = false, conflicts_with = "since_last_run")]
+    pub backfill: bool,
+
+    /// Keep running and repeat the fetch this long after each one finishes
+    /// (e.g. 30m, 6h) until SIGTERM or Ctrl-C. Combine with --since-last-run
+    /// so each cycle only fetches what changed; repositories nobody pushed to
+    /// are skipped cheaply either way.
+    #[arg(long, env = "INTERVAL", value_parser = parse_duration, conflicts_with = "dry_run")]
+    pub interval: Option<Duration>,
+
+    /// Start of the fetch window (and of the report's contributor window): a date,
+    /// timestamp, or span back from now such as 30d, 2w, 12m or 2y (m = months here)
+    #[arg(long, env = "SINCE", value_parser = parse_date, default_value = "12m")]
+    pub since: DateTime<Utc>,
+
+    /// End of the fetch window for commits (and of the report's contributor
+    /// window; now by default), in the same formats as --since
+    #[arg(long, env = "UNTIL", value_parser = parse_date)]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only fetch issues and PRs carrying this label; repeat for several.
+    /// Multiple labels are AND-combined (items must carry all of them), as
+    /// GitHub does. PRs are then found via the search API (max 1000 results).
+    #[arg(long = "label", env = "LABELS", value_delimiter = ',')]
+    pub labels: Vec<String>,
+
+    /// Only fetch commits, issues and PRs authored by this GitHub login; repeat
+    /// for several. Multiple authors are OR-combined (anything by any of them).
+    /// Commits are listed once per author; issues and PRs are found via the
+    /// search API (max 1000 results per author).
+    #[arg(long = "author", env = "AUTHORS", value_delimiter = ',')]
+    pub authors: Vec<String>,
+
+    /// GitHub login of a maintainer of both repositories; repeat for several.
+    /// Stored commits, PRs and issues are tagged by whether their author is one,
+    /// and the report splits activity between maintainers and the community
+    #[arg(long = "maintainer", env = "MAINTAINERS", value_delimiter = ',')]
+    pub maintainers: Vec<String>,
+
+    /// File of maintainer logins, one per line: `login` for both repositories
+    /// or `owner/name=login` for one of them. Blank lines and `#` comments are
+    /// ignored. Adds to --maintainer
+    #[arg(long, env = "MAINTAINERS_FILE")]
+    pub maintainers_file: Option<String>,
+
+    /// Entries of --maintainers-file as (repository, login), once loaded
+    #[arg(skip)]
+    pub maintainer_entries: Vec<(Option<String>, String)>,
+
+    /// Skip the GitHub API and only extract history from the clones. Remote
+    /// repositories are still cloned/updated; local directories are used as is
+    #[arg(long, env = "NO_API", default_value_t = false)]
+    pub no_api: bool,
+
+    /// Clone missing repositories blobless (`git clone --filter=blob:none`) to cut
+    /// the download size. Needs the git CLI; falls back to a full clone without it.
+    #[arg(long, env = "PARTIAL_CLONE", default_value_t = false)]
+    pub partial_clone: bool,
+
+    /// Store the release tags of the local clones and the commits of each
+    /// release since the one before it on its release line, for changelog
+    /// comparisons
+    #[arg(long, env = "TAG_CHANGELOGS", default_value_t = false)]
+    pub tag_changelogs: bool,
+
+    /// Regex selecting the release tags used for --tag-changelogs; the default
+    /// matches final Core (v27.1) and Knots (v27.1.knots20240801) releases
+    #[arg(
+        long,
+        env = "TAG_PATTERN",
+        default_value = r"^v\d+(\.\d+)+(\.knots\d+)?$"
+    )]
+    pub tag_pattern: String,
+
+    /// Only fetch these entity types (comma-separated or repeated); all by default.
+    /// Enrichment passes run only alongside the entity they enrich.
+    #[arg(long, value_enum, env = "ONLY", value_delimiter = ',')]
+    pub only: Vec<Entity>,
+
+    /// Branch whose history is extracted from the local clones; defaults to each
+    /// repository's default branch as reported by GitHub
+    #[arg(long, env = "DEFAULT_BRANCH")]
+    pub default_branch: Option<String>,
+
+    /// Store the unified diff of this commit (repeat or comma-separate for
+    /// several) for each repository that has it, from the local clone when
+    /// possible and otherwise from the API
+    #[arg(long = "patch", value_name = "SHA", value_delimiter = ',')]
+    pub patch_shas: Vec<String>,
+
+    /// Skip updating a local clone fetched more recently than this (e.g. 30m, 2h, 1d)
+    #[arg(long, env = "MAX_CLONE_AGE", value_parser = parse_duration)]
+    pub max_clone_age: Option<Duration>,
+
+    /// Number of rows inserted per database transaction. Insert throughput
+    /// levels off from about 1000 rows; smaller values shorten how long each
+    /// write holds the database lock
+    #[arg(long, env = "BATCH_SIZE", default_value_t = 5000)]
+    pub batch_size: usize,
+
+    /// Fetch each closed issue individually to record who closed it (one request per issue)
+    #[arg(long, default_value_t = false)]
+    pub enrich_closed_by: bool,
+
+    /// Longest rate-limit reset to wait out before giving up, in seconds
+    #[arg(long, env = "MAX_RATE_LIMIT_WAIT_SECS", default_value_t = 3600)]
+    pub max_rate_limit_wait_secs: u64,
+
+    /// Fail requests whose response body is larger than this many bytes
+    /// instead of reading it all into memory, e.g. from a misbehaving proxy
+    /// or mirror. Unlimited when unset
+    #[arg(long, env = "MAX_RESPONSE_BYTES")]
+    pub max_response_bytes: Option<u64>,
+
+    /// Most time all retries of a fetch run may spend waiting, in seconds;
+    /// once spent, the next retry fails the request. Unlimited by default
+    #[arg(long, env = "MAX_TOTAL_RETRY_SECS")]
+    pub max_total_retry_secs: Option<u64>,
+
+    /// Most GitHub API requests in flight at once. GitHub's secondary limits
+    /// allow up to 100 concurrent requests, but bursts get flagged well before
+    /// that; 4 is a safe value with a token, 1 without.
+    #[arg(long, env = "CONCURRENCY", default_value_t = 4)]
+    pub concurrency: usize,
+
+    /// Repositories loaded in parallel, each on its own thread and database
+    /// connection. Above 1 the database is switched to WAL mode so reports
+    /// can still read it, but SQLite allows one writer at a time: a load
+    /// waits up to --busy-timeout-ms for the other's write to finish, so
+    /// raise it if runs fail with "database is locked"
+    #[arg(long, env = "REPO_CONCURRENCY", default_value_t = 1)]
+    pub repo_concurrency: usize,
+
+    /// Cap on GitHub API requests started per second (unlimited if unset).
+    /// About 1 suits unauthenticated use (60 requests/hour regardless);
+    /// 10 keeps authenticated runs clear of abuse detection.
+    #[arg(long, env = "REQUESTS_PER_SECOND")]
+    pub requests_per_second: Option<f64>,
+
+    /// Random delay of up to this many milliseconds before each API request
+    #[arg(long, env = "JITTER_MS", default_value_t = 100)]
+    pub jitter_ms: u64,
+
+    /// Save each raw API response body under this directory (named by endpoint
+    /// and page) before parsing it, for debugging and later --replay
+    #[arg(long, env = "RAW_DUMP", conflicts_with = "replay")]
+    pub raw_dump: Option<String>,
+
+    /// Serve API responses from a --raw-dump directory instead of the network.
+    /// Requests must match the recording, so pin --since to a fixed date.
+    #[arg(long, env = "REPLAY")]
+    pub replay: Option<String>,
+
+    /// Print the estimated API requests and the remaining rate limit, then exit
+    /// without fetching. Exits with the rate limit status when the estimate
+    /// exceeds what is left, so `--dry-run && <fetch>` only starts a fetch that
+    /// fits. The estimate itself spends a few search requests per repository
+    #[arg(long, default_value_t = false, conflicts_with = "replay")]
+    pub dry_run: bool,
+
+    /// Copy the database to a timestamped file next to it before fetching, so
+    /// a bad run can be rolled back
+    #[arg(long, env = "BACKUP", default_value_t = false)]
+    pub backup: bool,
+
+    /// How many --backup copies of the database to keep; older ones are
+    /// deleted after each backup. 0 keeps them all
+    #[arg(long, env = "KEEP_BACKUPS", default_value_t = 5)]
+    pub keep_backups: usize,
+
+    /// Run `vacuum` after each successful fetch, so a database loaded
+    /// repeatedly doesn't keep growing with free pages
+    #[arg(long, env = "VACUUM_AFTER_LOAD", default_value_t = false)]
+    pub vacuum_after_load: bool,
+
+    /// Skip (and log) individual list records that fail to deserialize instead
+    /// of failing the whole page; the run summary counts them per repository
+    #[arg(long, env = "LENIENT", default_value_t = false)]
+    pub lenient: bool,
+
+    /// Fetch review thread resolution per PR via the GraphQL API (requires a token)
+    #[arg(long, default_value_t = false)]
+    pub review_threads: bool,
+
+    /// Send API requests whose path matches TEMPLATE to another host, e.g.
+    /// `/repos/{owner}/{repo}/commits=http://localhost:8080` for a cached
+    /// mirror; repeat for several. TARGET is a base URL or a URL template with
+    /// the same placeholders. The token is only sent there when TARGET ends in
+    /// `+auth`, which requires an https:// URL
+    #[arg(long = "endpoint-override", value_name = "TEMPLATE=TARGET", value_parser = EndpointOverride::parse)]
+    pub endpoint_overrides: Vec<EndpointOverride>,
+
+    /// Fetch the reviews of each PR to record its first review time and who
+    /// reviewed whom (one request per PR)
+    #[arg(long, default_value_t = false)]
+    pub reviews: bool,
+
+    /// Fetch the users with access to each repository and their permissions.
+    /// Needs a token with push access; repositories without it are skipped
+    /// with a warning
+    #[arg(long, default_value_t = false)]
+    pub collaborators: bool,
+
+    /// Fetch each repository's recent activity feed from the Events API
+    /// (pushes, PR and issue activity, releases). GitHub keeps about 90 days
+    /// and at most 300 events, so it's a cheap view of current activity
+    #[arg(long, default_value_t = false)]
+    pub events: bool,
+
+    /// Run the per-commit passes (CI statuses, patches) and the integration
+    /// type pass again for commits and PRs they already completed in earlier
+    /// runs, which are skipped by default
+    #[arg(long, default_value_t = false)]
+    pub re_enrich: bool,
+
+    /// Fetch the conversation comments of issues and PRs to record when
+    /// someone other than the author first responded (one request per PR and
+    /// per commented issue). With --reviews, a PR's first review counts too
+    #[arg(long, default_value_t = false)]
+    pub comments: bool,
+
+    /// Classify how each merged PR was integrated (merge commit, fast-forward,
+    /// rebase or squash). Merge commits missing from the stored commits cost a
+    /// request, and single-parent ones another for the PR's commits
+    #[arg(long, default_value_t = false)]
+    pub integration_type: bool,
+
+    /// Which PRs the reviews pass fetches reviews for
+    #[arg(long, value_enum, env = "REVIEWS_SCOPE", default_value_t = ReviewsScope::Merged)]
+    pub reviews_scope: ReviewsScope,
+
+    /// Fetch CI statuses and check runs for each fetched commit (two requests per commit)
+    #[arg(long, default_value_t = false)]
+    pub commit_statuses: bool,
+
+    /// After loading, cross-check stored rows against fetched data and exit
+    /// nonzero on anomalies (missing rows, duplicate keys, unmatched merge commits)
+    #[arg(long, default_value_t = false)]
+    pub verify: bool,
+
+    /// Stop fetching API commits and extracting local commits for a repository
+    /// after this many, guarding against huge repositories. Local extraction
+    /// resumes from where it stopped on the next run.
+    #[arg(long, env = "MAX_COMMITS_PER_REPO")]
+    pub max_commits_per_repo: Option<usize>,
+
+    /// Fetch only the N most recently created pull requests per repository,
+    /// for quick samples; other entities are not capped
+    #[arg(long, env = "LIMIT_PRS")]
+    pub limit_prs: Option<usize>,
+
+    /// Fetch only the N most recently created issues per repository. The
+    /// issues endpoint lists PRs too, and they count towards the cap
+    #[arg(long, env = "LIMIT_ISSUES")]
+    pub limit_issues: Option<usize>,
+
+    /// Persist locally extracted commits every N commits so long extractions can resume
+    #[arg(long, env = "AUTOSAVE_EVERY", default_value_t = 1000)]
+    pub autosave_every: usize,
+
+    /// Sort fetched commits before inserting them so repeated loads produce
+    /// identically ordered tables. This is a client-side sort applied after
+    /// the full fetch completes; the API itself returns newest first.
+    #[arg(long, value_enum, env = "COMMIT_ORDER", default_value_t = CommitOrder::Api)]
+    pub commit_order: CommitOrder,
+
+    /// How much of each commit message to store. Co-author trailers and
+    /// revert/backport tags are read from the full message either way, but
+    /// with `subject` or `none` later pattern changes only reach newly stored
+    /// commits.
+    #[arg(long, value_enum, env = "COMMIT_MESSAGE", default_value_t = CommitMessage::Full)]
+    pub commit_message: CommitMessage,
+
+    /// Which date of a commit fills `commit_timestamp`, and so drives the
+    /// activity trends. Rebased and cherry-picked commits have an author date
+    /// well before their committer date. Both are stored either way, and a
+    /// repository's stored commits are restamped when the choice changes
+    #[arg(long, value_enum, env = "COMMIT_DATE", default_value_t = CommitDate::Committer)]
+    pub commit_date: CommitDate,
+
+    /// Regex matched against commit messages to flag reverts
+    #[arg(long, env = "REVERT_PATTERN", default_value = r"(?m)^Revert\b")]
+    pub revert_pattern: String,
+
+    /// Regex matched against commit messages to flag backports/cherry-picks
+    #[arg(
+        long,
+        env = "BACKPORT_PATTERN",
+        default_value = r"(?i)\[backport\]|cherry[- ]picked from"
+    )]
+    pub backport_pattern: String,
+
+    /// Path prefix to subsystem mapping as `PREFIX=NAME`; repeat for several.
+    /// A path belongs to the subsystem of its longest matching prefix.
+    #[arg(
+        long = "subsystem",
+        env = "SUBSYSTEMS",
+        value_delimiter = ',',
+        default_values = [
+            "src/consensus/=consensus",
+            "src/script/=consensus",
+            "src/wallet/=wallet",
+            "src/qt/=gui",
+            "src/net=p2p",
+            "src/rpc/=rpc",
+            "src/policy/=policy",
+            "src/test/=tests",
+            "test/=tests",
+            "doc/=docs",
+            "build_msvc/=build",
+            "depends/=build",
+            "ci/=build",
+        ]
+    )]
+    pub subsystems: Vec<String>,
+
+    /// Log output format; `json` emits one JSON object per line
+    #[arg(long, value_enum, env = "LOG_FORMAT", default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Format of log lines written to stderr
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Order in which fetched commits are inserted
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitOrder {
+    /// Keep the order returned by the API
+    Api,
+    /// Oldest committer date first, ties broken by SHA
+    Date,
+    /// Lexicographic by SHA
+    Sha,
+}
+
+/// How much of a commit message is stored
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitMessage {
+    /// Only the first line
+    Subject,
+    /// The whole message
+    Full,
+    /// Nothing; the message column is NULL
+    None,
+}
+
+impl CommitMessage {
+    /// The part of `message` to store.
+    pub fn apply(self, message: Option<&str>) -> Option<&str> {
+        match self {
+            CommitMessage::Subject => message.and_then(|m| m.lines().next()),
+            CommitMessage::Full => message,
+            CommitMessage::None => None,
+        }
+    }
+}
+
+/// Which date of a commit is used as its timestamp
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitDate {
+    /// When the change was originally written
+    Author,
+    /// When the commit was made, e.g. by a rebase or cherry-pick
+    Committer,
+}
+
+impl CommitDate {
+    /// The chosen date, falling back to the other one when it is missing.
+    pub fn pick(
+        self,
+        author: Option<DateTime<Utc>>,
+        committer: Option<DateTime<Utc>>,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            CommitDate::Author => author.or(committer),
+            CommitDate::Committer => committer.or(author),
+        }
+    }
+}
+
+/// Entity types fetched per repository, selectable with `--only`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entity {
+    /// Commits from the GitHub API (and their CI statuses)
+    Commits,
+    /// Pull requests (and their review threads and reviews)
+    PullRequests,
+    /// Issues (and who closed them)
+    Issues,
+    /// Repository contributors
+    Contributors,
+    /// Milestones and their open/closed counts
+    Milestones,
+    /// Bytes of code per language, kept as a snapshot per run
+    Languages,
+    /// Commit history extracted from the local clone
+    LocalCommits,
+}
+
+impl Config {
+    /// Token for a configured repository: the one in the environment variable
+    /// named by its `@TOKEN_ENV` suffix, otherwise `--github-token`.
+    pub fn github_token_for(&self, repo_path: &str) -> DataResult<Option<String>> {
+        match self
+            .repo_token_envs
+            .iter()
+            .find(|(path, _)| path == repo_path)
+        {
+            Some((_, var)) => std::env::var(var).map(Some).map_err(|_| {
+                DataError::ConfigError(format!(
+                    "{} asks for the token in ${}, which is not set",
+                    repo_path, var
+                ))
+            }),
+            None => Ok(self.github_token.clone()),
+        }
+    }
+
+    /// Every token the configuration supplies, to keep them out of the logs.
+    pub fn secrets(&self) -> Vec<String> {
+        let per_repo = self
+            .repo_token_envs
+            .iter()
+            .filter_map(|(_, var)| std::env::var(var).ok());
+        self.github_token.iter().cloned().chain(per_repo).collect()
+    }
+
+    /// Reads --maintainers-file, if given, so a missing file fails the run
+    /// before anything is fetched.
+    pub fn load_maintainers_file(&mut self) -> DataResult<()> {
+        if let Some(path) = &self.maintainers_file {
+            let text = fs::read_to_string(path).map_err(|e| {
+                DataError::ConfigError(format!("Cannot read --maintainers-file {}: {}", path, e))
+            })?;
+            self.maintainer_entries = parse_maintainers(&text);
+        }
+        Ok(())
+    }
+
+    /// Maintainer logins of a repository (`owner/name`), lowercased: the
+    /// --maintainer logins and the --maintainers-file entries that apply to it.
+    pub fn maintainers_for(&self, repo_name_full: &str) -> BTreeSet<String> {
+        let shared = self.maintainers.iter().map(|m| m.to_lowercase());
+        let listed = self
+            .maintainer_entries
+            .iter()
+            .filter(|(repo, _)| {
+                repo.as_ref()
+                    .is_none_or(|repo| repo.eq_ignore_ascii_case(repo_name_full))
+            })
+            .map(|(_, login)| login.clone());
+        shared.chain(listed).collect()
+    }
+
+    /// Configured repositories fetched from the API without any credentials:
+    /// neither a token nor a GitHub App. Local directories need none.
+    pub fn unauthenticated_repos(&self) -> DataResult<Vec<&str>> {
+        let mut repos = Vec::new();
+        for repo_path in [&self.repo1_path, &self.repo2_path] {
+            if crate::git_ops::is_local_repo(repo_path) {
+                continue;
+            }
+            if self.github_token_for(repo_path)?.is_none() && self.app_id.is_none() {
+                repos.push(repo_path.as_str());
+            }
+        }
+        Ok(repos)
+    }
+
+    /// Makes `clone_dir` and `db_path` (and the databases `diff` compares)
+    /// absolute, resolving relative paths against `--workdir` or the current
+    /// directory. Commands that write (see [`Config::writes`]) also create the
+    /// workdir, the clone directory and the database's parent directory if
+    /// needed; read-only ones leave the filesystem alone.
+    pub fn resolve_paths(&mut self) -> DataResult<()> {
+        let writes = self.writes();
+        let resolve = |path: PathBuf| -> DataResult<PathBuf> {
+            if writes {
+                fs::create_dir_all(&path)?;
+            }
+            // A missing directory is only resolved lexically
+            Ok(fs::canonicalize(&path).or_else(|_| std::path::absolute(&path))?)
+        };
+        let base = match &self.workdir {
+            Some(workdir) => resolve(PathBuf::from(workdir))?,
+            None => std::env::current_dir()?,
+        };
+
+        if let Some(url) = &self.db_url {
+            self.db_path = sqlite_path(url)?;
+        }
+
+        self.clone_dir = path_string(resolve(base.join(&self.clone_dir))?)?;
+
+        // SQLite's in-memory database has no file to resolve
+        if self.db_path != ":memory:" {
+            let db_path = base.join(&self.db_path);
+            let file_name = db_path.file_name().ok_or_else(|| {
+                DataError::ConfigError(format!("--db-path {} is not a file", self.db_path))
+            })?;
+            let parent = db_path.parent().unwrap_or(Path::new("/"));
+            self.db_path = path_string(resolve(parent.to_path_buf())?.join(file_name))?;
+        }
+
+        if let Some(Command::Diff(args)) = &mut self.command {
+            args.db_a = path_string(base.join(&args.db_a))?;
+            args.db_b = path_string(base.join(&args.db_b))?;
+        }
+
+        log::info!("Using clone directory {}", self.clone_dir);
+        log::info!("Using database {}", self.db_path);
+        Ok(())
+    }
+
+    /// Whether the command writes to the database or the clones: fetching
+    /// (the default), `range`, `import` and `vacuum`.
+    pub fn writes(&self) -> bool {
+        matches!(
+            self.command,
+            None | Some(Command::Fetch)
+                | Some(Command::Range(_))
+                | Some(Command::Import(_))
+                | Some(Command::Vacuum(_))
+        )
+    }
+
+    /// Whether `entity` should be fetched, i.e. `--only` is unset or lists it.
+    pub fn fetches(&self, entity: Entity) -> bool {
+        self.only.is_empty() || self.only.contains(&entity)
+    }
+}
+
+/// The SQLite database path of a `--db-url`. Other schemes are rejected, as
+/// SQLite is the only backend.
+pub fn sqlite_path(url: &str) -> DataResult<String> {
+    if url == "sqlite::memory:" {
+        return Ok(":memory:".to_string());
+    }
+    if let Some(path) = url.strip_prefix("sqlite://") {
+        if path.is_empty() {
+            return Err(DataError::ConfigError(format!(
+                "--db-url {} has no database path",
+                url
+            )));
+        }
+        return Ok(path.to_string());
+    }
+    let scheme = url.split_once("://").map_or(url, |(scheme, _)| scheme);
+    Err(DataError::ConfigError(match scheme {
+        "postgres" | "postgresql" => format!(
+            "--db-url {}: Postgres is not supported; use a sqlite:// URL",
+            scheme
+        ),
+        _ => format!(
+            "--db-url {} has an unknown scheme; use sqlite://<path>",
+            url
+        ),
+    }))
+}
+
+/// The entries of a maintainers file as (repository, lowercased login); the
+/// repository is `None` for logins that apply to both.
+pub fn parse_maintainers(text: &str) -> Vec<(Option<String>, String)> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once('=') {
+            Some((repo, login)) => (Some(repo.trim().to_string()), login.trim().to_lowercase()),
+            None => (None, line.to_lowercase()),
+        })
+        .filter(|(_, login)| !login.is_empty())
+        .collect()
+}
+
+fn path_string(path: PathBuf) -> DataResult<String> {
+    path.into_os_string()
+        .into_string()
+        .map_err(|path| DataError::ConfigError(format!("Path {:?} is not valid UTF-8", path)))
+}
+
+/// PRs covered by the per-PR reviews pass
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReviewsScope {
+    /// Every PR, including open and abandoned ones
+    All,
+    /// Only merged PRs
+    Merged,
+    /// Closed PRs, merged or not
+    Closed,
+}
+
+/// Subcommands; running without one is equivalent to `fetch`
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Fetch data from GitHub and the local clones into the database
+    Fetch,
+    /// Print a comparison report from the data already in the database
+    Report(ReportArgs),
+    /// List the refs advertised by each repository's remote without cloning
+    Refs(RefsArgs),
+    /// Export the database tables to files for other tools
+    Export(ExportArgs),
+    /// Store the commits between two refs (base...head) for each repository
+    Range(RangeArgs),
+    /// Compare two database files, e.g. snapshots from different runs
+    Diff(DiffArgs),
+    /// Load an NDJSON export into the database, e.g. to rebuild it on another machine
+    Import(ImportArgs),
+    /// Search issue and PR titles and descriptions of each repository
+    Search(SearchArgs),
+    /// Reclaim the space left by replaced and pruned rows (VACUUM) and refresh
+    /// SQLite's query statistics
+    Vacuum(VacuumArgs),
+}
+
+/// Options for the `vacuum` subcommand
+#[derive(Args, Debug)]
+pub struct VacuumArgs {
+    /// First delete the history that accumulates across runs (events, repo
+    /// snapshots, language mixes and run records) from before this date, in
+    /// the same formats as --since. Commits, PRs and issues are kept
+    #[arg(long, value_parser = parse_date)]
+    pub prune_before: Option<DateTime<Utc>>,
+}
+
+/// Options for the `search` subcommand
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    /// Words to look for. FTS5 query syntax (`"full rbf"`, `rbf OR replace*`)
+    /// works when SQLite has FTS5; otherwise it is matched as a substring
+    pub query: String,
+
+    /// Most matches listed per repository
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+}
+
+/// Options for the `range` subcommand
+#[derive(Args, Debug)]
+pub struct RangeArgs {
+    /// Base ref (branch, tag or SHA); its history is excluded
+    #[arg(long)]
+    pub base: String,
+
+    /// Head ref (branch, tag or SHA)
+    #[arg(long)]
+    pub head: String,
+
+    /// Use the GitHub compare API instead of the local clones
+    #[arg(long, default_value_t = false)]
+    pub api: bool,
+}
+
+/// Options for the `diff` subcommand
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// The earlier database
+    #[arg(long)]
+    pub db_a: String,
+
+    /// The later database; keys only found here are reported as added
+    #[arg(long)]
+    pub db_b: String,
+
+    /// Added and removed keys listed per table and repository (0 for counts only)
+    #[arg(long, default_value_t = 10)]
+    pub max_keys: usize,
+}
+
+/// Options for the `import` subcommand
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// Directory written by `export --export-ndjson`
+    #[arg(long)]
+    pub from: String,
+}
+
+/// Options for the `export` subcommand
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Write one Parquet file per table into this directory
+    #[arg(long)]
+    pub export_parquet: Option<String>,
+
+    /// Write one NDJSON file per table into this directory; `import` reads
+    /// these back
+    #[arg(long)]
+    pub export_ndjson: Option<String>,
+
+    /// Replace logins, author names and emails with salted hashes.
+    /// The same identity hashes to the same value in every table.
+    #[arg(long, requires = "anonymize_salt")]
+    pub anonymize: bool,
+
+    /// Salt mixed into anonymized identities; keep it private
+    #[arg(long, env = "ANONYMIZE_SALT", hide_env_values = true)]
+    pub anonymize_salt: Option<String>,
+}
+
+/// Options for the `refs` subcommand
+#[derive(Args, Debug)]
+pub struct RefsArgs {
+    /// Remote URL or `owner/repo` to inspect instead of the two configured
+    /// repositories
+    #[arg(long)]
+    pub url: Option<String>,
+}
+
+/// Options for the `report` subcommand
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Time bucket used to group activity into a trend
+    #[arg(long, value_enum, default_value_t = Bucket::Month)]
+    pub bucket: Bucket,
+
+    /// Write the bucketed activity as tidy CSV to this path instead of printing a table
+    #[arg(long)]
+    pub csv: Option<String>,
+
+    /// Also write the reviewer->author edge list, weighted by reviewed PRs, as
+    /// CSV to this path for graph tools
+    #[arg(long)]
+    pub review_edges_csv: Option<String>,
+
+    /// Leave merge commits out of commit counts, the activity trend and churn
+    #[arg(long, env = "EXCLUDE_MERGES", default_value_t = false)]
+    pub exclude_merges: bool,
+
+    /// Only show this metric in the totals table; repeat for several.
+    /// All registered metrics are shown by default.
+    #[arg(long = "metric")]
+    pub metrics: Vec<String>,
+
+    /// Only trend language snapshots fetched at or after this date
+    /// (YYYY-MM-DD, RFC 3339 or a relative age like 90d)
+    #[arg(long, value_parser = parse_date)]
+    pub repo_languages_since: Option<DateTime<Utc>>,
+
+    /// Lowest title similarity (0 to 1) at which a PR of the second
+    /// repository counts as a backport of one of the first
+    #[arg(long, value_parser = parse_fraction, default_value_t = 0.85)]
+    pub backport_similarity: f64,
+
+    /// How to render the side-by-side comparison tables
+    #[arg(long, value_enum, default_value_t = TableFormat::Table)]
+    pub format: TableFormat,
+
+    /// Print plain aligned columns, as when piping; same as `--format plain`
+    #[arg(long, conflicts_with = "format")]
+    pub plain: bool,
+
+    /// Regex matched against commit subjects for the share of commits with a
+    /// conventional prefix. Core-style area prefixes (`wallet: ...`) match
+    /// `^[\w/.-]+: ` instead
+    #[arg(
+        long,
+        default_value = r"^(feat|fix|docs?|test|build|refactor|perf|ci|chore|style|revert)(\([^)]*\))?!?: "
+    )]
+    pub commit_prefix_pattern: String,
+
+    /// Never color table headers. Colors are also off when stdout is not a
+    /// terminal or NO_COLOR is set
+    #[arg(long, default_value_t = false)]
+    pub no_color: bool,
+}
+
+/// Rendering of the report's comparison tables
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableFormat {
+    /// Boxed table for the terminal
+    Table,
+    /// Aligned columns without borders
+    Plain,
+    /// Tab-separated values, for spreadsheets
+    Tsv,
+    /// Markdown tables, for issues and documents
+    Markdown,
+}
+
+/// Granularity of the activity trend buckets
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bucket {
+    Day,
+    Week,
+    Month,
+}
+
+/// Parses a duration such as `90s`, `30m`, `2h` or `1d`; bare numbers are seconds.
+pub fn parse_duration_arg(s: &str) -> DataResult<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| DataError::ConfigError(format!("invalid duration '{}'", s)))?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(DataError::ConfigError(format!(
+                "invalid duration unit in '{}'; use s, m, h or d",
+                s
+            )))
+        }
+    };
+    let secs = value
+        .checked_mul(multiplier)
+        .ok_or_else(|| DataError::ConfigError(format!("duration '{}' is too long", s)))?;
+    Ok(Duration::from_secs(secs))
+}
+
+// clap adapter for `parse_duration_arg`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    parse_duration_arg(s).map_err(|e| e.to_string())
+}
+
+// Parses a number from 0 to 1.
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(format!("'{}' is not a number from 0 to 1", s)),
+    }
+}
+
+// clap adapter for `timeparse::parse_date_arg`.
+fn parse_date(s: &str) -> Result<DateTime<Utc>, String> {
+    parse_date_arg(s).map_err(|e| e.to_string())
+}
+
+// Splits `repo@TOKEN_ENV` into the repository and the variable name. Only an
+// upper-case suffix counts, so `git@github.com:owner/repo` is left alone.
+fn split_token_env(spec: &str) -> Option<(&str, &str)> {
+    let (repo, var) = spec.rsplit_once('@')?;
+    let is_var = !var.is_empty()
+        && var
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    (is_var && !repo.is_empty()).then_some((repo, var))
 }
 
 pub fn parse_config() -> Config {
-    Config::parse()
+    let mut config = Config::parse();
+    for path in [&mut config.repo1_path, &mut config.repo2_path] {
+        if let Some((repo, var)) = split_token_env(path) {
+            let (repo, var) = (repo.to_string(), var.to_string());
+            config.repo_token_envs.push((repo.clone(), var));
+            *path = repo;
+        }
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_take_a_unit_and_reject_overflow() {
+        let secs = |s: &str| parse_duration_arg(s).map(|d| d.as_secs()).ok();
+        assert_eq!(secs("90"), Some(90));
+        assert_eq!(secs(" 30m "), Some(1800));
+        assert_eq!(secs("2h"), Some(7200));
+        assert_eq!(secs("1d"), Some(86_400));
+        assert_eq!(secs("1w"), None);
+        assert_eq!(secs("h"), None);
+        assert_eq!(secs(&u64::MAX.to_string()), Some(u64::MAX));
+        assert!(matches!(
+            parse_duration_arg(&format!("{}d", u64::MAX / 60)),
+            Err(DataError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn db_urls_select_a_sqlite_path() {
+        assert_eq!(
+            sqlite_path("sqlite://data/repo.db").unwrap(),
+            "data/repo.db"
+        );
+        assert_eq!(
+            sqlite_path("sqlite:///var/lib/repo.db").unwrap(),
+            "/var/lib/repo.db"
+        );
+        assert_eq!(sqlite_path("sqlite::memory:").unwrap(), ":memory:");
+        for url in ["sqlite://", "postgres://u@host/db", "repo.db"] {
+            assert!(matches!(sqlite_path(url), Err(DataError::ConfigError(_))));
+        }
+    }
+
+    #[test]
+    fn maintainers_files_list_shared_and_per_repo_logins() {
+        let text = "# core team\nLaanwj\n\nbitcoinknots/bitcoin = luke-jr  # lead\nfoo/bar=\n";
+        assert_eq!(
+            parse_maintainers(text),
+            vec![
+                (None, "laanwj".to_string()),
+                (
+                    Some("bitcoinknots/bitcoin".to_string()),
+                    "luke-jr".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_writing_commands_create_directories() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let workdir = dir.path().join("work");
+        let config = |command: &str| {
+            Config::try_parse_from([
+                "data_loader_rust",
+                "--workdir",
+                workdir.to_str().unwrap(),
+                "--db-path",
+                "db/data.db",
+                command,
+            ])
+            .unwrap()
+        };
+
+        let mut report = config("report");
+        report.resolve_paths().unwrap();
+        assert!(!workdir.exists());
+        assert_eq!(
+            Path::new(&report.db_path),
+            workdir.join("db").join("data.db")
+        );
+
+        config("fetch").resolve_paths().unwrap();
+        assert!(workdir.join("db").is_dir());
+        assert!(workdir.join("repo_clones").is_dir());
+    }
 }
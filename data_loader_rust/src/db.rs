@@ -1,18 +1,198 @@
+use crate::classify::CommitClassifier;
+use crate::config::{CommitDate, CommitMessage, Entity};
 use crate::errors::Result;
-use crate::github::{GitHubCommit, GitHubContributor, GitHubIssue, GitHubPullRequest};
+use crate::git_ops::{GitTag, LocalCommit};
+use crate::github::{
+    GitHubCheckRun, GitHubCollaborator, GitHubCommit, GitHubContributor, GitHubEvent, GitHubIssue,
+    GitHubMilestone, GitHubPullRequest, GitHubStatus, RepoInfo, ReviewThreadCounts,
+};
+use crate::summary::RunSummary;
+use crate::trailers::{parse_coauthors, parse_trailers};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
 use rusqlite::params;
-use rusqlite::Connection;
+use rusqlite::{
+    Connection, DatabaseName, OpenFlags, OptionalExtension, Transaction, TransactionBehavior,
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+use std::time::Duration;
 
 /// Establishes a connection to the SQLite database file.
-pub fn establish_connection(db_path: &str) -> Result<Connection> {
+pub fn establish_connection(db_path: &str, busy_timeout: Duration) -> Result<Connection> {
     log::info!("Connecting to database: {}", db_path);
     let conn = Connection::open(db_path)?;
+    // Wait out locks held by concurrent loaders/reports instead of failing
+    conn.busy_timeout(busy_timeout)?;
     // Enable foreign keys for potential relational data later
     conn.execute("PRAGMA foreign_keys = ON;", [])?;
     log::info!("Database connection established.");
     Ok(conn)
 }
 
+/// Starts a transaction that takes the write lock up front (`BEGIN
+/// IMMEDIATE`). A deferred transaction that reads before it writes fails at
+/// once with "database is locked" when another connection wrote in between,
+/// since waiting could not help it; this one waits out the busy timeout like
+/// any other write.
+pub fn write_transaction(conn: &Connection) -> Result<Transaction<'_>> {
+    Ok(Transaction::new_unchecked(
+        conn,
+        TransactionBehavior::Immediate,
+    )?)
+}
+
+/// Opens an existing database without taking write locks, so reports can run
+/// while another process is loading data. Tables are not created.
+pub fn establish_readonly_connection(db_path: &str, busy_timeout: Duration) -> Result<Connection> {
+    log::info!("Connecting to database read-only: {}", db_path);
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(busy_timeout)?;
+    log::info!("Database connection established.");
+    Ok(conn)
+}
+
+/// Copies the database to `<db_path>.<UTC timestamp>.bak` with SQLite's online
+/// backup API, which yields a consistent copy even if another process is
+/// writing. Returns the backup's path.
+pub fn backup_database(conn: &Connection, db_path: &str) -> Result<String> {
+    let backup_path = format!("{}.{}.bak", db_path, Utc::now().format("%Y%m%dT%H%M%SZ"));
+    log::info!("Backing up database to {}", backup_path);
+    conn.backup(DatabaseName::Main, &backup_path, None)?;
+    Ok(backup_path)
+}
+
+/// Deletes all but the `keep` newest backups `backup_database` made of
+/// `db_path`; `keep` 0 keeps them all. Returns the deleted paths.
+pub fn prune_backups(db_path: &str, keep: usize) -> Result<Vec<String>> {
+    let path = Path::new(db_path);
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return Ok(Vec::new());
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let prefix = format!("{}.", name);
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let file_name = entry?.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        let is_backup = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .is_some_and(|stamp| {
+                chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%SZ").is_ok()
+            });
+        if is_backup {
+            backups.push(dir.join(file_name));
+        }
+    }
+    if keep == 0 || backups.len() <= keep {
+        return Ok(Vec::new());
+    }
+    // The timestamps sort chronologically as text
+    backups.sort();
+    let mut deleted = Vec::new();
+    for backup in &backups[..backups.len() - keep] {
+        log::info!("Deleting old backup {}", backup.display());
+        std::fs::remove_file(backup)?;
+        deleted.push(backup.to_string_lossy().into_owned());
+    }
+    Ok(deleted)
+}
+
+/// Rolls back a transaction a failed write left open, so the connection can
+/// be reused. Returns whether one was open.
+pub fn rollback_open_transaction(conn: &Connection) -> Result<bool> {
+    if conn.is_autocommit() {
+        return Ok(false);
+    }
+    conn.execute_batch("ROLLBACK;")?;
+    Ok(true)
+}
+
+// Size of the database file plus its WAL, which holds pages not yet
+// checkpointed into the main file. A missing file counts as empty.
+fn database_size(db_path: &str) -> u64 {
+    [db_path.to_string(), format!("{}-wal", db_path)]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Rebuilds the database file to drop the free pages left by deletes and
+/// replaced rows, then lets SQLite refresh its query planner statistics. A
+/// WAL database is checkpointed first so the rebuild sees every page, and
+/// again afterwards so the WAL file is truncated. Returns the size in bytes
+/// (database plus WAL) before and after.
+pub fn vacuum_database(conn: &Connection, db_path: &str) -> Result<(u64, u64)> {
+    let before = database_size(db_path);
+    let journal_mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+    let wal = journal_mode.eq_ignore_ascii_case("wal");
+    if wal {
+        checkpoint(conn)?;
+    }
+    log::info!("Vacuuming {}", db_path);
+    conn.execute_batch("VACUUM; PRAGMA optimize;")?;
+    if wal {
+        checkpoint(conn)?;
+    }
+    Ok((before, database_size(db_path)))
+}
+
+// Append-only history tables and the column holding when each row was recorded
+const HISTORY_TABLES: &[(&str, &str)] = &[
+    ("repo_events", "created_at"),
+    ("repo_snapshots", "fetched_at"),
+    ("repo_languages", "fetched_at"),
+    ("run_repos", "started_at"),
+    ("runs", "started_at"),
+];
+
+/// Deletes the rows of the history tables recorded before `before` and returns
+/// how many were deleted. Commits, PRs and issues are left alone, since a
+/// fetch would not bring back rows outside its --since window.
+pub fn prune_history(conn: &Connection, before: &DateTime<Utc>) -> Result<usize> {
+    let tx = write_transaction(conn)?;
+    let mut pruned = 0;
+    for (table, column) in HISTORY_TABLES {
+        pruned += tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE julianday({}) < julianday(?1)",
+                table, column
+            ),
+            params![before.to_rfc3339()],
+        )?;
+    }
+    tx.commit()?;
+    Ok(pruned)
+}
+
+/// Switches the database to write-ahead logging, which lets readers and one
+/// writer work at the same time. The mode is stored in the file and persists.
+pub fn enable_wal(conn: &Connection) -> Result<()> {
+    let mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        log::warn!("Could not enable WAL mode; the journal mode is {}", mode);
+    }
+    Ok(())
+}
+
+// Copies the WAL into the database file and truncates the WAL. The pragma
+// returns a status row, so it can't go through `execute_batch`.
+fn checkpoint(conn: &Connection) -> Result<()> {
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(())
+}
+
 /// Creates the necessary database tables if they don't exist.
 pub fn create_tables(conn: &Connection) -> Result<()> {
     log::info!("Creating database tables if they don't exist...");
@@ -21,21 +201,21 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
     // Using TEXT for timestamps (ISO 8601 format) is common and simple.
     // Using INTEGER for GitHub IDs/Numbers.
     // VARCHAR maps to TEXT in SQLite.
+    let tx = write_transaction(conn)?;
     conn.execute_batch(
         r"
-        BEGIN;
-
         -- Schemas are not directly supported in SQLite,
         -- table names will include the prefix implicitly.
 
         CREATE TABLE IF NOT EXISTS github_commits (
-            sha TEXT PRIMARY KEY,
+            sha TEXT,
             repo_name TEXT,
             author_login TEXT,
             committer_login TEXT,
             message TEXT,
             commit_timestamp TEXT, -- Store as ISO 8601 string
-            api_url TEXT
+            api_url TEXT,
+            PRIMARY KEY (repo_name, sha) -- Forks share most of their history
         );
 
         CREATE TABLE IF NOT EXISTS github_pull_requests (
@@ -76,30 +256,416 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
             PRIMARY KEY (repo_name, login) -- Composite key
         );
 
+        CREATE TABLE IF NOT EXISTS collaborators (
+            repo_name TEXT,
+            login TEXT,
+            id INTEGER,
+            admin INTEGER,
+            maintain INTEGER,
+            push INTEGER,
+            triage INTEGER,
+            pull INTEGER,
+            role_name TEXT, -- admin, maintain, write, triage, read or a custom role
+            fetched_at TEXT,
+            PRIMARY KEY (repo_name, login)
+        );
+
+        -- Recent activity feed from the Events API. Rows accumulate across
+        -- runs, while GitHub only serves about the last 90 days.
+        CREATE TABLE IF NOT EXISTS repo_events (
+            repo_name TEXT,
+            id TEXT, -- GitHub event ID
+            event_type TEXT, -- PushEvent, PullRequestEvent, ... or Other
+            actor_login TEXT,
+            action TEXT, -- opened, closed, created, ...; NULL for pushes and refs
+            number INTEGER, -- PR or issue number
+            ref_name TEXT, -- pushed, created or deleted ref, or release tag
+            created_at TEXT,
+            PRIMARY KEY (repo_name, id)
+        );
+
         -- Add tables for reviews, comments, contributors, etc.
 
         CREATE TABLE IF NOT EXISTS git_commits (
-            sha TEXT PRIMARY KEY,
+            sha TEXT,
             repo_name TEXT,
             author_name TEXT,
             author_email TEXT,
             commit_timestamp TEXT,
-            message TEXT
+            message TEXT,
+            PRIMARY KEY (repo_name, sha)
+        );
+
+        -- Tracks the last commit persisted by the git2 extractor so an
+        -- interrupted extraction can resume instead of starting over.
+        CREATE TABLE IF NOT EXISTS git_extraction_state (
+            repo_name TEXT PRIMARY KEY,
+            last_sha TEXT,
+            updated_at TEXT
+        );
+
+        -- Release tags of the local clones matching --tag-pattern
+        CREATE TABLE IF NOT EXISTS git_tags (
+            repo_name TEXT,
+            tag_name TEXT,
+            commit_sha TEXT,
+            committed_at TEXT,
+            PRIMARY KEY (repo_name, tag_name)
+        );
+
+        -- Commits between consecutive release tags (from_tag..to_tag)
+        CREATE TABLE IF NOT EXISTS tag_changelog (
+            repo_name TEXT,
+            from_tag TEXT,
+            to_tag TEXT,
+            commit_sha TEXT,
+            subject TEXT,
+            PRIMARY KEY (repo_name, from_tag, to_tag, commit_sha)
+        );
+
+        -- Unified diffs of the commits requested with --patch
+        CREATE TABLE IF NOT EXISTS commit_patches (
+            repo_name TEXT,
+            sha TEXT,
+            source TEXT, -- 'git' or 'api'
+            patch TEXT,
+            PRIMARY KEY (repo_name, sha)
+        );
+
+        -- Commits between two refs (base...head), from git2 or the compare API
+        CREATE TABLE IF NOT EXISTS commit_ranges (
+            repo_name TEXT,
+            range_spec TEXT, -- e.g. v26.0...v27.0
+            source TEXT, -- 'git' or 'api'
+            sha TEXT,
+            PRIMARY KEY (repo_name, range_spec, source, sha)
+        );
+
+        CREATE TABLE IF NOT EXISTS pr_review_threads (
+            repo_name TEXT,
+            pr_number INTEGER,
+            resolved INTEGER,
+            unresolved INTEGER,
+            PRIMARY KEY (repo_name, pr_number)
+        );
+
+        CREATE TABLE IF NOT EXISTS review_edges (
+            repo_name TEXT,
+            reviewer_login TEXT,
+            author_login TEXT,
+            pr_number INTEGER,
+            PRIMARY KEY (repo_name, pr_number, reviewer_login)
+        );
+
+        CREATE TABLE IF NOT EXISTS commit_subsystems (
+            repo_name TEXT,
+            sha TEXT,
+            subsystem TEXT,
+            PRIMARY KEY (repo_name, sha, subsystem)
+        );
+
+        CREATE TABLE IF NOT EXISTS file_renames (
+            repo_name TEXT,
+            commit_sha TEXT,
+            old_path TEXT,
+            new_path TEXT,
+            PRIMARY KEY (repo_name, commit_sha, old_path)
+        );
+
+        CREATE TABLE IF NOT EXISTS commit_coauthors (
+            repo_name TEXT,
+            commit_sha TEXT,
+            name TEXT,
+            email TEXT,
+            PRIMARY KEY (repo_name, commit_sha, email)
+        );
+
+        CREATE TABLE IF NOT EXISTS enriched_commits (
+            repo_name TEXT,
+            sha TEXT,
+            enrichment TEXT, -- ENRICH_STATUSES, ENRICH_PATCH
+            enriched_at TEXT,
+            PRIMARY KEY (repo_name, sha, enrichment)
+        );
+
+        CREATE TABLE IF NOT EXISTS commit_trailers (
+            repo_name TEXT,
+            commit_sha TEXT,
+            key TEXT, -- as written, e.g. Signed-off-by
+            value TEXT,
+            PRIMARY KEY (repo_name, commit_sha, key, value)
+        );
+
+        CREATE TABLE IF NOT EXISTS issue_labels (
+            repo_name TEXT,
+            issue_number INTEGER, -- issues and PRs share the number space
+            label TEXT,
+            PRIMARY KEY (repo_name, issue_number, label)
+        );
+
+        CREATE TABLE IF NOT EXISTS commit_statuses (
+            repo_name TEXT,
+            sha TEXT,
+            source TEXT, -- 'status' or 'check_run'
+            context TEXT,
+            state TEXT, -- status state, or check run conclusion (status while running)
+            created_at TEXT,
+            PRIMARY KEY (repo_name, sha, source, context)
+        );
+
+        CREATE TABLE IF NOT EXISTS runs (
+            started_at TEXT PRIMARY KEY,
+            finished_at TEXT,
+            status TEXT -- 'ok' or 'failed'
+        );
+
+        -- How each repository of a run fared: 'ok', 'failed', 'skipped'
+        -- (unavailable) or 'truncated' (stopped at --max-commits-per-repo)
+        CREATE TABLE IF NOT EXISTS run_repos (
+            started_at TEXT,
+            repo_name TEXT,
+            status TEXT,
+            PRIMARY KEY (started_at, repo_name)
+        );
+
+        CREATE TABLE IF NOT EXISTS repo_snapshots (
+            repo_name TEXT,
+            fetched_at TEXT,
+            stargazers_count INTEGER,
+            watchers_count INTEGER,
+            forks_count INTEGER,
+            open_issues_count INTEGER,
+            pushed_at TEXT,
+            PRIMARY KEY (repo_name, fetched_at)
+        );
+
+        -- Appended per run, so the language mix can be trended
+        CREATE TABLE IF NOT EXISTS repo_languages (
+            repo_name TEXT,
+            fetched_at TEXT,
+            language TEXT,
+            bytes INTEGER,
+            PRIMARY KEY (repo_name, fetched_at, language)
+        );
+
+        CREATE TABLE IF NOT EXISTS milestones (
+            repo_name TEXT,
+            number INTEGER,
+            id INTEGER,
+            title TEXT,
+            state TEXT, -- 'open' or 'closed'
+            open_issues INTEGER, -- GitHub's counts, PRs included
+            closed_issues INTEGER,
+            due_on TEXT,
+            created_at TEXT,
+            closed_at TEXT,
+            PRIMARY KEY (repo_name, number)
         );
 
-        COMMIT;
+        CREATE TABLE IF NOT EXISTS repo_push_state (
+            repo_name TEXT PRIMARY KEY,
+            pushed_at TEXT, -- RepoInfo.pushed_at as of the last complete fetch
+            recorded_at TEXT
+        );
         ",
     )?;
+    tx.commit()?;
+
+    for (table, column, decl) in ADDED_COLUMNS {
+        ensure_column(conn, table, column, decl)?;
+    }
+    migrate_commit_keys(conn)?;
 
     log::info!("Table creation check complete.");
     Ok(())
 }
 
-/// Inserts or replaces GitHub commit data into the database.
+/// Columns added after the initial schema, as (table, column, declaration).
+/// They are added to existing databases on startup, so older files keep working.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("github_commits", "verified", "INTEGER"), // 0/1, NULL if unknown
+    ("github_commits", "verification_reason", "TEXT"),
+    ("github_commits", "is_revert", "INTEGER"),
+    ("github_commits", "is_backport", "INTEGER"),
+    ("github_commits", "html_url", "TEXT"),
+    ("github_commits", "is_merge", "INTEGER"), // 0/1 from the parent count
+    // Both dates of the commit; commit_timestamp holds the one --commit-date picks
+    ("github_commits", "author_date", "TEXT"),
+    ("github_commits", "committer_date", "TEXT"),
+    ("git_commits", "author_date", "TEXT"),
+    ("git_commits", "committer_date", "TEXT"),
+    ("git_commits", "is_revert", "INTEGER"),
+    ("git_commits", "is_backport", "INTEGER"),
+    // Lines added/removed vs. the first parent; NULL when extracted from a partial clone
+    ("git_commits", "insertions", "INTEGER"),
+    ("git_commits", "deletions", "INTEGER"),
+    ("git_commits", "is_merge", "INTEGER"),
+    // Reachable from the default branch tip of the local clone; NULL when
+    // the clone doesn't have the commit
+    ("github_commits", "on_default_branch", "INTEGER"),
+    ("git_commits", "on_default_branch", "INTEGER"),
+    // License GitHub detected at the time of the snapshot; NULL when none
+    ("repo_snapshots", "license_key", "TEXT"),
+    ("repo_snapshots", "license_name", "TEXT"),
+    ("repo_snapshots", "license_spdx_id", "TEXT"),
+    // Only populated by single-PR fetches; the list endpoint leaves these NULL
+    ("github_pull_requests", "mergeable", "INTEGER"),
+    ("github_pull_requests", "rebaseable", "INTEGER"),
+    ("github_pull_requests", "mergeable_state", "TEXT"),
+    // Filled in by the reviews pass; NULL for PRs without reviews
+    ("github_pull_requests", "first_review_at", "TEXT"),
+    ("github_issues", "closed_by_login", "TEXT"),
+    // Why the issue was closed (completed, not_planned) or reopened; NULL for
+    // issues closed before GitHub recorded it
+    ("github_issues", "state_reason", "TEXT"),
+    // The issues endpoint lists pull requests too; 1 for those. NULL on rows
+    // stored before the flag existed, until they are fetched again
+    ("github_issues", "is_pull_request", "INTEGER"),
+    // First comment by someone other than the author, from the comments pass
+    ("github_issues", "first_comment_at", "TEXT"),
+    ("github_pull_requests", "first_comment_at", "TEXT"),
+    // Seconds from opening to the first comment or review by someone other
+    // than the author; NULL when nobody responded or it wasn't fetched
+    ("github_issues", "first_response_seconds", "INTEGER"),
+    ("github_pull_requests", "first_response_seconds", "INTEGER"),
+    // Number of the milestone the item belongs to; NULL when it has none
+    ("github_issues", "milestone_number", "INTEGER"),
+    ("github_pull_requests", "milestone_number", "INTEGER"),
+    // Only present on single-PR fetches (--label/--author); the list endpoint omits it
+    ("github_pull_requests", "merged_by_login", "TEXT"),
+    // Description text, indexed for the `search` subcommand
+    ("github_pull_requests", "body", "TEXT"),
+    ("github_issues", "body", "TEXT"),
+    ("github_pull_requests", "head_sha", "TEXT"),
+    ("github_pull_requests", "base_sha", "TEXT"),
+    // merge, fast-forward, rebase or squash; set by --integration-type
+    ("github_pull_requests", "integration_type", "TEXT"),
+    // Author is in the --maintainer/--maintainers-file list; NULL when the
+    // author's login is unknown or no list was configured
+    ("github_commits", "is_maintainer", "INTEGER"),
+    ("github_pull_requests", "is_maintainer", "INTEGER"),
+    ("github_issues", "is_maintainer", "INTEGER"),
+    // Comma-separated --label filter the run fetched with; NULL when unfiltered
+    ("runs", "label_filter", "TEXT"),
+    // Comma-separated --author filter; NULL when unfiltered
+    ("runs", "author_filter", "TEXT"),
+    // Comma-separated --only entities; NULL when every entity was fetched
+    ("runs", "only_filter", "TEXT"),
+    // Start of the commit window the complete fetches behind pushed_at
+    // covered; NULL for state recorded before it was kept
+    ("repo_push_state", "fetched_since", "TEXT"),
+];
+
+/// Migrates databases created before commits were keyed per repository.
+///
+/// Their `github_commits` and `git_commits` have `sha` as the sole primary
+/// key, so a commit present in both forks was stored once, under whichever
+/// repository was loaded last. Each such table is rebuilt in its own
+/// transaction with a (repo_name, sha) key, keeping all columns and rows.
+/// Commits already lost to that overwrite are not recovered; they come back
+/// once the repository that lost them is fetched again. Databases created
+/// since have the new key and are left alone.
+fn migrate_commit_keys(conn: &Connection) -> Result<()> {
+    for table in ["github_commits", "git_commits"] {
+        migrate_commit_key(conn, table)?;
+    }
+    Ok(())
+}
+
+fn migrate_commit_key(conn: &Connection, table: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let key: Vec<&str> = columns
+        .iter()
+        .filter(|(_, _, pk)| *pk > 0)
+        .map(|(name, _, _)| name.as_str())
+        .collect();
+    if key != ["sha"] {
+        return Ok(());
+    }
+
+    let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+        row.get(0)
+    })?;
+    log::warn!(
+        "Migrating {}: rebuilding its {} rows with a (repo_name, sha) primary key, so \
+         commits shared by both repositories are kept for each. Fetch both repositories \
+         again to restore commits an earlier load overwrote",
+        table,
+        rows
+    );
+    let definitions: Vec<String> = columns
+        .iter()
+        .map(|(name, decl, _)| format!("{} {}", name, decl))
+        .collect();
+    let tx = write_transaction(conn)?;
+    conn.execute_batch(&format!(
+        r"
+        CREATE TABLE {table}_rekeyed ({columns}, PRIMARY KEY (repo_name, sha));
+        INSERT INTO {table}_rekeyed SELECT * FROM {table};
+        DROP TABLE {table};
+        ALTER TABLE {table}_rekeyed RENAME TO {table};
+        ",
+        table = table,
+        columns = definitions.join(", ")
+    ))?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Adds a column to a table unless it already exists.
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+    if !exists {
+        log::info!("Adding column {}.{}", table, column);
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {};",
+            table, column, decl
+        ))?;
+    }
+    Ok(())
+}
+
+/// Calls `insert_row` for every row, committing a transaction every
+/// `batch_size` rows so large loads don't hold one long transaction.
+/// A failing row rolls back only the current batch.
+fn insert_in_batches<T>(
+    conn: &Connection,
+    rows: &[T],
+    batch_size: usize,
+    mut insert_row: impl FnMut(&T) -> Result<()>,
+) -> Result<()> {
+    for chunk in rows.chunks(batch_size.max(1)) {
+        // Dropped without a commit on error, which rolls the batch back
+        let tx = write_transaction(conn)?;
+        chunk.iter().try_for_each(&mut insert_row)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Inserts or replaces GitHub commit data into the database. Reverts and
+/// backports are tagged from the full message, before `message_mode` trims it.
 pub fn insert_github_commits(
     conn: &Connection,
     commits: &[GitHubCommit],
     repo_name_full: &str,
+    batch_size: usize,
+    classifier: &CommitClassifier,
+    message_mode: CommitMessage,
+    date_mode: CommitDate,
 ) -> Result<()> {
     log::info!(
         "Inserting {} commits for repo '{}' into database...",
@@ -109,64 +675,141 @@ pub fn insert_github_commits(
     let mut stmt = conn.prepare_cached(
         r"
         INSERT OR REPLACE INTO github_commits (
-            sha, repo_name, author_login, committer_login, message, commit_timestamp, api_url
+            sha, repo_name, author_login, committer_login, message, commit_timestamp, api_url,
+            html_url, verified, verification_reason, is_merge, author_date, committer_date,
+            is_revert, is_backport
         )
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
         ",
     )?;
 
-    conn.execute_batch("BEGIN TRANSACTION;")?; // Start transaction for bulk insert
-
-    for commit in commits {
+    insert_in_batches(conn, commits, batch_size, |commit| {
+        let message = commit.commit.message.as_deref();
+        insert_coauthors(conn, repo_name_full, &commit.sha, message)?;
+        insert_trailers(conn, repo_name_full, &commit.sha, message)?;
         let author_login = commit.author.as_ref().map(|u| u.login.as_str());
         let committer_login = commit.committer.as_ref().map(|u| u.login.as_str());
-        let commit_timestamp = commit
-            .commit
-            .committer // Use committer date as it usually represents when it entered the repo
-            .as_ref()
-            .and_then(|c| c.date)
+        let author_date = commit.author_date();
+        let committer_date = commit.committer_date();
+        let commit_timestamp = date_mode
+            .pick(author_date, committer_date)
             .map(|dt| dt.to_rfc3339());
+        if commit_timestamp.is_none() {
+            log::warn!(
+                "Commit {} in {} has no committer or author date; storing it without a timestamp",
+                commit.sha,
+                repo_name_full
+            );
+        }
+        let verification = commit.commit.verification.as_ref();
 
         stmt.execute(params![
             commit.sha,
             repo_name_full,
             author_login,
             committer_login,
-            commit.commit.message,
+            message_mode.apply(commit.commit.message.as_deref()),
             commit_timestamp,
-            commit.url
+            commit.url, // the commit API URL; `commit.commit.url` is the git data URL
+            commit.html_url,
+            verification.map(|v| v.verified),
+            verification.and_then(|v| v.reason.as_deref()),
+            commit.is_merge(),
+            author_date.map(|dt| dt.to_rfc3339()),
+            committer_date.map(|dt| dt.to_rfc3339()),
+            message.map(|m| classifier.is_revert(m)),
+            message.map(|m| classifier.is_backport(m))
         ])?;
-    }
+        Ok(())
+    })?;
+    restamp_commits(conn, "github_commits", repo_name_full, date_mode)?;
 
-    conn.execute_batch("COMMIT;")?; // Commit transaction
     log::info!("Successfully inserted commits for {}", repo_name_full);
     Ok(())
 }
 
+// Recomputes `commit_timestamp` of a repository's earlier commits with the
+// date `date_mode` picks, so a run with a different --commit-date doesn't
+// leave the two kinds of timestamp mixed. Rows stored before the author and
+// committer dates were kept have neither and are left alone.
+fn restamp_commits(
+    conn: &Connection,
+    table: &str,
+    repo_name_full: &str,
+    date_mode: CommitDate,
+) -> Result<()> {
+    let picked = match date_mode {
+        CommitDate::Author => "COALESCE(author_date, committer_date, commit_timestamp)",
+        CommitDate::Committer => "COALESCE(committer_date, author_date, commit_timestamp)",
+    };
+    let restamped = conn.execute(
+        &format!(
+            "UPDATE {} SET commit_timestamp = {} WHERE repo_name = ?1 AND commit_timestamp IS NOT {}",
+            table, picked, picked
+        ),
+        params![repo_name_full],
+    )?;
+    if restamped > 0 {
+        log::info!(
+            "Restamped {} commits of {} in {} with their {:?} date",
+            restamped,
+            repo_name_full,
+            table,
+            date_mode
+        );
+    }
+    Ok(())
+}
+
 /// Inserts or replaces GitHub Pull Request data into the database.
 pub fn insert_github_pull_requests(
     conn: &Connection,
     prs: &[GitHubPullRequest],
     repo_name_full: &str,
+    batch_size: usize,
 ) -> Result<()> {
     log::info!(
         "Inserting {} PRs for repo '{}' into database...",
         prs.len(),
         repo_name_full
     );
+    // An upsert rather than a replace, so columns filled by enrichment passes
+    // survive a refetch. The mergeability fields and merged_by only come from
+    // single-PR fetches; a list fetch leaves the stored values alone.
     let mut stmt = conn.prepare_cached(
         r"
-        INSERT OR REPLACE INTO github_pull_requests (
+        INSERT INTO github_pull_requests (
             id, number, repo_name, state, title, user_login,
-            created_at, updated_at, closed_at, merged_at, merge_commit_sha
+            created_at, updated_at, closed_at, merged_at, merge_commit_sha,
+            mergeable, rebaseable, mergeable_state, milestone_number,
+            head_sha, base_sha, merged_by_login, body
         )
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19
+        )
+        ON CONFLICT (id) DO UPDATE SET
+            number = excluded.number,
+            repo_name = excluded.repo_name,
+            state = excluded.state,
+            title = excluded.title,
+            user_login = excluded.user_login,
+            created_at = excluded.created_at,
+            updated_at = excluded.updated_at,
+            closed_at = excluded.closed_at,
+            merged_at = excluded.merged_at,
+            merge_commit_sha = excluded.merge_commit_sha,
+            mergeable = COALESCE(excluded.mergeable, mergeable),
+            rebaseable = COALESCE(excluded.rebaseable, rebaseable),
+            mergeable_state = COALESCE(excluded.mergeable_state, mergeable_state),
+            milestone_number = excluded.milestone_number,
+            head_sha = excluded.head_sha,
+            base_sha = excluded.base_sha,
+            merged_by_login = COALESCE(excluded.merged_by_login, merged_by_login),
+            body = excluded.body
         ",
     )?;
 
-    conn.execute_batch("BEGIN TRANSACTION;")?;
-
-    for pr in prs {
+    insert_in_batches(conn, prs, batch_size, |pr| {
         let user_login = pr.user.as_ref().map(|u| u.login.as_str());
         let created_at_str = pr.created_at.to_rfc3339();
         let updated_at_str = pr.updated_at.to_rfc3339();
@@ -184,11 +827,19 @@ pub fn insert_github_pull_requests(
             updated_at_str,
             closed_at_str,
             merged_at_str,
-            pr.merge_commit_sha
+            pr.merge_commit_sha,
+            pr.mergeable,
+            pr.rebaseable,
+            pr.mergeable_state,
+            pr.milestone.as_ref().map(|m| m.number),
+            pr.head.as_ref().map(|b| b.sha.as_str()),
+            pr.base.as_ref().map(|b| b.sha.as_str()),
+            pr.merged_by.as_ref().map(|u| u.login.as_str()),
+            pr.body
         ])?;
-    }
+        Ok(())
+    })?;
 
-    conn.execute_batch("COMMIT;")?;
     log::info!("Successfully inserted PRs for {}", repo_name_full);
     Ok(())
 }
@@ -198,26 +849,52 @@ pub fn insert_github_issues(
     conn: &Connection,
     issues: &[GitHubIssue],
     repo_name_full: &str,
+    batch_size: usize,
 ) -> Result<()> {
     log::info!(
         "Inserting {} issues for repo '{}' into database...",
         issues.len(),
         repo_name_full
     );
+    // An upsert, so first comments found by the comments pass survive a
+    // refetch. closed_by only comes from single-issue fetches.
     let mut stmt = conn.prepare_cached(
         r"
-        INSERT OR REPLACE INTO github_issues (
+        INSERT INTO github_issues (
             id, number, repo_name, state, title, user_login,
-            created_at, updated_at, closed_at, comments_count
+            created_at, updated_at, closed_at, comments_count, closed_by_login,
+            milestone_number, body, state_reason, is_pull_request
         )
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+        ON CONFLICT (id) DO UPDATE SET
+            number = excluded.number,
+            repo_name = excluded.repo_name,
+            state = excluded.state,
+            title = excluded.title,
+            user_login = excluded.user_login,
+            created_at = excluded.created_at,
+            updated_at = excluded.updated_at,
+            closed_at = excluded.closed_at,
+            comments_count = excluded.comments_count,
+            closed_by_login = COALESCE(excluded.closed_by_login, closed_by_login),
+            milestone_number = excluded.milestone_number,
+            body = excluded.body,
+            state_reason = excluded.state_reason,
+            is_pull_request = excluded.is_pull_request
         ",
     )?;
-    // TODO: Handle labels, assignees separately if needed (many-to-many tables)
-
-    conn.execute_batch("BEGIN TRANSACTION;")?;
+    // Labels are replaced wholesale so removed labels don't linger
+    let mut delete_labels =
+        conn.prepare_cached("DELETE FROM issue_labels WHERE repo_name = ?1 AND issue_number = ?2")?;
+    let mut insert_label = conn.prepare_cached(
+        r"
+        INSERT OR IGNORE INTO issue_labels (repo_name, issue_number, label)
+        VALUES (?1, ?2, ?3)
+        ",
+    )?;
+    // TODO: Handle assignees separately if needed (many-to-many table)
 
-    for issue in issues {
+    insert_in_batches(conn, issues, batch_size, |issue| {
         let user_login = issue.user.as_ref().map(|u| u.login.as_str());
         let created_at_str = issue.created_at.to_rfc3339();
         let updated_at_str = issue.updated_at.to_rfc3339();
@@ -233,25 +910,1831 @@ pub fn insert_github_issues(
             created_at_str,
             updated_at_str,
             closed_at_str,
-            issue.comments
+            issue.comments,
+            issue.closed_by.as_ref().map(|u| u.login.as_str()),
+            issue.milestone.as_ref().map(|m| m.number),
+            issue.body,
+            issue.state_reason,
+            issue.pull_request.is_some()
         ])?;
-    }
 
-    conn.execute_batch("COMMIT;")?;
+        delete_labels.execute(params![repo_name_full, issue.number])?;
+        for label in &issue.labels {
+            insert_label.execute(params![repo_name_full, issue.number, label.name])?;
+        }
+        Ok(())
+    })?;
+
     log::info!("Successfully inserted issues for {}", repo_name_full);
     Ok(())
 }
 
-/// Inserts or replaces GitHub Contributor data into the database.
-pub fn insert_github_contributors(
+/// Inserts or replaces a repository's milestones.
+pub fn insert_milestones(
+    conn: &Connection,
+    milestones: &[GitHubMilestone],
+    repo_name_full: &str,
+    batch_size: usize,
+) -> Result<()> {
+    log::info!(
+        "Inserting {} milestones for repo '{}' into database...",
+        milestones.len(),
+        repo_name_full
+    );
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO milestones (
+            repo_name, number, id, title, state, open_issues, closed_issues,
+            due_on, created_at, closed_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+        ",
+    )?;
+    insert_in_batches(conn, milestones, batch_size, |milestone| {
+        stmt.execute(params![
+            repo_name_full,
+            milestone.number,
+            milestone.id,
+            milestone.title,
+            milestone.state,
+            milestone.open_issues,
+            milestone.closed_issues,
+            milestone.due_on.map(|dt| dt.to_rfc3339()),
+            milestone.created_at.to_rfc3339(),
+            milestone.closed_at.map(|dt| dt.to_rfc3339())
+        ])?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Sets when a PR was first reviewed, as found by the reviews pass.
+pub fn update_pr_first_review_at(
+    conn: &Connection,
+    repo_name_full: &str,
+    number: i64,
+    first_review_at: Option<&DateTime<Utc>>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE github_pull_requests SET first_review_at = ?1 WHERE repo_name = ?2 AND number = ?3",
+        params![
+            first_review_at.map(|dt| dt.to_rfc3339()),
+            repo_name_full,
+            number
+        ],
+    )?;
+    Ok(())
+}
+
+/// Sets when someone other than the author first commented on an issue or,
+/// with `pull_request`, on a PR.
+pub fn update_first_comment_at(
     conn: &Connection,
-    contributors: &[GitHubContributor],
     repo_name_full: &str,
+    number: i64,
+    pull_request: bool,
+    first_comment_at: Option<&DateTime<Utc>>,
 ) -> Result<()> {
-    // TODO: Implement insertion logic similar to commits/issues/prs
-    log::warn!("insert_github_contributors is not yet implemented.");
+    let table = if pull_request {
+        "github_pull_requests"
+    } else {
+        "github_issues"
+    };
+    conn.execute(
+        &format!(
+            "UPDATE {} SET first_comment_at = ?1 WHERE repo_name = ?2 AND number = ?3",
+            table
+        ),
+        params![
+            first_comment_at.map(|dt| dt.to_rfc3339()),
+            repo_name_full,
+            number
+        ],
+    )?;
     Ok(())
 }
 
-// TODO: Add functions to insert fetched data into the tables using rusqlite prepared statements
-// e.g., insert_github_contributors(conn: &Connection, contributors: &[GitHubContributor], repo_name: &str) -> Result<()>
+/// Derives `first_response_seconds` of a repo's issues and PRs from the
+/// first comment and, for PRs, the first review, whichever came first.
+pub fn derive_first_response(conn: &Connection, repo_name_full: &str) -> Result<()> {
+    conn.execute(
+        r"
+        UPDATE github_issues
+        SET first_response_seconds =
+            CAST(ROUND((julianday(first_comment_at) - julianday(created_at)) * 86400) AS INTEGER)
+        WHERE repo_name = ?1
+        ",
+        params![repo_name_full],
+    )?;
+    conn.execute(
+        r"
+        UPDATE github_pull_requests
+        SET first_response_seconds = CAST(ROUND((
+            julianday(COALESCE(MIN(first_comment_at, first_review_at), first_comment_at, first_review_at))
+            - julianday(created_at)) * 86400) AS INTEGER)
+        WHERE repo_name = ?1
+        ",
+        params![repo_name_full],
+    )?;
+    Ok(())
+}
+
+/// Replaces the reviewer->author edges of a PR with one edge per distinct
+/// reviewer, as found by the reviews pass.
+pub fn replace_review_edges(
+    conn: &Connection,
+    repo_name_full: &str,
+    pr_number: i64,
+    author_login: &str,
+    reviewer_logins: &[&str],
+) -> Result<()> {
+    let tx = write_transaction(conn)?;
+    conn.execute(
+        "DELETE FROM review_edges WHERE repo_name = ?1 AND pr_number = ?2",
+        params![repo_name_full, pr_number],
+    )?;
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR IGNORE INTO review_edges (repo_name, reviewer_login, author_login, pr_number)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+    )?;
+    for reviewer in reviewer_logins {
+        stmt.execute(params![repo_name_full, reviewer, author_login, pr_number])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Stored commits (local or API) not yet known to be on the default branch.
+/// Commits already on it are skipped, as merged history doesn't leave it.
+pub fn commits_off_default_branch(conn: &Connection, repo_name_full: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT sha FROM git_commits
+        WHERE repo_name = ?1 AND (on_default_branch IS NULL OR on_default_branch = 0)
+        UNION
+        SELECT sha FROM github_commits
+        WHERE repo_name = ?1 AND (on_default_branch IS NULL OR on_default_branch = 0)
+        ",
+    )?;
+    let shas = stmt
+        .query_map(params![repo_name_full], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(shas)
+}
+
+/// Records whether each commit is reachable from the default branch, in both
+/// commit tables.
+pub fn update_on_default_branch(
+    conn: &Connection,
+    repo_name_full: &str,
+    reachability: &[(String, bool)],
+) -> Result<()> {
+    let tx = write_transaction(conn)?;
+    {
+        let mut git = conn.prepare(
+            "UPDATE git_commits SET on_default_branch = ?1 WHERE repo_name = ?2 AND sha = ?3",
+        )?;
+        let mut github = conn.prepare(
+            "UPDATE github_commits SET on_default_branch = ?1 WHERE repo_name = ?2 AND sha = ?3",
+        )?;
+        for (sha, reachable) in reachability {
+            git.execute(params![reachable, repo_name_full, sha])?;
+            github.execute(params![reachable, repo_name_full, sha])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Per-commit enrichment passes whose completed commits are recorded in
+/// `enriched_commits`, so later runs can skip them.
+pub const ENRICH_STATUSES: &str = "statuses";
+pub const ENRICH_PATCH: &str = "patch";
+
+/// Commits of a repository the `enrichment` pass already completed.
+pub fn enriched_shas(
+    conn: &Connection,
+    repo_name_full: &str,
+    enrichment: &str,
+) -> Result<HashSet<String>> {
+    let mut stmt =
+        conn.prepare("SELECT sha FROM enriched_commits WHERE repo_name = ?1 AND enrichment = ?2")?;
+    let shas = stmt
+        .query_map(params![repo_name_full, enrichment], |row| row.get(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+    Ok(shas)
+}
+
+/// Records that the `enrichment` pass completed for a commit.
+pub fn mark_enriched(
+    conn: &Connection,
+    repo_name_full: &str,
+    sha: &str,
+    enrichment: &str,
+) -> Result<()> {
+    conn.execute(
+        r"
+        INSERT OR REPLACE INTO enriched_commits (repo_name, sha, enrichment, enriched_at)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+        params![repo_name_full, sha, enrichment, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Stores (or replaces) the patch of one commit.
+pub fn insert_commit_patch(
+    conn: &Connection,
+    repo_name_full: &str,
+    sha: &str,
+    source: &str,
+    patch: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO commit_patches (repo_name, sha, source, patch) VALUES (?1, ?2, ?3, ?4)",
+        params![repo_name_full, sha, source, patch],
+    )?;
+    Ok(())
+}
+
+/// Sets how a merged PR was integrated, as found by the integration pass.
+pub fn update_pr_integration_type(
+    conn: &Connection,
+    repo_name_full: &str,
+    number: i64,
+    integration_type: &str,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE github_pull_requests SET integration_type = ?1 WHERE repo_name = ?2 AND number = ?3",
+        params![integration_type, repo_name_full, number],
+    )?;
+    Ok(())
+}
+
+/// Numbers of a repository's PRs the integration pass already classified.
+pub fn classified_pr_numbers(conn: &Connection, repo_name_full: &str) -> Result<HashSet<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT number FROM github_pull_requests WHERE repo_name = ?1 AND integration_type IS NOT NULL",
+    )?;
+    let numbers = stmt
+        .query_map(params![repo_name_full], |row| row.get(0))?
+        .collect::<rusqlite::Result<HashSet<i64>>>()?;
+    Ok(numbers)
+}
+
+/// Whether an already stored commit is a merge, and its message, from the
+/// local history or else the API commits. `None` when neither has the commit
+/// with both known.
+pub fn stored_commit_shape(
+    conn: &Connection,
+    repo_name_full: &str,
+    sha: &str,
+) -> Result<Option<(bool, String)>> {
+    let shape = conn
+        .query_row(
+            r"
+            SELECT is_merge, message FROM git_commits
+            WHERE repo_name = ?1 AND sha = ?2 AND is_merge IS NOT NULL AND message IS NOT NULL
+            UNION ALL
+            SELECT is_merge, message FROM github_commits
+            WHERE repo_name = ?1 AND sha = ?2 AND is_merge IS NOT NULL AND message IS NOT NULL
+            LIMIT 1
+            ",
+            params![repo_name_full, sha],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(shape)
+}
+
+/// Sets who closed an issue, as found by the issue-detail enrichment pass.
+pub fn update_issue_closed_by(
+    conn: &Connection,
+    repo_name_full: &str,
+    number: i64,
+    closed_by_login: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE github_issues SET closed_by_login = ?1 WHERE repo_name = ?2 AND number = ?3",
+        params![closed_by_login, repo_name_full, number],
+    )?;
+    Ok(())
+}
+
+/// Inserts or replaces per-PR review thread resolution counts.
+pub fn insert_review_thread_counts(
+    conn: &Connection,
+    counts: &[ReviewThreadCounts],
+    repo_name_full: &str,
+    batch_size: usize,
+) -> Result<()> {
+    log::info!(
+        "Inserting review thread counts for {} PRs of repo '{}' into database...",
+        counts.len(),
+        repo_name_full
+    );
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO pr_review_threads (repo_name, pr_number, resolved, unresolved)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+    )?;
+
+    insert_in_batches(conn, counts, batch_size, |c| {
+        stmt.execute(params![
+            repo_name_full,
+            c.pr_number,
+            c.resolved,
+            c.unresolved
+        ])?;
+        Ok(())
+    })?;
+
+    log::info!(
+        "Successfully inserted review thread counts for {}",
+        repo_name_full
+    );
+    Ok(())
+}
+
+/// The filters that limited what a fetch run loaded: --label, --author and
+/// --only. Empty means unfiltered.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunFilters<'a> {
+    pub labels: &'a [String],
+    pub authors: &'a [String],
+    pub only: &'a [Entity],
+}
+
+impl RunFilters<'_> {
+    // Each filter as stored in `runs`: sorted and comma-separated, NULL when unset
+    fn columns(&self) -> [Option<String>; 3] {
+        let join = |mut values: Vec<String>| {
+            values.sort();
+            values.dedup();
+            (!values.is_empty()).then(|| values.join(","))
+        };
+        let only = self
+            .only
+            .iter()
+            .filter_map(|entity| entity.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        [
+            join(self.labels.to_vec()),
+            join(self.authors.to_vec()),
+            join(only),
+        ]
+    }
+}
+
+/// Records a completed fetch run, the filters it ran with and how each of its
+/// repositories fared.
+pub fn record_run(conn: &Connection, summary: &RunSummary, filters: &RunFilters) -> Result<()> {
+    let status = if summary.failed_repos() == 0 {
+        "ok"
+    } else {
+        "failed"
+    };
+    let started_at = summary.started_at.to_rfc3339();
+    let [labels, authors, only] = filters.columns();
+    let tx = write_transaction(conn)?;
+    conn.execute(
+        r"
+        INSERT OR REPLACE INTO runs (
+            started_at, finished_at, status, label_filter, author_filter, only_filter
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ",
+        params![
+            started_at,
+            summary.finished_at.map(|dt| dt.to_rfc3339()),
+            status,
+            labels,
+            authors,
+            only
+        ],
+    )?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR REPLACE INTO run_repos (started_at, repo_name, status) VALUES (?1, ?2, ?3)",
+    )?;
+    for repo in &summary.repos {
+        let status = if repo.error.is_some() {
+            "failed"
+        } else if repo.skipped.is_some() {
+            "skipped"
+        } else if repo.truncated {
+            "truncated"
+        } else {
+            "ok"
+        };
+        stmt.execute(params![started_at, repo.repo_name, status])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Completion time of the most recent run that loaded a repository
+/// completely with `filters` or fewer, if any: a run limited to other labels,
+/// authors or entities didn't load everything this one asks for. Runs
+/// recorded before per-repository outcomes were kept don't count.
+pub fn last_successful_run_finished_at(
+    conn: &Connection,
+    repo_name_full: &str,
+    filters: &RunFilters,
+) -> Result<Option<DateTime<Utc>>> {
+    let [labels, authors, only] = filters.columns();
+    let finished_at: Option<String> = conn
+        .query_row(
+            r"
+            SELECT r.finished_at
+            FROM runs r
+            JOIN run_repos rr ON rr.started_at = r.started_at
+            WHERE rr.repo_name = ?1 AND rr.status = 'ok' AND r.finished_at IS NOT NULL
+              AND (r.label_filter IS NULL OR r.label_filter = ?2)
+              AND (r.author_filter IS NULL OR r.author_filter = ?3)
+              AND (r.only_filter IS NULL OR r.only_filter = ?4)
+            ORDER BY r.finished_at DESC
+            LIMIT 1
+            ",
+            params![repo_name_full, labels, authors, only],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(finished_at
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc)))
+}
+
+/// Committer timestamp of the oldest API commit stored for a repository, or
+/// `None` when it has none. Independent of `--commit-date`, so backfill
+/// windows don't move when the choice changes.
+pub fn earliest_commit_timestamp(
+    conn: &Connection,
+    repo_name_full: &str,
+) -> Result<Option<DateTime<Utc>>> {
+    let earliest: Option<String> = conn.query_row(
+        "SELECT MIN(COALESCE(committer_date, commit_timestamp)) FROM github_commits WHERE repo_name = ?1",
+        params![repo_name_full],
+        |row| row.get(0),
+    )?;
+    Ok(earliest
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc)))
+}
+
+/// Appends a snapshot of the repository's headline counts. Earlier snapshots
+/// are kept so repeated runs build up a history.
+pub fn insert_repo_snapshot(
+    conn: &Connection,
+    info: &RepoInfo,
+    repo_name_full: &str,
+    fetched_at: &DateTime<Utc>,
+) -> Result<()> {
+    conn.execute(
+        r"
+        INSERT OR REPLACE INTO repo_snapshots (
+            repo_name, fetched_at, stargazers_count, watchers_count,
+            forks_count, open_issues_count, pushed_at,
+            license_key, license_name, license_spdx_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+        ",
+        params![
+            repo_name_full,
+            fetched_at.to_rfc3339(),
+            info.stargazers_count,
+            info.watchers_count,
+            info.forks_count,
+            info.open_issues_count,
+            info.pushed_at.to_rfc3339(),
+            info.license.as_ref().map(|l| &l.key),
+            info.license.as_ref().map(|l| &l.name),
+            info.license.as_ref().and_then(|l| l.spdx_id.as_ref())
+        ],
+    )?;
+    log::info!("Recorded repository snapshot for {}", repo_name_full);
+    Ok(())
+}
+
+/// Records the bytes per language of a repository as of `fetched_at`.
+/// Earlier snapshots are kept.
+pub fn insert_repo_languages(
+    conn: &Connection,
+    languages: &BTreeMap<String, i64>,
+    repo_name_full: &str,
+    fetched_at: &DateTime<Utc>,
+    batch_size: usize,
+) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO repo_languages (repo_name, fetched_at, language, bytes)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+    )?;
+    let fetched_at = fetched_at.to_rfc3339();
+    let rows: Vec<_> = languages.iter().collect();
+    insert_in_batches(conn, &rows, batch_size, |(language, bytes)| {
+        stmt.execute(params![repo_name_full, fetched_at, language, bytes])?;
+        Ok(())
+    })?;
+    log::info!(
+        "Recorded {} languages for {}",
+        languages.len(),
+        repo_name_full
+    );
+    Ok(())
+}
+
+/// `pushed_at` of a repository as of its last complete commit and PR fetch,
+/// if the fetches since then cover the window from `since`; a request for
+/// older data can't be answered from them.
+pub fn last_seen_pushed_at(
+    conn: &Connection,
+    repo_name_full: &str,
+    since: &DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>> {
+    let pushed_at: Option<String> = conn
+        .query_row(
+            r"
+            SELECT pushed_at FROM repo_push_state
+            WHERE repo_name = ?1 AND fetched_since <= ?2
+            ",
+            params![repo_name_full, since.to_rfc3339()],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(pushed_at
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc)))
+}
+
+/// Remembers `pushed_at` after a complete commit and PR fetch from `since`, so
+/// the next run can tell whether the repository has changed since. A fetch
+/// that starts before the previous one was recorded continues its window, so
+/// the covered window only grows; one that starts later leaves a gap and
+/// replaces it.
+pub fn record_pushed_at(
+    conn: &Connection,
+    repo_name_full: &str,
+    pushed_at: &DateTime<Utc>,
+    since: &DateTime<Utc>,
+) -> Result<()> {
+    conn.execute(
+        r"
+        INSERT INTO repo_push_state (repo_name, pushed_at, recorded_at, fetched_since)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (repo_name) DO UPDATE SET
+            pushed_at = excluded.pushed_at,
+            recorded_at = excluded.recorded_at,
+            fetched_since = CASE
+                WHEN excluded.fetched_since <= recorded_at
+                    THEN MIN(COALESCE(fetched_since, excluded.fetched_since), excluded.fetched_since)
+                ELSE excluded.fetched_since
+            END
+        ",
+        params![
+            repo_name_full,
+            pushed_at.to_rfc3339(),
+            Utc::now().to_rfc3339(),
+            since.to_rfc3339()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Stores the statuses and check runs of one commit.
+pub fn insert_commit_statuses(
+    conn: &Connection,
+    repo_name_full: &str,
+    sha: &str,
+    statuses: &[GitHubStatus],
+    check_runs: &[GitHubCheckRun],
+) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO commit_statuses (repo_name, sha, source, context, state, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ",
+    )?;
+    let tx = write_transaction(conn)?;
+    for status in statuses {
+        stmt.execute(params![
+            repo_name_full,
+            sha,
+            "status",
+            status.context,
+            status.state,
+            status.created_at.map(|dt| dt.to_rfc3339())
+        ])?;
+    }
+    for run in check_runs {
+        stmt.execute(params![
+            repo_name_full,
+            sha,
+            "check_run",
+            run.name,
+            run.conclusion.as_deref().unwrap_or(&run.status),
+            run.started_at.map(|dt| dt.to_rfc3339())
+        ])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Inserts or replaces GitHub Contributor data into the database.
+pub fn insert_github_contributors(
+    conn: &Connection,
+    contributors: &[GitHubContributor],
+    repo_name_full: &str,
+    batch_size: usize,
+) -> Result<()> {
+    log::info!(
+        "Inserting {} contributors for repo '{}' into database...",
+        contributors.len(),
+        repo_name_full
+    );
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO github_contributors (
+            id, repo_name, login, contributions, contributor_type
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+    )?;
+
+    insert_in_batches(conn, contributors, batch_size, |contributor| {
+        stmt.execute(params![
+            contributor.id,
+            repo_name_full,
+            contributor.login,
+            contributor.contributions,
+            contributor.contributor_type
+        ])?;
+        Ok(())
+    })?;
+
+    log::info!("Successfully inserted contributors for {}", repo_name_full);
+    Ok(())
+}
+
+pub fn insert_repo_events(
+    conn: &Connection,
+    events: &[GitHubEvent],
+    repo_name_full: &str,
+    batch_size: usize,
+) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO repo_events (
+            repo_name, id, event_type, actor_login, action, number, ref_name, created_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        ",
+    )?;
+    insert_in_batches(conn, events, batch_size, |event| {
+        stmt.execute(params![
+            repo_name_full,
+            event.id,
+            event.kind.event_type(),
+            event.actor.as_ref().map(|a| a.login.as_str()),
+            event.kind.action(),
+            event.kind.number(),
+            event.kind.ref_name(),
+            event.created_at.to_rfc3339()
+        ])?;
+        Ok(())
+    })?;
+    log::info!("Stored {} events for {}", events.len(), repo_name_full);
+    Ok(())
+}
+
+/// Replaces the stored collaborators of a repository, so users whose access
+/// was revoked don't linger.
+pub fn replace_collaborators(
+    conn: &Connection,
+    collaborators: &[GitHubCollaborator],
+    repo_name_full: &str,
+    fetched_at: &DateTime<Utc>,
+) -> Result<()> {
+    let tx = write_transaction(conn)?;
+    conn.execute(
+        "DELETE FROM collaborators WHERE repo_name = ?1",
+        params![repo_name_full],
+    )?;
+    {
+        let mut stmt = conn.prepare_cached(
+            r"
+            INSERT OR REPLACE INTO collaborators (
+                repo_name, login, id, admin, maintain, push, triage, pull, role_name, fetched_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ",
+        )?;
+        for collaborator in collaborators {
+            let permissions = collaborator.permissions.clone().unwrap_or_default();
+            stmt.execute(params![
+                repo_name_full,
+                collaborator.login,
+                collaborator.id,
+                permissions.admin,
+                permissions.maintain,
+                permissions.push,
+                permissions.triage,
+                permissions.pull,
+                collaborator.role_name,
+                fetched_at.to_rfc3339()
+            ])?;
+        }
+    }
+    tx.commit()?;
+    log::info!(
+        "Stored {} collaborators for {}",
+        collaborators.len(),
+        repo_name_full
+    );
+    Ok(())
+}
+
+/// Inserts or replaces locally extracted commits into `git_commits`.
+pub fn insert_git_commits(
+    conn: &Connection,
+    commits: &[LocalCommit],
+    repo_name_full: &str,
+    batch_size: usize,
+    classifier: &CommitClassifier,
+    message_mode: CommitMessage,
+    date_mode: CommitDate,
+) -> Result<()> {
+    for chunk in commits.chunks(batch_size.max(1)) {
+        let tx = write_transaction(conn)?;
+        insert_git_commit_rows(
+            conn,
+            chunk,
+            repo_name_full,
+            classifier,
+            message_mode,
+            date_mode,
+        )?;
+        tx.commit()?;
+    }
+    restamp_commits(conn, "git_commits", repo_name_full, date_mode)
+}
+
+/// Inserts a batch produced by the git2 extractor and records the last SHA
+/// of the batch as the resume point, all in one transaction.
+pub fn save_extracted_commits(
+    conn: &Connection,
+    commits: &[LocalCommit],
+    repo_name_full: &str,
+    classifier: &CommitClassifier,
+    message_mode: CommitMessage,
+    date_mode: CommitDate,
+) -> Result<()> {
+    let tx = write_transaction(conn)?;
+    insert_git_commit_rows(
+        conn,
+        commits,
+        repo_name_full,
+        classifier,
+        message_mode,
+        date_mode,
+    )?;
+    restamp_commits(conn, "git_commits", repo_name_full, date_mode)?;
+    insert_commit_subsystem_rows(conn, commits, repo_name_full, classifier)?;
+
+    if let Some(last) = commits.last() {
+        conn.execute(
+            r"
+            INSERT OR REPLACE INTO git_extraction_state (repo_name, last_sha, updated_at)
+            VALUES (?1, ?2, ?3)
+            ",
+            params![repo_name_full, last.sha, chrono::Utc::now().to_rfc3339()],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+// Tags commits with the subsystems their changed paths fall under; callers
+// own the transaction.
+fn insert_commit_subsystem_rows(
+    conn: &Connection,
+    commits: &[LocalCommit],
+    repo_name_full: &str,
+    classifier: &CommitClassifier,
+) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR IGNORE INTO commit_subsystems (repo_name, sha, subsystem)
+        VALUES (?1, ?2, ?3)
+        ",
+    )?;
+    for commit in commits {
+        for subsystem in classifier.subsystems(&commit.changed_paths) {
+            stmt.execute(params![repo_name_full, commit.sha, subsystem])?;
+        }
+    }
+    Ok(())
+}
+
+// Stores the `Co-authored-by` trailers of a commit message.
+fn insert_coauthors(
+    conn: &Connection,
+    repo_name_full: &str,
+    sha: &str,
+    message: Option<&str>,
+) -> Result<()> {
+    let Some(message) = message else {
+        return Ok(());
+    };
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO commit_coauthors (repo_name, commit_sha, name, email)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+    )?;
+    for coauthor in parse_coauthors(message) {
+        stmt.execute(params![repo_name_full, sha, coauthor.name, coauthor.email])?;
+    }
+    Ok(())
+}
+
+// Stores the trailers (Signed-off-by, Reviewed-by, ...) of a commit message.
+fn insert_trailers(
+    conn: &Connection,
+    repo_name_full: &str,
+    sha: &str,
+    message: Option<&str>,
+) -> Result<()> {
+    let Some(message) = message else {
+        return Ok(());
+    };
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR IGNORE INTO commit_trailers (repo_name, commit_sha, key, value)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+    )?;
+    for trailer in parse_trailers(message) {
+        stmt.execute(params![repo_name_full, sha, trailer.key, trailer.value])?;
+    }
+    Ok(())
+}
+
+// Stores the files a local commit renamed.
+fn insert_file_renames(
+    conn: &Connection,
+    repo_name_full: &str,
+    commit: &LocalCommit,
+) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO file_renames (repo_name, commit_sha, old_path, new_path)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+    )?;
+    for (old_path, new_path) in &commit.renames {
+        stmt.execute(params![repo_name_full, commit.sha, old_path, new_path])?;
+    }
+    Ok(())
+}
+
+// Inserts `git_commits` rows; callers own the transaction. Reverts and
+// backports are tagged from the full message, like co-authors and trailers,
+// before `message_mode` trims it.
+fn insert_git_commit_rows(
+    conn: &Connection,
+    commits: &[LocalCommit],
+    repo_name_full: &str,
+    classifier: &CommitClassifier,
+    message_mode: CommitMessage,
+    date_mode: CommitDate,
+) -> Result<()> {
+    log::debug!(
+        "Inserting {} local commits for repo '{}' into database...",
+        commits.len(),
+        repo_name_full
+    );
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO git_commits (
+            sha, repo_name, author_name, author_email, commit_timestamp, message,
+            insertions, deletions, is_merge, author_date, committer_date, is_revert, is_backport
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+        ",
+    )?;
+
+    for commit in commits {
+        insert_coauthors(conn, repo_name_full, &commit.sha, commit.message.as_deref())?;
+        insert_trailers(conn, repo_name_full, &commit.sha, commit.message.as_deref())?;
+        insert_file_renames(conn, repo_name_full, commit)?;
+        let commit_timestamp = date_mode
+            .pick(commit.author_timestamp, commit.commit_timestamp)
+            .map(|dt| dt.to_rfc3339());
+
+        stmt.execute(params![
+            commit.sha,
+            repo_name_full,
+            commit.author_name,
+            commit.author_email,
+            commit_timestamp,
+            message_mode.apply(commit.message.as_deref()),
+            commit.insertions,
+            commit.deletions,
+            commit.is_merge,
+            commit.author_timestamp.map(|dt| dt.to_rfc3339()),
+            commit.commit_timestamp.map(|dt| dt.to_rfc3339()),
+            commit.message.as_deref().map(|m| classifier.is_revert(m)),
+            commit.message.as_deref().map(|m| classifier.is_backport(m))
+        ])?;
+    }
+    Ok(())
+}
+
+/// Replaces the stored release tags of a repository.
+pub fn replace_git_tags(conn: &Connection, repo_name_full: &str, tags: &[GitTag]) -> Result<()> {
+    let tx = write_transaction(conn)?;
+    conn.execute(
+        "DELETE FROM git_tags WHERE repo_name = ?1",
+        params![repo_name_full],
+    )?;
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO git_tags (repo_name, tag_name, commit_sha, committed_at)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+    )?;
+    for tag in tags {
+        stmt.execute(params![
+            repo_name_full,
+            tag.name,
+            tag.sha,
+            tag.committed_at.map(|dt| dt.to_rfc3339())
+        ])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// The commits (SHA, subject) of a release since the release before it.
+pub struct TagChangelog<'a> {
+    pub from_tag: &'a str,
+    pub to_tag: &'a str,
+    pub commits: Vec<(String, String)>,
+}
+
+/// Replaces the stored changelogs of a repository, so pairs no longer derived
+/// from its tags don't linger.
+pub fn replace_tag_changelogs(
+    conn: &Connection,
+    repo_name_full: &str,
+    changelogs: &[TagChangelog],
+) -> Result<()> {
+    let tx = write_transaction(conn)?;
+    conn.execute(
+        "DELETE FROM tag_changelog WHERE repo_name = ?1",
+        params![repo_name_full],
+    )?;
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO tag_changelog (repo_name, from_tag, to_tag, commit_sha, subject)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+    )?;
+    for changelog in changelogs {
+        for (sha, subject) in &changelog.commits {
+            stmt.execute(params![
+                repo_name_full,
+                changelog.from_tag,
+                changelog.to_tag,
+                sha,
+                subject
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Records which commits belong to a `base...head` range, replacing any
+/// previous membership stored for the same range, repo and source.
+pub fn insert_commit_range(
+    conn: &Connection,
+    repo_name_full: &str,
+    range_spec: &str,
+    source: &str,
+    shas: &[&str],
+) -> Result<()> {
+    let tx = write_transaction(conn)?;
+    conn.execute(
+        "DELETE FROM commit_ranges WHERE repo_name = ?1 AND range_spec = ?2 AND source = ?3",
+        params![repo_name_full, range_spec, source],
+    )?;
+    let mut stmt = conn.prepare_cached(
+        r"
+        INSERT OR REPLACE INTO commit_ranges (repo_name, range_spec, source, sha)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+    )?;
+    for sha in shas {
+        stmt.execute(params![repo_name_full, range_spec, source, sha])?;
+    }
+    tx.commit()?;
+    log::info!(
+        "Stored {} commits for range {} of {} ({})",
+        shas.len(),
+        range_spec,
+        repo_name_full,
+        source
+    );
+    Ok(())
+}
+
+/// Recomputes the `is_revert`/`is_backport` flags of a repo's commits in both
+/// `github_commits` and `git_commits` from their stored messages. Commits
+/// stored without a message keep their flags. Only run it when full messages
+/// are stored: a subject alone may lack the markers the insert tagged from the
+/// body. Returns the number of rows updated.
+pub fn tag_commit_messages(
+    conn: &Connection,
+    classifier: &CommitClassifier,
+    repo_name_full: &str,
+) -> Result<usize> {
+    let mut updated = 0;
+    let tx = write_transaction(conn)?;
+    for table in ["github_commits", "git_commits"] {
+        let mut select = conn.prepare(&format!(
+            "SELECT sha, message FROM {} WHERE repo_name = ?1 AND message IS NOT NULL",
+            table
+        ))?;
+        let mut update = conn.prepare(&format!(
+            "UPDATE {} SET is_revert = ?1, is_backport = ?2 WHERE sha = ?3 AND repo_name = ?4",
+            table
+        ))?;
+        let rows = select
+            .query_map(params![repo_name_full], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (sha, message) in rows {
+            updated += update.execute(params![
+                classifier.is_revert(&message),
+                classifier.is_backport(&message),
+                sha,
+                repo_name_full
+            ])?;
+        }
+    }
+    tx.commit()?;
+    log::info!(
+        "Tagged reverts/backports on {} commits for {}",
+        updated,
+        repo_name_full
+    );
+    Ok(updated)
+}
+
+/// Recomputes the `is_maintainer` flag of a repo's commits, PRs and issues
+/// from their author logins, compared case-insensitively with `maintainers`.
+/// Items without an author login are left NULL. Returns the number of rows
+/// updated.
+pub fn tag_maintainers(
+    conn: &Connection,
+    repo_name_full: &str,
+    maintainers: &BTreeSet<String>,
+) -> Result<usize> {
+    let mut updated = 0;
+    let tx = write_transaction(conn)?;
+    conn.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS maintainer_logins (login TEXT PRIMARY KEY);
+         DELETE FROM temp.maintainer_logins;",
+    )?;
+    {
+        let mut insert =
+            conn.prepare("INSERT OR IGNORE INTO temp.maintainer_logins (login) VALUES (?1)")?;
+        for login in maintainers {
+            insert.execute(params![login.to_lowercase()])?;
+        }
+    }
+    for (table, login_column) in [
+        ("github_commits", "author_login"),
+        ("github_pull_requests", "user_login"),
+        ("github_issues", "user_login"),
+    ] {
+        updated += conn.execute(
+            &format!(
+                "UPDATE {0} SET is_maintainer = CASE
+                     WHEN {1} IS NULL THEN NULL
+                     ELSE LOWER({1}) IN (SELECT login FROM temp.maintainer_logins)
+                 END
+                 WHERE repo_name = ?1",
+                table, login_column
+            ),
+            params![repo_name_full],
+        )?;
+    }
+    tx.commit()?;
+    log::info!(
+        "Tagged maintainer authorship on {} items for {}",
+        updated,
+        repo_name_full
+    );
+    Ok(updated)
+}
+
+/// SHAs of a repository's local commits stored without line counts, i.e.
+/// extracted from a partial clone or before the counts were recorded.
+pub fn commits_missing_churn(conn: &Connection, repo_name_full: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT sha FROM git_commits WHERE repo_name = ?1 AND insertions IS NULL ORDER BY sha",
+    )?;
+    let shas = stmt
+        .query_map(params![repo_name_full], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(shas)
+}
+
+/// Fills in `insertions`/`deletions` for already stored local commits.
+pub fn update_commit_churn(
+    conn: &Connection,
+    repo_name_full: &str,
+    churn: &[(String, i64, i64)],
+) -> Result<usize> {
+    let tx = write_transaction(conn)?;
+    let mut updated = 0;
+    {
+        let mut stmt = tx.prepare(
+            "UPDATE git_commits SET insertions = ?3, deletions = ?4 WHERE repo_name = ?1 AND sha = ?2",
+        )?;
+        for (sha, insertions, deletions) in churn {
+            updated += stmt.execute(params![repo_name_full, sha, insertions, deletions])?;
+        }
+    }
+    tx.commit()?;
+    Ok(updated)
+}
+
+/// Returns the last commit SHA persisted by the git2 extractor for a repo.
+pub fn last_extracted_sha(conn: &Connection, repo_name_full: &str) -> Result<Option<String>> {
+    let sha = conn
+        .query_row(
+            "SELECT last_sha FROM git_extraction_state WHERE repo_name = ?1",
+            params![repo_name_full],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(sha)
+}
+
+// TODO: Add functions to insert fetched data into the tables using rusqlite prepared statements
+// e.g., insert_github_contributors(conn: &Connection, contributors: &[GitHubContributor], repo_name: &str) -> Result<()>
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classifier() -> CommitClassifier {
+        CommitClassifier::new("^Revert", "(?i)backport", &[]).unwrap()
+    }
+
+    #[test]
+    fn failed_writes_roll_back_and_leave_the_connection_usable() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        // Fails after its transaction has begun
+        conn.execute_batch("DROP TABLE collaborators;").unwrap();
+        assert!(replace_collaborators(&conn, &[], "x/y", &Utc::now()).is_err());
+        assert!(conn.is_autocommit());
+
+        let tag = GitTag {
+            name: "v1.0".to_string(),
+            sha: "abc".to_string(),
+            committed_at: None,
+        };
+        replace_git_tags(&conn, "x/y", &[tag]).unwrap();
+        let tags: i64 = conn
+            .query_row("SELECT COUNT(*) FROM git_tags", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tags, 1);
+    }
+
+    #[test]
+    fn sha_keyed_commit_tables_are_migrated_to_per_repository_keys() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r"
+            CREATE TABLE git_commits (
+                sha TEXT PRIMARY KEY,
+                repo_name TEXT,
+                author_name TEXT,
+                author_email TEXT,
+                commit_timestamp TEXT,
+                message TEXT
+            );
+            INSERT INTO git_commits (sha, repo_name, message) VALUES ('abc', 'x/y', 'kept');
+            ",
+        )
+        .unwrap();
+        create_tables(&conn).unwrap();
+
+        let message: String = conn
+            .query_row(
+                "SELECT message FROM git_commits WHERE sha = 'abc'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(message, "kept");
+        // The same commit can now be stored for the other repository
+        conn.execute(
+            "INSERT INTO git_commits (sha, repo_name) VALUES ('abc', 'x/z')",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn writes_wait_for_a_lock_released_within_the_busy_timeout() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("busy.db");
+        let path = path.to_str().unwrap();
+        let holder = establish_connection(path, Duration::ZERO).unwrap();
+        create_tables(&holder).unwrap();
+        holder.execute_batch("BEGIN EXCLUSIVE;").unwrap();
+
+        let writer = establish_connection(path, Duration::from_secs(10)).unwrap();
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            holder.execute_batch("COMMIT;").unwrap();
+        });
+        writer
+            .execute(
+                "INSERT INTO git_commits (sha, repo_name) VALUES ('a1', 'x/y')",
+                [],
+            )
+            .unwrap();
+        release.join().unwrap();
+
+        // Without a timeout the same lock fails immediately
+        let holder = establish_connection(path, Duration::ZERO).unwrap();
+        holder.execute_batch("BEGIN EXCLUSIVE;").unwrap();
+        let impatient = establish_connection(path, Duration::ZERO).unwrap();
+        assert!(impatient
+            .execute(
+                "INSERT INTO git_commits (sha, repo_name) VALUES ('a2', 'x/y')",
+                []
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn prune_backups_keeps_the_newest_of_this_database() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("repo.db");
+        let db_path = db_path.to_str().unwrap();
+        let names = [
+            "repo.db.20240101T000000Z.bak",
+            "repo.db.20240102T000000Z.bak",
+            "repo.db.20240103T000000Z.bak",
+            "other.db.20230101T000000Z.bak",
+            "repo.db.notes.bak",
+        ];
+        for name in names {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+
+        let deleted = prune_backups(db_path, 2).unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert!(deleted[0].ends_with("repo.db.20240101T000000Z.bak"));
+        let mut left: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "other.db.20230101T000000Z.bak",
+                "repo.db.20240102T000000Z.bak",
+                "repo.db.20240103T000000Z.bak",
+                "repo.db.notes.bak",
+            ]
+        );
+        assert!(prune_backups(db_path, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn prune_history_keeps_recent_rows_and_fetched_data() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn.execute_batch(
+            r"
+            INSERT INTO repo_events (repo_name, id, created_at) VALUES
+                ('x/y', '1', '2023-12-31T23:59:59Z'),
+                ('x/y', '2', '2024-01-01T00:00:00Z');
+            INSERT INTO repo_snapshots (repo_name, fetched_at) VALUES
+                ('x/y', '2023-06-01T00:00:00+00:00'),
+                ('x/y', '2024-06-01T00:00:00+00:00');
+            INSERT INTO runs (started_at) VALUES ('2023-06-01T00:00:00.123456+00:00');
+            INSERT INTO run_repos (started_at, repo_name)
+                VALUES ('2023-06-01T00:00:00.123456+00:00', 'x/y');
+            INSERT INTO git_commits (sha, repo_name, commit_timestamp)
+                VALUES ('a', 'x/y', '2020-01-01T00:00:00+00:00');
+            ",
+        )
+        .unwrap();
+        let before = "2024-01-01T00:00:00Z".parse().unwrap();
+        assert_eq!(prune_history(&conn, &before).unwrap(), 4);
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(count("repo_events"), 1);
+        assert_eq!(count("repo_snapshots"), 1);
+        assert_eq!(count("runs"), 0);
+        assert_eq!(count("git_commits"), 1);
+    }
+
+    #[test]
+    fn vacuum_reclaims_deleted_pages_in_wal_mode() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("vacuum.db");
+        let path = path.to_str().unwrap();
+        let conn = establish_connection(path, Duration::ZERO).unwrap();
+        create_tables(&conn).unwrap();
+        let _: String = conn
+            .query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))
+            .unwrap();
+        conn.execute_batch(
+            r"
+            WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000)
+            INSERT INTO git_commits (sha, repo_name, message)
+                SELECT 'sha' || i, 'x/y', printf('%.500c', 'm') FROM n;
+            DELETE FROM git_commits;
+            ",
+        )
+        .unwrap();
+
+        let (before, after) = vacuum_database(&conn, path).unwrap();
+        assert!(after < before / 4, "{} -> {}", before, after);
+        let wal = std::fs::metadata(format!("{}-wal", path)).unwrap();
+        assert_eq!(wal.len(), 0);
+    }
+
+    #[test]
+    fn earliest_commit_timestamp_is_per_repository() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        assert_eq!(earliest_commit_timestamp(&conn, "x/y").unwrap(), None);
+        conn.execute_batch(
+            r"
+            INSERT INTO github_commits (sha, repo_name, commit_timestamp) VALUES
+                ('a1', 'x/y', '2024-03-01T00:00:00+00:00'),
+                ('a2', 'x/y', '2023-11-15T12:00:00+00:00'),
+                ('a3', 'x/y', NULL),
+                ('b1', 'x/z', '2020-01-01T00:00:00+00:00');
+            ",
+        )
+        .unwrap();
+        let earliest = earliest_commit_timestamp(&conn, "x/y").unwrap().unwrap();
+        assert_eq!(earliest.to_rfc3339(), "2023-11-15T12:00:00+00:00");
+    }
+
+    #[test]
+    fn stores_api_and_html_urls_of_github_commits() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let commit: GitHubCommit = serde_json::from_value(serde_json::json!({
+            "sha": "abc123",
+            "commit": {
+                "author": null,
+                "committer": null,
+                "message": "test commit",
+                "url": "https://api.github.com/repos/bitcoin/bitcoin/git/commits/abc123",
+                "comment_count": 0,
+                "verification": null
+            },
+            "url": "https://api.github.com/repos/bitcoin/bitcoin/commits/abc123",
+            "html_url": "https://github.com/bitcoin/bitcoin/commit/abc123",
+            "comments_url": "https://api.github.com/repos/bitcoin/bitcoin/commits/abc123/comments",
+            "author": null,
+            "committer": null
+        }))
+        .unwrap();
+
+        insert_github_commits(
+            &conn,
+            &[commit],
+            "bitcoin/bitcoin",
+            10,
+            &classifier(),
+            CommitMessage::Full,
+            CommitDate::Committer,
+        )
+        .unwrap();
+
+        let (api_url, html_url): (String, String) = conn
+            .query_row(
+                "SELECT api_url, html_url FROM github_commits WHERE sha = 'abc123'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            api_url,
+            "https://api.github.com/repos/bitcoin/bitcoin/commits/abc123"
+        );
+        assert_eq!(html_url, "https://github.com/bitcoin/bitcoin/commit/abc123");
+    }
+
+    fn pull_request(merged_by: Option<&str>, mergeable: Option<bool>) -> GitHubPullRequest {
+        serde_json::from_value(serde_json::json!({
+            "id": 500,
+            "number": 5,
+            "html_url": "",
+            "state": "closed",
+            "title": "Refactor net",
+            "user": {"login": "alice", "id": 1},
+            "body": null,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-03T00:00:00Z",
+            "closed_at": "2024-01-02T00:00:00Z",
+            "merged_at": "2024-01-02T00:00:00Z",
+            "merge_commit_sha": "m5",
+            "head": null,
+            "base": null,
+            "merged": true,
+            "mergeable": mergeable,
+            "rebaseable": null,
+            "mergeable_state": null,
+            "merged_by": merged_by.map(|login| serde_json::json!({"login": login, "id": 2})),
+            "milestone": null,
+            "comments_url": "",
+            "review_comments_url": "",
+            "statuses_url": ""
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn list_fetches_keep_fields_only_single_pr_fetches_return() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        insert_github_pull_requests(&conn, &[pull_request(Some("bob"), Some(true))], "x/y", 10)
+            .unwrap();
+        conn.execute(
+            "UPDATE github_pull_requests SET first_review_at = '2024-01-01T06:00:00+00:00'",
+            [],
+        )
+        .unwrap();
+
+        // The list endpoint returns neither merged_by nor mergeability
+        insert_github_pull_requests(&conn, &[pull_request(None, None)], "x/y", 10).unwrap();
+        let row: (Option<String>, Option<bool>, Option<String>) = conn
+            .query_row(
+                "SELECT merged_by_login, mergeable, first_review_at FROM github_pull_requests",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            (
+                Some("bob".to_string()),
+                Some(true),
+                Some("2024-01-01T06:00:00+00:00".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn last_successful_run_is_per_repository() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let mut summary = RunSummary::new();
+        summary
+            .repos
+            .push(crate::summary::RepoSummary::new("x/core"));
+        summary.repos.push(crate::summary::RepoSummary {
+            error: Some("timed out".to_string()),
+            ..crate::summary::RepoSummary::new("x/knots")
+        });
+        summary.finish();
+        let unfiltered = RunFilters::default();
+        record_run(&conn, &summary, &unfiltered).unwrap();
+
+        let last_run = |repo| last_successful_run_finished_at(&conn, repo, &unfiltered);
+        assert!(last_run("x/core").unwrap().is_some());
+        assert_eq!(last_run("x/knots").unwrap(), None);
+        assert_eq!(last_run("x/other").unwrap(), None);
+    }
+
+    #[test]
+    fn last_successful_run_must_cover_the_requested_filters() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let record = |filters: &RunFilters| {
+            let mut summary = RunSummary::new();
+            summary.repos.push(crate::summary::RepoSummary::new("x/y"));
+            summary.finish();
+            record_run(&conn, &summary, filters).unwrap();
+        };
+        let last_run = |filters: &RunFilters| {
+            last_successful_run_finished_at(&conn, "x/y", filters)
+                .unwrap()
+                .is_some()
+        };
+        let labels = ["GUI".to_string(), "Wallet".to_string()];
+        let filtered = RunFilters {
+            labels: &labels,
+            only: &[Entity::Issues],
+            ..RunFilters::default()
+        };
+        record(&filtered);
+
+        assert!(last_run(&filtered));
+        // Label order doesn't matter
+        let reordered = ["Wallet".to_string(), "GUI".to_string()];
+        assert!(last_run(&RunFilters {
+            labels: &reordered,
+            ..filtered
+        }));
+        // A filtered run covers neither an unfiltered nor a differently filtered one
+        assert!(!last_run(&RunFilters::default()));
+        assert!(!last_run(&RunFilters {
+            only: &[Entity::PullRequests],
+            ..filtered
+        }));
+
+        // An unfiltered run covers any filters
+        record(&RunFilters::default());
+        assert!(last_run(&RunFilters::default()));
+    }
+
+    #[test]
+    fn pushed_at_only_counts_for_windows_the_fetches_covered() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let at = |s: &str| -> DateTime<Utc> { s.parse().unwrap() };
+        let pushed_at = at("2024-06-01T00:00:00Z");
+        record_pushed_at(&conn, "x/y", &pushed_at, &at("2024-01-01T00:00:00Z")).unwrap();
+
+        let seen = |since| last_seen_pushed_at(&conn, "x/y", &at(since)).unwrap();
+        assert_eq!(seen("2024-03-01T00:00:00Z"), Some(pushed_at));
+        assert_eq!(seen("2024-01-01T00:00:00Z"), Some(pushed_at));
+        // A wider --since needs the older history fetched first
+        assert_eq!(seen("2023-01-01T00:00:00Z"), None);
+
+        // A --since-last-run fetch overlaps the last one, so it continues the
+        // window instead of narrowing it
+        let since_last_run = Utc::now() - chrono::Duration::hours(1);
+        record_pushed_at(&conn, "x/y", &pushed_at, &since_last_run).unwrap();
+        assert_eq!(seen("2024-01-01T00:00:00Z"), Some(pushed_at));
+    }
+
+    #[test]
+    fn tag_changelogs_are_replaced_each_run() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let changelog = |from_tag, commits: &[&str]| TagChangelog {
+            from_tag,
+            to_tag: "v26.0",
+            commits: commits
+                .iter()
+                .map(|sha| (sha.to_string(), String::new()))
+                .collect(),
+        };
+        // An earlier run paired v26.0 with a point release of v25
+        replace_tag_changelogs(&conn, "x/y", &[changelog("v25.1", &["a", "b"])]).unwrap();
+        replace_tag_changelogs(&conn, "x/y", &[changelog("v25.0", &["a", "b", "c"])]).unwrap();
+
+        assert_eq!(
+            crate::queries::changelog_sizes(&conn, "x/y").unwrap(),
+            [("v26.0".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn refetched_prs_stay_classified() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let prs = [pull_request(None, None)];
+        insert_github_pull_requests(&conn, &prs, "x/y", 10).unwrap();
+        assert!(classified_pr_numbers(&conn, "x/y").unwrap().is_empty());
+        update_pr_integration_type(&conn, "x/y", 5, "squash").unwrap();
+
+        insert_github_pull_requests(&conn, &prs, "x/y", 10).unwrap();
+        assert_eq!(
+            classified_pr_numbers(&conn, "x/y").unwrap(),
+            HashSet::from([5])
+        );
+    }
+
+    #[test]
+    fn refetched_issues_keep_their_first_response() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let issue: GitHubIssue = serde_json::from_value(serde_json::json!({
+            "id": 900,
+            "number": 9,
+            "html_url": "",
+            "state": "open",
+            "state_reason": null,
+            "title": "Crash on startup",
+            "user": {"login": "alice", "id": 1},
+            "labels": [],
+            "assignee": null,
+            "assignees": [],
+            "locked": false,
+            "comments": 1,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z",
+            "closed_at": null,
+            "body": null,
+            "closed_by": null,
+            "milestone": null,
+            "pull_request": null
+        }))
+        .unwrap();
+        let issues = [issue];
+        insert_github_issues(&conn, &issues, "x/y", 10).unwrap();
+        let first_comment_at = "2024-01-01T01:00:00+00:00".parse().unwrap();
+        update_first_comment_at(&conn, "x/y", 9, false, Some(&first_comment_at)).unwrap();
+        derive_first_response(&conn, "x/y").unwrap();
+
+        insert_github_issues(&conn, &issues, "x/y", 10).unwrap();
+        let (seconds, is_pull_request): (Option<i64>, Option<bool>) = conn
+            .query_row(
+                "SELECT first_response_seconds, is_pull_request FROM github_issues WHERE number = 9",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(seconds, Some(3600));
+        assert_eq!(is_pull_request, Some(false));
+    }
+
+    #[test]
+    fn commit_date_mode_picks_the_stored_timestamp() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let commit = |sha: &str| -> GitHubCommit {
+            serde_json::from_value(serde_json::json!({
+                "sha": sha,
+                "commit": {
+                    "author": {"name": "a", "email": "a@x", "date": "2024-01-01T00:00:00Z"},
+                    "committer": {"name": "c", "email": "c@x", "date": "2024-02-01T00:00:00Z"},
+                    "message": "rebased",
+                    "url": "",
+                    "comment_count": 0,
+                    "verification": null
+                },
+                "url": "",
+                "html_url": "",
+                "comments_url": "",
+                "author": null,
+                "committer": null
+            }))
+            .unwrap()
+        };
+        insert_github_commits(
+            &conn,
+            &[commit("c1")],
+            "x/y",
+            10,
+            &classifier(),
+            CommitMessage::Full,
+            CommitDate::Committer,
+        )
+        .unwrap();
+        insert_github_commits(
+            &conn,
+            &[commit("a1")],
+            "x/y",
+            10,
+            &classifier(),
+            CommitMessage::Full,
+            CommitDate::Author,
+        )
+        .unwrap();
+
+        let stored = |sha: &str| -> (String, String, String) {
+            conn.query_row(
+                "SELECT commit_timestamp, author_date, committer_date FROM github_commits WHERE sha = ?1",
+                params![sha],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap()
+        };
+        // Switching modes restamps the commits stored before
+        assert_eq!(stored("c1").0, "2024-01-01T00:00:00+00:00");
+        assert_eq!(
+            stored("a1"),
+            (
+                "2024-01-01T00:00:00+00:00".to_string(),
+                "2024-01-01T00:00:00+00:00".to_string(),
+                "2024-02-01T00:00:00+00:00".to_string()
+            )
+        );
+        // Backfill keeps using the committer date whichever was picked
+        let earliest = earliest_commit_timestamp(&conn, "x/y").unwrap().unwrap();
+        assert_eq!(earliest.to_rfc3339(), "2024-02-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn enrichment_markers_are_per_repository_and_pass() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        mark_enriched(&conn, "x/y", "abc", ENRICH_STATUSES).unwrap();
+        mark_enriched(&conn, "x/y", "abc", ENRICH_STATUSES).unwrap();
+        mark_enriched(&conn, "x/y", "def", ENRICH_PATCH).unwrap();
+        assert_eq!(
+            enriched_shas(&conn, "x/y", ENRICH_STATUSES).unwrap(),
+            HashSet::from(["abc".to_string()])
+        );
+        assert!(enriched_shas(&conn, "a/b", ENRICH_STATUSES)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn commit_message_mode_trims_but_keeps_coauthors_trailers_and_tags() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let commit = |sha: &str| LocalCommit {
+            sha: sha.to_string(),
+            author_name: None,
+            author_email: None,
+            commit_timestamp: None,
+            author_timestamp: None,
+            message: Some(
+                "Add feature\n\nBackport of #123\n\nCo-authored-by: Carol <carol@example.com>\n"
+                    .to_string(),
+            ),
+            changed_paths: Vec::new(),
+            renames: Vec::new(),
+            insertions: None,
+            deletions: None,
+            is_merge: false,
+        };
+        insert_git_commits(
+            &conn,
+            &[commit("s1")],
+            "x/y",
+            10,
+            &classifier(),
+            CommitMessage::Subject,
+            CommitDate::Committer,
+        )
+        .unwrap();
+        insert_git_commits(
+            &conn,
+            &[commit("n1")],
+            "x/y",
+            10,
+            &classifier(),
+            CommitMessage::None,
+            CommitDate::Committer,
+        )
+        .unwrap();
+
+        let message = |sha: &str| -> Option<String> {
+            conn.query_row(
+                "SELECT message FROM git_commits WHERE sha = ?1",
+                params![sha],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(message("s1").as_deref(), Some("Add feature"));
+        assert_eq!(message("n1"), None);
+        let backports: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM git_commits WHERE is_backport = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(backports, 2);
+        let coauthors: i64 = conn
+            .query_row("SELECT COUNT(*) FROM commit_coauthors", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(coauthors, 2);
+        let trailers: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM commit_trailers WHERE key = 'Co-authored-by'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(trailers, 2);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::errors::Result;
+use crate::export::table_names;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How one table (or derived set, such as merged PRs) of one repository
+/// differs between two databases. Keys are primary keys without `repo_name`,
+/// joined with `/`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableDiff {
+    pub table: String,
+    pub repo_name: String,
+    pub count_a: usize,
+    pub count_b: usize,
+    /// Keys present only in database B
+    pub added: Vec<String>,
+    /// Keys present only in database A
+    pub removed: Vec<String>,
+}
+
+// Sets derived from row state rather than presence: an existing PR that got
+// merged or an issue that got closed between snapshots shows up as added.
+const DERIVED_SETS: &[(&str, &str, &str)] = &[
+    (
+        "merged PRs",
+        "github_pull_requests",
+        "SELECT repo_name, number FROM github_pull_requests WHERE merged_at IS NOT NULL",
+    ),
+    (
+        "closed issues",
+        "github_issues",
+        "SELECT repo_name, number FROM github_issues WHERE state = 'closed'",
+    ),
+];
+
+type KeySets = BTreeMap<String, BTreeSet<String>>;
+
+/// Compares every repo-scoped table present in both databases, plus the
+/// merged-PR and closed-issue sets. Only reads from either connection.
+pub fn diff_databases(a: &Connection, b: &Connection) -> Result<Vec<TableDiff>> {
+    let tables_a: BTreeSet<String> = table_names(a)?.into_iter().collect();
+    let tables_b: BTreeSet<String> = table_names(b)?.into_iter().collect();
+    for table in tables_a.symmetric_difference(&tables_b) {
+        log::warn!(
+            "Table {} only exists in one of the databases; skipped",
+            table
+        );
+    }
+    let mut queries = Vec::new();
+    for table in tables_a.intersection(&tables_b) {
+        if let Some(sql) = key_query(a, table)? {
+            queries.push((table.clone(), sql));
+        }
+    }
+    queries.extend(
+        DERIVED_SETS
+            .iter()
+            .filter(|(_, table, _)| tables_a.contains(*table) && tables_b.contains(*table))
+            .map(|(label, _, sql)| (label.to_string(), sql.to_string())),
+    );
+
+    let mut diffs = Vec::new();
+    for (table, sql) in queries {
+        let keys_a = key_sets(a, &sql)?;
+        let keys_b = key_sets(b, &sql)?;
+        let repos: BTreeSet<&String> = keys_a.keys().chain(keys_b.keys()).collect();
+        let empty = BTreeSet::new();
+        for repo in repos {
+            let in_a = keys_a.get(repo).unwrap_or(&empty);
+            let in_b = keys_b.get(repo).unwrap_or(&empty);
+            diffs.push(TableDiff {
+                table: table.clone(),
+                repo_name: repo.clone(),
+                count_a: in_a.len(),
+                count_b: in_b.len(),
+                added: in_b.difference(in_a).cloned().collect(),
+                removed: in_a.difference(in_b).cloned().collect(),
+            });
+        }
+    }
+    Ok(diffs)
+}
+
+// Query selecting `repo_name` followed by the other primary key columns, or
+// `None` for tables that aren't scoped to a repository. Tables without a
+// declared key fall back to their rowid.
+fn key_query(conn: &Connection, table: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut columns = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(1)?, row.get::<_, i64>(5)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if !columns.iter().any(|(name, _)| name == "repo_name") {
+        return Ok(None);
+    }
+    columns.sort_by_key(|(_, pk)| *pk);
+    let mut keys: Vec<String> = columns
+        .into_iter()
+        .filter(|(name, pk)| *pk > 0 && name != "repo_name")
+        .map(|(name, _)| name)
+        .collect();
+    if keys.is_empty() {
+        keys.push("rowid".to_string());
+    }
+    Ok(Some(format!(
+        "SELECT repo_name, {} FROM {}",
+        keys.join(", "),
+        table
+    )))
+}
+
+// Runs a query whose first column is the repository and the rest form the key.
+fn key_sets(conn: &Connection, sql: &str) -> Result<KeySets> {
+    let mut stmt = conn.prepare(sql)?;
+    let key_columns = stmt.column_count() - 1;
+    let mut rows = stmt.query([])?;
+    let mut sets = KeySets::new();
+    while let Some(row) = rows.next()? {
+        let repo: Option<String> = row.get(0)?;
+        let key = (1..=key_columns)
+            .map(|i| row.get_ref(i).map(key_part))
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .join("/");
+        sets.entry(repo.unwrap_or_default())
+            .or_default()
+            .insert(key);
+    }
+    Ok(sets)
+}
+
+fn key_part(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) | ValueRef::Blob(t) => String::from_utf8_lossy(t).into_owned(),
+    }
+}
+
+/// Prints the counts per table and repository, listing up to `max_keys` of
+/// the added and removed keys for each.
+pub fn print_diff(diffs: &[TableDiff], max_keys: usize) {
+    println!(
+        "{:<22} {:<28} {:>8} {:>8} {:>8} {:>8}",
+        "table", "repository", "A", "B", "added", "removed"
+    );
+    for diff in diffs {
+        println!(
+            "{:<22} {:<28} {:>8} {:>8} {:>8} {:>8}",
+            diff.table,
+            diff.repo_name,
+            diff.count_a,
+            diff.count_b,
+            diff.added.len(),
+            diff.removed.len()
+        );
+    }
+    if max_keys == 0 {
+        return;
+    }
+    for diff in diffs {
+        for (sign, keys) in [("+", &diff.added), ("-", &diff.removed)] {
+            if keys.is_empty() {
+                continue;
+            }
+            println!();
+            println!(
+                "{} {} {} ({}):",
+                sign,
+                diff.table,
+                diff.repo_name,
+                keys.len()
+            );
+            for key in keys.iter().take(max_keys) {
+                println!("  {}", key);
+            }
+            if keys.len() > max_keys {
+                println!("  ... and {} more", keys.len() - max_keys);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_tables;
+
+    fn db(sql: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn.execute_batch(sql).unwrap();
+        conn
+    }
+
+    #[test]
+    fn reports_new_removed_and_newly_merged() {
+        let a = db(r"
+            INSERT INTO git_commits (sha, repo_name) VALUES ('a1', 'x/y'), ('a2', 'x/y');
+            INSERT INTO github_pull_requests (id, number, repo_name) VALUES (10, 1, 'x/y');
+            ");
+        let b = db(r"
+            INSERT INTO git_commits (sha, repo_name) VALUES ('a2', 'x/y'), ('a3', 'x/y');
+            INSERT INTO github_pull_requests (id, number, repo_name, merged_at)
+                VALUES (10, 1, 'x/y', '2024-01-01T00:00:00+00:00');
+            ");
+        let diffs = diff_databases(&a, &b).unwrap();
+        let find = |table: &str| diffs.iter().find(|d| d.table == table).unwrap();
+
+        let commits = find("git_commits");
+        assert_eq!((commits.count_a, commits.count_b), (2, 2));
+        assert_eq!(commits.added, ["a3"]);
+        assert_eq!(commits.removed, ["a1"]);
+
+        assert!(find("github_pull_requests").added.is_empty());
+        assert_eq!(find("merged PRs").added, ["1"]);
+        assert!(diffs.iter().all(|d| d.table != "runs"));
+    }
+}
@@ -14,6 +14,12 @@ pub enum DataError {
     #[error("JSON deserialization error: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[error("Arrow error: {0}")]
+    ArrowError(#[from] arrow::error::ArrowError),
+
+    #[error("Parquet error: {0}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -26,14 +32,134 @@ pub enum DataError {
     #[error("Resource not found (404)")]
     NotFoundError,
 
+    #[error("Resource is gone (410), e.g. deleted")]
+    GoneError,
+
+    #[error("Resource unavailable for legal reasons (451), e.g. a DMCA takedown")]
+    LegalUnavailableError,
+
     #[error("GitHub API error: {status} - {message}")]
     GitHubApiError {
         status: reqwest::StatusCode,
         message: String,
     },
 
+    #[error("Response from {url} is larger than the {limit}-byte --max-response-bytes limit")]
+    ResponseTooLarge { url: String, limit: u64 },
+
+    #[error("{0}")]
+    PartialFailure(String),
+
     #[error("Other error: {0}")]
     Other(String),
 }
 
+/// Process exit codes, so wrappers can tell failures apart.
+pub mod exit_code {
+    pub const SUCCESS: u8 = 0;
+    /// Any failure without a more specific code
+    pub const FAILURE: u8 = 1;
+    /// Invalid flags or configuration (also used by clap for usage errors)
+    pub const CONFIG: u8 = 2;
+    /// The GitHub rate limit is exhausted; retry after it resets
+    pub const RATE_LIMITED: u8 = 3;
+    /// The network or GitHub failed, or the credentials were rejected
+    pub const NETWORK: u8 = 4;
+    /// The run finished but some repositories failed to load
+    pub const PARTIAL_FAILURE: u8 = 5;
+}
+
+impl DataError {
+    /// True for resources GitHub deliberately no longer serves (410, 451).
+    /// Retrying won't help, so callers skip them and carry on.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            DataError::GoneError | DataError::LegalUnavailableError
+        )
+    }
+
+    /// The process exit code this error maps to; see [`exit_code`].
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            DataError::ConfigError(_) => exit_code::CONFIG,
+            DataError::RateLimitError => exit_code::RATE_LIMITED,
+            DataError::RequestError(_)
+            | DataError::GitHubApiError { .. }
+            | DataError::ResponseTooLarge { .. } => exit_code::NETWORK,
+            // Clones and fetches: unreachable remotes and rejected credentials
+            DataError::GitError(e)
+                if matches!(e.class(), git2::ErrorClass::Net | git2::ErrorClass::Http)
+                    || e.code() == git2::ErrorCode::Auth =>
+            {
+                exit_code::NETWORK
+            }
+            DataError::PartialFailure(_) => exit_code::PARTIAL_FAILURE,
+            _ => exit_code::FAILURE,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, DataError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{ErrorClass, ErrorCode};
+
+    #[test]
+    fn errors_map_to_their_exit_codes() {
+        let git = |code, class| DataError::GitError(git2::Error::new(code, class, "git"));
+        let cases = [
+            (DataError::Other("boom".to_string()), exit_code::FAILURE),
+            (DataError::NotFoundError, exit_code::FAILURE),
+            (
+                git(ErrorCode::NotFound, ErrorClass::Reference),
+                exit_code::FAILURE,
+            ),
+            (DataError::ConfigError("bad".to_string()), exit_code::CONFIG),
+            (DataError::RateLimitError, exit_code::RATE_LIMITED),
+            (
+                DataError::GitHubApiError {
+                    status: reqwest::StatusCode::UNAUTHORIZED,
+                    message: "Bad credentials".to_string(),
+                },
+                exit_code::NETWORK,
+            ),
+            (
+                DataError::ResponseTooLarge {
+                    url: "https://api.github.com".to_string(),
+                    limit: 1,
+                },
+                exit_code::NETWORK,
+            ),
+            (git(ErrorCode::Auth, ErrorClass::Ssh), exit_code::NETWORK),
+            (
+                git(ErrorCode::GenericError, ErrorClass::Net),
+                exit_code::NETWORK,
+            ),
+            (
+                git(ErrorCode::GenericError, ErrorClass::Http),
+                exit_code::NETWORK,
+            ),
+            (
+                DataError::PartialFailure("1 of 2 repositories failed".to_string()),
+                exit_code::PARTIAL_FAILURE,
+            ),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{}", error);
+        }
+        assert_eq!(exit_code::SUCCESS, 0);
+        assert_eq!(
+            [
+                exit_code::FAILURE,
+                exit_code::CONFIG,
+                exit_code::RATE_LIMITED,
+                exit_code::NETWORK,
+                exit_code::PARTIAL_FAILURE
+            ],
+            [1, 2, 3, 4, 5]
+        );
+    }
+}
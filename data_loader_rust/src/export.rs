@@ -0,0 +1,365 @@
+use crate::errors::Result;
+use arrow::array::{ArrayRef, Float64Builder, Int64Builder, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use regex::Regex;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+// Rows buffered per record batch while writing Parquet files
+const PARQUET_BATCH_ROWS: usize = 10_000;
+
+/// Names of all user tables in the database. Virtual tables such as the
+/// search index, and their shadow tables, are derived data and left out.
+pub fn table_names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT name FROM pragma_table_list
+        WHERE schema = 'main' AND type = 'table' AND name NOT LIKE 'sqlite_%'
+        ORDER BY name
+        ",
+    )?;
+    let names = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(names)
+}
+
+// Column names and their Arrow types, read from `PRAGMA table_info`.
+fn table_schema(conn: &Connection, table: &str) -> Result<Vec<(String, DataType)>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| {
+            let name: String = row.get(1)?;
+            let decl: String = row.get(2)?;
+            Ok((name, sqlite_to_arrow_type(&decl)))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(columns)
+}
+
+// Maps a declared SQLite column type to an Arrow type, following SQLite's
+// type-affinity rules loosely. Anything unrecognised is exported as text.
+fn sqlite_to_arrow_type(decl: &str) -> DataType {
+    let decl = decl.to_ascii_uppercase();
+    if decl.contains("INT") {
+        DataType::Int64
+    } else if decl.contains("REAL") || decl.contains("FLOA") || decl.contains("DOUB") {
+        DataType::Float64
+    } else {
+        DataType::Utf8
+    }
+}
+
+/// Replaces identity columns with a salted SHA-256 of their value, so exports
+/// can be shared without exposing contributors while joins still work.
+/// Email addresses inside other text, such as sign-offs in commit messages,
+/// are replaced by the same hash.
+pub struct Anonymizer {
+    salt: String,
+    email: Regex,
+}
+
+impl Anonymizer {
+    pub fn new(salt: &str) -> Self {
+        Self {
+            salt: salt.to_string(),
+            email: Regex::new(r"[\w.+-]+@[\w-]+(\.[\w-]+)+").expect("valid email pattern"),
+        }
+    }
+
+    // Columns of `table` holding GitHub logins or git identities. Trailer
+    // values are mostly `Name <email>` (Signed-off-by, Reviewed-by, ...).
+    fn is_identity_column(table: &str, column: &str) -> bool {
+        column == "login"
+            || column.ends_with("_login")
+            || matches!(
+                (table, column),
+                ("git_commits", "author_name" | "author_email")
+                    | ("commit_coauthors", "name" | "email")
+                    | ("commit_trailers", "value")
+            )
+    }
+
+    // The exported form of a text value: identity columns are hashed whole,
+    // other text has the email addresses in it hashed.
+    fn text(&self, identity: bool, text: &[u8]) -> String {
+        if identity {
+            return self.hash(text);
+        }
+        let text = String::from_utf8_lossy(text);
+        self.email
+            .replace_all(&text, |email: &regex::Captures| {
+                self.hash(email[0].as_bytes())
+            })
+            .into_owned()
+    }
+
+    fn hash(&self, value: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(value);
+        hex::encode(hasher.finalize())
+    }
+}
+
+enum ColumnBuilder {
+    Int(Int64Builder),
+    Float(Float64Builder),
+    Text(StringBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Int64 => ColumnBuilder::Int(Int64Builder::new()),
+            DataType::Float64 => ColumnBuilder::Float(Float64Builder::new()),
+            _ => ColumnBuilder::Text(StringBuilder::new()),
+        }
+    }
+
+    // SQLite is dynamically typed, so values that don't fit the declared
+    // column type are coerced where that is lossless and nulled otherwise.
+    fn append(&mut self, value: ValueRef<'_>) {
+        match self {
+            ColumnBuilder::Int(b) => b.append_option(match value {
+                ValueRef::Integer(i) => Some(i),
+                _ => None,
+            }),
+            ColumnBuilder::Float(b) => b.append_option(match value {
+                ValueRef::Real(f) => Some(f),
+                ValueRef::Integer(i) => Some(i as f64),
+                _ => None,
+            }),
+            ColumnBuilder::Text(b) => match value {
+                ValueRef::Null => b.append_null(),
+                ValueRef::Integer(i) => b.append_value(i.to_string()),
+                ValueRef::Real(f) => b.append_value(f.to_string()),
+                ValueRef::Text(t) | ValueRef::Blob(t) => b.append_value(String::from_utf8_lossy(t)),
+            },
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Int(b) => Arc::new(b.finish()),
+            ColumnBuilder::Float(b) => Arc::new(b.finish()),
+            ColumnBuilder::Text(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+/// Writes every table in the database to `<dir>/<table>.parquet`, hashing
+/// identity columns when an anonymizer is given. Returns the number of files written.
+pub fn export_parquet(
+    conn: &Connection,
+    dir: &Path,
+    anonymizer: Option<&Anonymizer>,
+) -> Result<usize> {
+    fs::create_dir_all(dir)?;
+    let tables = table_names(conn)?;
+    for table in &tables {
+        let path = dir.join(format!("{}.parquet", table));
+        let rows = export_table_parquet(conn, table, &path, anonymizer)?;
+        log::info!("Exported {} rows from {} to {:?}", rows, table, path);
+    }
+    Ok(tables.len())
+}
+
+fn export_table_parquet(
+    conn: &Connection,
+    table: &str,
+    path: &Path,
+    anonymizer: Option<&Anonymizer>,
+) -> Result<usize> {
+    let columns = table_schema(conn, table)?;
+    let identity: Vec<bool> = columns
+        .iter()
+        .map(|(name, _)| Anonymizer::is_identity_column(table, name))
+        .collect();
+    let schema = Arc::new(Schema::new(
+        columns
+            .iter()
+            .map(|(name, data_type)| Field::new(name, data_type.clone(), true))
+            .collect::<Vec<_>>(),
+    ));
+
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), None)?;
+    let mut builders: Vec<ColumnBuilder> = columns
+        .iter()
+        .map(|(_, data_type)| ColumnBuilder::new(data_type))
+        .collect();
+
+    let mut flush = |builders: &mut Vec<ColumnBuilder>| -> Result<()> {
+        let arrays = builders.iter_mut().map(ColumnBuilder::finish).collect();
+        let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+        writer.write(&batch)?;
+        Ok(())
+    };
+
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table))?;
+    let mut rows = stmt.query([])?;
+    let mut total = 0;
+    let mut pending = 0;
+    while let Some(row) = rows.next()? {
+        for (i, builder) in builders.iter_mut().enumerate() {
+            let value = row.get_ref(i)?;
+            match (anonymizer, value) {
+                (Some(anonymizer), ValueRef::Text(text)) => {
+                    let text = anonymizer.text(identity[i], text);
+                    builder.append(ValueRef::Text(text.as_bytes()));
+                }
+                _ => builder.append(value),
+            }
+        }
+        total += 1;
+        pending += 1;
+        if pending == PARQUET_BATCH_ROWS {
+            flush(&mut builders)?;
+            pending = 0;
+        }
+    }
+    if pending > 0 || total == 0 {
+        flush(&mut builders)?;
+    }
+
+    writer.close()?;
+    Ok(total)
+}
+
+/// Writes every table in the database to `<dir>/<table>.ndjson`, one JSON
+/// object per row keyed by column name, hashing identity columns when an
+/// anonymizer is given. `import` reads these files back. Returns the number
+/// of files written.
+pub fn export_ndjson(
+    conn: &Connection,
+    dir: &Path,
+    anonymizer: Option<&Anonymizer>,
+) -> Result<usize> {
+    fs::create_dir_all(dir)?;
+    let tables = table_names(conn)?;
+    for table in &tables {
+        let path = dir.join(format!("{}.ndjson", table));
+        let rows = export_table_ndjson(conn, table, &path, anonymizer)?;
+        log::info!("Exported {} rows from {} to {:?}", rows, table, path);
+    }
+    Ok(tables.len())
+}
+
+fn export_table_ndjson(
+    conn: &Connection,
+    table: &str,
+    path: &Path,
+    anonymizer: Option<&Anonymizer>,
+) -> Result<usize> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let identity: Vec<bool> = columns
+        .iter()
+        .map(|name| Anonymizer::is_identity_column(table, name))
+        .collect();
+
+    let mut rows = stmt.query([])?;
+    let mut total = 0;
+    while let Some(row) = rows.next()? {
+        let mut object = serde_json::Map::with_capacity(columns.len());
+        for (i, column) in columns.iter().enumerate() {
+            let value = match (anonymizer, row.get_ref(i)?) {
+                (Some(anonymizer), ValueRef::Text(text)) => {
+                    serde_json::Value::from(anonymizer.text(identity[i], text))
+                }
+                (_, value) => json_value(value),
+            };
+            object.insert(column.clone(), value);
+        }
+        serde_json::to_writer(&mut out, &object)?;
+        out.write_all(b"\n")?;
+        total += 1;
+    }
+    out.flush()?;
+    Ok(total)
+}
+
+// JSON has no NaN or infinity, so such reals are exported as null.
+fn json_value(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        ValueRef::Text(t) | ValueRef::Blob(t) => String::from_utf8_lossy(t).into_owned().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Exporter = fn(&Connection, &Path, Option<&Anonymizer>) -> Result<usize>;
+
+    // Every exported file of `dir`, concatenated. Parquet pages are written
+    // uncompressed, so plain strings show up in the bytes.
+    fn exported_bytes(dir: &Path) -> String {
+        let mut all = Vec::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            all.extend(fs::read(entry.unwrap().path()).unwrap());
+        }
+        String::from_utf8_lossy(&all).into_owned()
+    }
+
+    // Exports `conn` with `export`, in plain text and anonymized, and checks
+    // that each of `emails` only shows up in the plain export.
+    fn assert_anonymized(conn: &Connection, export: Exporter, emails: &[&str]) {
+        let dir = tempfile::TempDir::new().unwrap();
+        export(conn, &dir.path().join("plain"), None).unwrap();
+        let plain = exported_bytes(&dir.path().join("plain"));
+        let anonymizer = Anonymizer::new("salt");
+        export(conn, &dir.path().join("anonymized"), Some(&anonymizer)).unwrap();
+        let anonymized = exported_bytes(&dir.path().join("anonymized"));
+        for email in emails {
+            assert!(
+                plain.contains(email),
+                "{} missing from the plain export",
+                email
+            );
+            assert!(!anonymized.contains(email), "{} leaked", email);
+        }
+        // Hashed consistently, so the message still joins to the author
+        assert!(anonymized.contains(&anonymizer.hash(b"alice@example.com")));
+    }
+
+    fn commits_with_identities() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::create_tables(&conn).unwrap();
+        conn.execute_batch(
+            r"
+            INSERT INTO git_commits (sha, repo_name, author_name, author_email, message) VALUES
+                ('a1', 'x/y', 'Alice', 'alice@example.com',
+                 'Fix' || char(10) || char(10) || 'Signed-off-by: Alice <alice@example.com>');
+            INSERT INTO commit_coauthors (repo_name, commit_sha, name, email) VALUES
+                ('x/y', 'a1', 'Bob', 'bob@example.com');
+            INSERT INTO commit_trailers (repo_name, commit_sha, key, value) VALUES
+                ('x/y', 'a1', 'Reviewed-by', 'Carol <carol@example.com>');
+            ",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn anonymized_exports_contain_no_email_addresses() {
+        let conn = commits_with_identities();
+        let emails = ["alice@example.com", "bob@example.com", "carol@example.com"];
+        assert_anonymized(&conn, export_parquet, &emails);
+        assert_anonymized(&conn, export_ndjson, &emails);
+    }
+}
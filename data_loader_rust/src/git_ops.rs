@@ -1,21 +1,229 @@
-use crate::errors::Result;
-use git2::{AutotagOption, Cred, FetchOptions, FetchPrune, RemoteCallbacks, Repository};
+use crate::errors::{DataError, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{
+    AutotagOption, Cred, CredentialType, Delta, DiffFindOptions, DiffFormat, Direction, ErrorCode,
+    FetchOptions, FetchPrune, Oid, Remote, RemoteCallbacks, Repository, Revwalk, Sort,
+};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+
+// libgit2 invokes the credentials callback again after every rejected
+// credential, so each method is tried at most once per connection.
+const MAX_CREDENTIAL_ATTEMPTS: usize = 8;
+
+// Environment variable and credential helper through which `partial_clone`
+// passes the token to the git CLI. GitHub accepts a token as the password of
+// any user name.
+const TOKEN_CREDENTIAL_ENV: &str = "COREVSKNOTS_GIT_TOKEN";
+const TOKEN_CREDENTIAL_HELPER: &str = "credential.helper=!f() { test \"$1\" = get && \
+    echo username=x-access-token && echo \"password=$COREVSKNOTS_GIT_TOKEN\"; }; f";
+
+// SSH private keys tried, in order, after the agent.
+const SSH_KEY_FILES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Walks through the credential types libgit2 allows for a URL:
+/// token userpass for HTTPS, then ssh-agent, then on-disk SSH keys for SSH,
+/// then the platform default. Fails once every option has been tried.
+struct CredentialStrategy<'a> {
+    github_token: Option<&'a str>,
+    attempts: usize,
+    tried_username: bool,
+    tried_token: bool,
+    tried_agent: bool,
+    tried_default: bool,
+    ssh_keys: Vec<PathBuf>,
+}
+
+impl<'a> CredentialStrategy<'a> {
+    fn new(github_token: Option<&'a str>) -> Self {
+        let ssh_dir = std::env::var_os("HOME").map(|home| Path::new(&home).join(".ssh"));
+        let ssh_keys = ssh_dir
+            .map(|dir| {
+                // Reversed so that popping yields keys in preference order
+                SSH_KEY_FILES
+                    .iter()
+                    .rev()
+                    .map(|name| dir.join(name))
+                    .filter(|path| path.exists())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            github_token,
+            attempts: 0,
+            tried_username: false,
+            tried_token: false,
+            tried_agent: false,
+            tried_default: false,
+            ssh_keys,
+        }
+    }
+
+    fn next(
+        &mut self,
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> std::result::Result<Cred, git2::Error> {
+        self.attempts += 1;
+        if self.attempts > MAX_CREDENTIAL_ATTEMPTS {
+            return Err(git2::Error::from_str(&format!(
+                "Authentication failed for {} after {} attempts",
+                url, MAX_CREDENTIAL_ATTEMPTS
+            )));
+        }
+        let username = username_from_url.unwrap_or("git");
+
+        if allowed.contains(CredentialType::USERNAME) && !self.tried_username {
+            self.tried_username = true;
+            return Cred::username(username);
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !self.tried_token {
+            self.tried_token = true;
+            if let Some(token) = self.github_token {
+                log::debug!("Trying token authentication for {}", url);
+                return Cred::userpass_plaintext(token, ""); // Token as username, empty password
+            }
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            if !self.tried_agent {
+                self.tried_agent = true;
+                log::debug!("Trying ssh-agent authentication for {}", url);
+                return Cred::ssh_key_from_agent(username);
+            }
+            if let Some(key) = self.ssh_keys.pop() {
+                log::debug!("Trying SSH key {:?} for {}", key, url);
+                return Cred::ssh_key(username, None, &key, None);
+            }
+        }
+        if allowed.contains(CredentialType::DEFAULT) && !self.tried_default {
+            self.tried_default = true;
+            return Cred::default();
+        }
+        Err(git2::Error::from_str(&format!(
+            "No usable credentials for {} (allowed: {:?})",
+            url, allowed
+        )))
+    }
+}
+
+/// Builds remote callbacks that authenticate using [`CredentialStrategy`].
+fn credential_callbacks(github_token: Option<&str>) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut strategy = CredentialStrategy::new(github_token);
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        strategy.next(url, username_from_url, allowed_types)
+    });
+    callbacks
+}
+
+/// Lists the refs advertised by a remote without fetching any objects,
+/// like `git ls-remote`. Useful to pick a ref to clone and to check
+/// credentials cheaply.
+pub fn list_remote_refs(repo_url: &str, github_token: Option<&str>) -> Result<Vec<(String, Oid)>> {
+    log::info!("Listing remote refs for {}", repo_url);
+    let mut remote = Remote::create_detached(repo_url)?;
+    let connection = remote.connect_auth(
+        Direction::Fetch,
+        Some(credential_callbacks(github_token)),
+        None,
+    )?;
+    let refs = connection
+        .list()?
+        .iter()
+        .map(|head| (head.name().to_string(), head.oid()))
+        .collect();
+    Ok(refs)
+}
+
+/// Path of the local clone for a repository URL under `local_base_dir`.
+pub fn local_repo_path(repo_url: &str, local_base_dir: &str) -> PathBuf {
+    if is_local_repo(repo_url) {
+        return local_dir(repo_url).to_path_buf();
+    }
+    Path::new(local_base_dir).join(local_repo_dir_name(repo_url))
+}
+
+/// True if a configured repository names a local directory rather than a URL
+/// or `owner/name`: a `file://` URL, `.`, or a path starting with `/`, `./`
+/// or `../`. A bare relative path is read as `owner/name`, even if a
+/// directory of that name exists. Local repositories are never cloned or
+/// fetched.
+pub fn is_local_repo(repo_path: &str) -> bool {
+    repo_path.starts_with("file://")
+        || repo_path == "."
+        || ["/", "./", "../"]
+            .iter()
+            .any(|prefix| repo_path.starts_with(prefix))
+}
+
+/// The directory a local repository names, without any `file://` scheme.
+pub fn local_dir(repo_path: &str) -> &Path {
+    Path::new(repo_path.strip_prefix("file://").unwrap_or(repo_path))
+}
+
+/// The stored name of a local repository: its directory name and a hash of
+/// its canonical path, e.g. `bitcoin-1a2b3c4d`, so two clones with the same
+/// directory name don't share rows.
+pub fn local_repo_name(repo_path: &str) -> Result<String> {
+    let path = local_dir(repo_path).canonicalize()?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().trim_end_matches(".git").to_string())
+        .ok_or_else(|| {
+            DataError::ConfigError(format!("Cannot name the repository at {:?}", path))
+        })?;
+    let digest = Sha256::digest(path.to_string_lossy().as_bytes());
+    Ok(format!("{}-{}", name, &hex::encode(digest)[..8]))
+}
+
+fn local_repo_dir_name(repo_url: &str) -> String {
+    repo_url
+        .split('/')
+        .next_back()
+        .unwrap_or("unknown_repo")
+        .replace(".git", "")
+}
+
+/// Lets libgit2 open repositories created by `git clone --filter=...`.
+///
+/// Partial clones set `extensions.partialclone`, which libgit2 rejects as an
+/// unknown extension unless it has been registered. Reading commits and trees
+/// works as usual; only blobs may be missing. Safe to call more than once.
+pub fn allow_partial_clones() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        // SAFETY: runs once, before any repository is opened by this process,
+        // so no other thread is reading libgit2's global options.
+        if let Err(e) = unsafe { git2::opts::set_extensions(&["partialclone"]) } {
+            log::warn!("Could not register the partialclone extension: {}", e);
+        }
+    });
+}
 
 /// Ensures a repository is cloned or updated.
 /// Returns the path to the local repository.
+///
+/// When `max_age` is set and the clone was last fetched more recently than
+/// that, the fetch is skipped.
+///
+/// With `partial_clone`, a missing repository is cloned blobless (see
+/// [`partial_clone`]); updates of an existing clone always go through git2.
 pub fn ensure_repo_cloned_or_updated(
     repo_url: &str,
     local_base_dir: &str,
     github_token: Option<&str>,
+    max_age: Option<std::time::Duration>,
+    partial_clone: bool,
 ) -> Result<PathBuf> {
-    let repo_name = repo_url
-        .split('/')
-        .last()
-        .unwrap_or("unknown_repo")
-        .replace(".git", "");
-    let local_repo_path = Path::new(local_base_dir).join(&repo_name);
+    allow_partial_clones();
+    let repo_name = local_repo_dir_name(repo_url);
+    let local_repo_path = local_repo_path(repo_url, local_base_dir);
 
     if !Path::new(local_base_dir).exists() {
         fs::create_dir_all(local_base_dir)?;
@@ -27,21 +235,37 @@ pub fn ensure_repo_cloned_or_updated(
             repo_name
         );
         let repo = Repository::open(&local_repo_path)?;
-        fetch_all_and_prune(&repo, github_token)?;
+        if let Some(age) = max_age.and_then(|max_age| fresh_fetch_age(&repo, max_age)) {
+            log::info!(
+                "Repository {} was fetched {}s ago; skipping update.",
+                repo_name,
+                age.num_seconds()
+            );
+            return Ok(local_repo_path);
+        }
+        fetch_all_and_prune(&repo, repo_url, github_token)?;
+        record_fetch_time(&repo)?;
     } else {
         log::info!("Cloning repository {} from {}...", repo_name, repo_url);
-        let mut fo = FetchOptions::new();
-        if let Some(token) = github_token {
-            let mut callbacks = RemoteCallbacks::new();
-            callbacks.credentials(|_url, _username_from_server, _allowed_types| {
-                Cred::userpass_plaintext(token, "") // Use token as username, empty password
-            });
-            fo.remote_callbacks(callbacks);
-        }
-        fo.download_tags(AutotagOption::All);
-        git2::build::RepoBuilder::new()
-            .fetch_options(fo)
-            .clone(repo_url, &local_repo_path)?;
+        let repo = if partial_clone {
+            match self::partial_clone(repo_url, &local_repo_path, github_token) {
+                Ok(repo) => repo,
+                Err(e) => {
+                    log::warn!(
+                        "Partial clone of {} failed ({}); falling back to a full clone.",
+                        repo_name,
+                        e
+                    );
+                    if local_repo_path.exists() {
+                        fs::remove_dir_all(&local_repo_path)?;
+                    }
+                    full_clone(repo_url, &local_repo_path, github_token)?
+                }
+            }
+        } else {
+            full_clone(repo_url, &local_repo_path, github_token)?
+        };
+        record_fetch_time(&repo)?;
         log::info!(
             "Repository {} cloned successfully to {:?}.",
             repo_name,
@@ -51,31 +275,106 @@ pub fn ensure_repo_cloned_or_updated(
     Ok(local_repo_path)
 }
 
-fn fetch_all_and_prune(repo: &Repository, github_token: Option<&str>) -> Result<()> {
-    log::info!("Fetching all remotes for {:?}", repo.path());
+fn full_clone(repo_url: &str, path: &Path, github_token: Option<&str>) -> Result<Repository> {
     let mut fo = FetchOptions::new();
+    fo.remote_callbacks(credential_callbacks(github_token));
+    fo.download_tags(AutotagOption::All);
+    let repo = git2::build::RepoBuilder::new()
+        .fetch_options(fo)
+        .clone(repo_url, path)?;
+    Ok(repo)
+}
+
+/// Clones without file contents (`--filter=blob:none`), which keeps every
+/// commit and tree but skips the bulk of a large repository's download.
+///
+/// The linked libgit2 (1.8) cannot negotiate object filters, so this shells
+/// out to the `git` CLI (2.19 or newer, with a server that allows filters).
+/// A token is handed to git through a one-off credential helper that reads it
+/// from the child's environment, keeping it off the command line; without
+/// one, git's own credential configuration applies. Callers should fall back
+/// to a full clone when it fails.
+pub fn partial_clone(
+    repo_url: &str,
+    path: &Path,
+    github_token: Option<&str>,
+) -> Result<Repository> {
+    log::info!("Cloning {} without blobs into {:?}", repo_url, path);
+    let mut command = Command::new("git");
     if let Some(token) = github_token {
-        let mut callbacks = RemoteCallbacks::new();
-        callbacks.credentials(|_url, _username_from_server, _allowed_types| {
-            Cred::userpass_plaintext(token, "")
-        });
-        fo.remote_callbacks(callbacks);
+        // The empty helper clears configured ones so the token is what's sent
+        command
+            .args(["-c", "credential.helper=", "-c", TOKEN_CREDENTIAL_HELPER])
+            .env(TOKEN_CREDENTIAL_ENV, token);
     }
+    let output = command
+        .args(["clone", "--quiet", "--no-checkout", "--filter=blob:none"])
+        .arg(repo_url)
+        .arg(path)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .map_err(|e| DataError::Other(format!("could not run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(DataError::Other(format!(
+            "git clone exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    allow_partial_clones();
+    Ok(Repository::open(path)?)
+}
+
+// Marker file inside the git dir holding the time of the last clone/fetch.
+const LAST_FETCH_FILE: &str = "corevsknots_last_fetch";
+
+fn record_fetch_time(repo: &Repository) -> Result<()> {
+    fs::write(repo.path().join(LAST_FETCH_FILE), Utc::now().to_rfc3339())?;
+    Ok(())
+}
+
+// Returns how long ago the clone was fetched if that is within `max_age`.
+fn fresh_fetch_age(repo: &Repository, max_age: std::time::Duration) -> Option<chrono::Duration> {
+    let recorded = fs::read_to_string(repo.path().join(LAST_FETCH_FILE)).ok()?;
+    let fetched_at = DateTime::parse_from_rfc3339(recorded.trim()).ok()?;
+    let age = Utc::now().signed_duration_since(fetched_at);
+    let max_age = chrono::Duration::from_std(max_age).ok()?;
+    (age <= max_age).then_some(age)
+}
+
+// Fetches every remote of the clone. A clone without remotes (such as a
+// directory initialized by hand) would otherwise never update, so `origin`
+// is added pointing at `repo_url` first.
+fn fetch_all_and_prune(
+    repo: &Repository,
+    repo_url: &str,
+    github_token: Option<&str>,
+) -> Result<()> {
+    log::info!("Fetching all remotes for {:?}", repo.path());
+    let mut fo = FetchOptions::new();
+    fo.remote_callbacks(credential_callbacks(github_token));
     fo.prune(FetchPrune::On);
     fo.download_tags(AutotagOption::All);
 
-    let remotes = repo.remotes()?;
-    for remote_name_opt in remotes.iter() {
-        if let Some(remote_name) = remote_name_opt {
-            log::debug!("Fetching remote: {}", remote_name);
-            match repo.find_remote(remote_name) {
-                Ok(mut remote) => {
-                    remote.fetch(&[] as &[&str], Some(&mut fo), None)?;
-                    log::info!("Fetched remote {} successfully.", remote_name);
-                }
-                Err(e) => {
-                    log::warn!("Could not find remote {}: {}. Skipping.", remote_name, e);
-                }
+    let mut remotes = repo.remotes()?;
+    if remotes.is_empty() {
+        log::warn!(
+            "Clone {:?} has no remotes, so it cannot be updated; adding origin -> {}",
+            repo.path(),
+            repo_url
+        );
+        repo.remote("origin", repo_url)?;
+        remotes = repo.remotes()?;
+    }
+    for remote_name in remotes.iter().flatten() {
+        log::debug!("Fetching remote: {}", remote_name);
+        match repo.find_remote(remote_name) {
+            Ok(mut remote) => {
+                remote.fetch(&[] as &[&str], Some(&mut fo), None)?;
+                log::info!("Fetched remote {} successfully.", remote_name);
+            }
+            Err(e) => {
+                log::warn!("Could not find remote {}: {}. Skipping.", remote_name, e);
             }
         }
     }
@@ -83,5 +382,885 @@ fn fetch_all_and_prune(repo: &Repository, github_token: Option<&str>) -> Result<
     Ok(())
 }
 
-// TODO: Add functions to extract commit data, etc., from the local repo using git2
-// This might involve iterating over revwalk, similar to how it's done in Python.
+/// A file renamed by a commit, as (old path, new path).
+pub type Rename = (String, String);
+
+/// Commit metadata extracted from a local clone.
+#[derive(Debug, Clone)]
+pub struct LocalCommit {
+    pub sha: String,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Committer date
+    pub commit_timestamp: Option<DateTime<Utc>>,
+    pub author_timestamp: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+    /// Paths changed relative to the first parent (or everything, for a root commit).
+    /// Renamed files contribute both their old and new path.
+    pub changed_paths: Vec<String>,
+    /// Files renamed relative to the first parent.
+    pub renames: Vec<Rename>,
+    /// Lines added and removed relative to the first parent; `None` in
+    /// partial clones, which lack the file contents to count them.
+    pub insertions: Option<i64>,
+    pub deletions: Option<i64>,
+    /// Two or more parents
+    pub is_merge: bool,
+}
+
+impl LocalCommit {
+    fn from_git2(repo: &Repository, commit: &git2::Commit, blobless: bool) -> Result<Self> {
+        let author = commit.author();
+        let commit_timestamp = Utc.timestamp_opt(commit.time().seconds(), 0).single();
+        let author_timestamp = Utc.timestamp_opt(author.when().seconds(), 0).single();
+        let CommitDiff {
+            paths: changed_paths,
+            renames,
+            churn,
+        } = diff_commit(repo, commit, blobless)?;
+        Ok(Self {
+            sha: commit.id().to_string(),
+            author_name: author.name().map(str::to_string),
+            author_email: author.email().map(str::to_string),
+            commit_timestamp,
+            author_timestamp,
+            message: commit.message().map(str::to_string),
+            changed_paths,
+            renames,
+            insertions: churn.map(|(insertions, _)| insertions),
+            deletions: churn.map(|(_, deletions)| deletions),
+            is_merge: commit.parent_count() >= 2,
+        })
+    }
+}
+
+/// Walks the history reachable from `branch` (HEAD when `None`), oldest first, handing commits to
+/// `on_batch` every `batch_size` commits so callers can persist them
+/// incrementally instead of holding the whole history in memory.
+///
+/// Commits are visited in topological order, so every ancestor of a commit is
+/// emitted before it. When `resume_after` is set, that commit and all of its
+/// ancestors are hidden from the walk, which lets a re-run continue from the
+/// last SHA that was successfully persisted.
+///
+/// At most `max_commits` commits are visited per call. Because the walk is
+/// oldest first and resumable, a capped extraction picks up where it stopped
+/// on the next run.
+///
+/// Returns the number of commits visited and whether the cap stopped the walk
+/// before the end of the history.
+pub fn extract_commits<F>(
+    repo_path: &Path,
+    branch: Option<&str>,
+    resume_after: Option<&str>,
+    batch_size: usize,
+    max_commits: Option<usize>,
+    mut on_batch: F,
+) -> Result<(usize, bool)>
+where
+    F: FnMut(&[LocalCommit]) -> Result<()>,
+{
+    let repo = Repository::open(repo_path)?;
+    if has_no_commits(&repo)? {
+        log::warn!(
+            "Repository at {:?} has no commits yet; nothing to extract.",
+            repo_path
+        );
+        return Ok((0, false));
+    }
+    let blobless = is_partial_clone(&repo);
+    if blobless {
+        log::info!(
+            "{:?} is a partial clone; only exact renames are detected and line counts are skipped",
+            repo_path
+        );
+    }
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    match branch {
+        Some(branch) => {
+            let oid = branch_tip(&repo, branch)?;
+            log::info!("Walking {} ({}) in {:?}", branch, oid, repo_path);
+            revwalk.push(oid)?;
+        }
+        None => revwalk.push_head()?,
+    }
+
+    if let Some(sha) = resume_after {
+        match Oid::from_str(sha).and_then(|oid| revwalk.hide(oid)) {
+            Ok(()) => log::info!("Resuming commit extraction after {}", sha),
+            Err(e) => log::warn!(
+                "Could not resume after {} ({}); extracting full history.",
+                sha,
+                e
+            ),
+        }
+    }
+
+    let batch_size = batch_size.max(1);
+    let mut batch: Vec<LocalCommit> = Vec::with_capacity(batch_size);
+    let mut total = 0;
+
+    let mut truncated = false;
+    for oid in revwalk {
+        if max_commits.is_some_and(|max| total >= max) {
+            truncated = true;
+            break;
+        }
+        let commit = repo.find_commit(oid?)?;
+        batch.push(LocalCommit::from_git2(&repo, &commit, blobless)?);
+        total += 1;
+
+        if batch.len() >= batch_size {
+            on_batch(&batch)?;
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        on_batch(&batch)?;
+    }
+
+    log::info!("Extracted {} commits from {:?}", total, repo_path);
+    Ok((total, truncated))
+}
+
+/// Whether each of `shas` is reachable from the tip of `branch`, i.e. has
+/// been merged into it. SHAs the clone doesn't have are left out, so their
+/// state stays unknown.
+pub fn reachable_from_branch(
+    repo_path: &Path,
+    branch: &str,
+    shas: &[String],
+) -> Result<Vec<(String, bool)>> {
+    let repo = Repository::open(repo_path)?;
+    if has_no_commits(&repo)? {
+        return Ok(Vec::new());
+    }
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(branch_tip(&repo, branch)?)?;
+    let reachable = revwalk.collect::<std::result::Result<HashSet<Oid>, _>>()?;
+    Ok(shas
+        .iter()
+        .filter_map(|sha| {
+            let oid = Oid::from_str(sha).ok()?;
+            repo.find_commit(oid).ok()?;
+            Some((sha.clone(), reachable.contains(&oid)))
+        })
+        .collect())
+}
+
+/// Unified diff of a commit against its first parent (or of everything it
+/// adds, for a root commit). `None` when the clone doesn't have the commit,
+/// or is a partial clone that lacks the file contents.
+pub fn commit_patch(repo_path: &Path, sha: &str) -> Result<Option<String>> {
+    let repo = Repository::open(repo_path)?;
+    if is_partial_clone(&repo) {
+        return Ok(None);
+    }
+    let Some(commit) = Oid::from_str(sha)
+        .ok()
+        .and_then(|oid| repo.find_commit(oid).ok())
+    else {
+        return Ok(None);
+    };
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    let mut patch = Vec::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        // Content lines come without their +/-/space marker
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin() as u8);
+        }
+        patch.extend_from_slice(line.content());
+        true
+    })?;
+    Ok(Some(String::from_utf8_lossy(&patch).into_owned()))
+}
+
+/// Lines added and removed by each of `shas` relative to its first parent,
+/// for backfilling commits stored before line counts were recorded. Empty for
+/// a partial clone; commits the clone doesn't have are skipped.
+pub fn commit_churn(repo_path: &Path, shas: &[String]) -> Result<Vec<(String, i64, i64)>> {
+    let repo = Repository::open(repo_path)?;
+    if is_partial_clone(&repo) {
+        return Ok(Vec::new());
+    }
+    let mut churn = Vec::new();
+    for sha in shas {
+        let Some(commit) = Oid::from_str(sha)
+            .ok()
+            .and_then(|oid| repo.find_commit(oid).ok())
+        else {
+            continue;
+        };
+        if let Some((insertions, deletions)) = diff_commit(&repo, &commit, false)?.churn {
+            churn.push((sha.clone(), insertions, deletions));
+        }
+    }
+    Ok(churn)
+}
+
+/// Returns the commits reachable from `head` but not from `base`
+/// (git's `base..head`), newest first. Both may be any revspec such as a
+/// branch, tag or SHA.
+pub fn commits_between(repo_path: &Path, base: &str, head: &str) -> Result<Vec<LocalCommit>> {
+    let repo = Repository::open(repo_path)?;
+    let Some(revwalk) = range_walk(&repo, base, head)? else {
+        return Ok(Vec::new());
+    };
+    let blobless = is_partial_clone(&repo);
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        commits.push(LocalCommit::from_git2(&repo, &commit, blobless)?);
+    }
+    Ok(commits)
+}
+
+/// SHAs and subject lines of the commits in `base..head`, newest first.
+/// Cheaper than [`commits_between`] since the commits aren't diffed.
+pub fn commit_subjects_between(
+    repo_path: &Path,
+    base: &str,
+    head: &str,
+) -> Result<Vec<(String, String)>> {
+    let repo = Repository::open(repo_path)?;
+    let Some(revwalk) = range_walk(&repo, base, head)? else {
+        return Ok(Vec::new());
+    };
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let subject = commit.summary().unwrap_or_default().to_string();
+        commits.push((commit.id().to_string(), subject));
+    }
+    Ok(commits)
+}
+
+// Walk over `base..head` in topological order, or `None` for a repository
+// without commits.
+fn range_walk<'r>(repo: &'r Repository, base: &str, head: &str) -> Result<Option<Revwalk<'r>>> {
+    if has_no_commits(repo)? {
+        log::warn!(
+            "Repository at {:?} has no commits yet; range {}..{} is empty.",
+            repo.path(),
+            base,
+            head
+        );
+        return Ok(None);
+    }
+    let head_oid = repo.revparse_single(head)?.peel_to_commit()?.id();
+    let base_oid = repo.revparse_single(base)?.peel_to_commit()?.id();
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(head_oid)?;
+    revwalk.hide(base_oid)?;
+    Ok(Some(revwalk))
+}
+
+/// A tag and the commit it points at, directly or via an annotated tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitTag {
+    pub name: String,
+    pub sha: String,
+    pub committed_at: Option<DateTime<Utc>>,
+}
+
+/// Tags whose names match `pattern`, in version order (then by name). Tags of
+/// non-commits are skipped.
+pub fn list_tags(repo_path: &Path, pattern: &Regex) -> Result<Vec<GitTag>> {
+    let repo = Repository::open(repo_path)?;
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        if !pattern.is_match(name) {
+            continue;
+        }
+        let object = repo.revparse_single(&format!("refs/tags/{}", name))?;
+        let Ok(commit) = object.peel_to_commit() else {
+            log::debug!("Skipping tag {}, which doesn't point at a commit", name);
+            continue;
+        };
+        tags.push(GitTag {
+            name: name.to_string(),
+            sha: commit.id().to_string(),
+            committed_at: Utc.timestamp_opt(commit.time().seconds(), 0).single(),
+        });
+    }
+    tags.sort_by_cached_key(|tag| (tag_version(&tag.name), tag.name.clone()));
+    Ok(tags)
+}
+
+// The version numbers of a tag name: the dot-separated numbers after any
+// prefix (`v0.21.1` -> [0, 21, 1]), then the numbers in whatever follows, as
+// a tiebreak (`v27.1.knots20240801` -> [27, 1], [20240801]).
+fn tag_version(name: &str) -> (Vec<u64>, Vec<u64>) {
+    let number = |digits: &str| digits.parse().unwrap_or(u64::MAX);
+    let start = name
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(name.len());
+    let mut rest = &name[start..];
+    let mut release = Vec::new();
+    loop {
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        release.push(number(&rest[..end]));
+        rest = &rest[end..];
+        match rest.strip_prefix('.') {
+            Some(next) if next.starts_with(|c: char| c.is_ascii_digit()) => rest = next,
+            _ => break,
+        }
+    }
+    let suffix = rest
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|digits| !digits.is_empty())
+        .map(number)
+        .collect();
+    (release, suffix)
+}
+
+/// The releases whose changelogs are recorded, as (previous, release). A tag
+/// follows the previous tag of its release line (`v25.1` -> `v25.2`), and
+/// the first tag of a line follows the first of the line before (`v25.0` ->
+/// `v26.0`), so point releases of maintenance branches don't interleave with
+/// new major versions. A line is a version without its last number. `tags`
+/// must be in version order, as `list_tags` returns them.
+pub fn changelog_pairs(tags: &[GitTag]) -> Vec<(&GitTag, &GitTag)> {
+    let mut pairs = Vec::new();
+    let mut line: Option<(Vec<u64>, &GitTag)> = None;
+    let mut previous: Option<&GitTag> = None;
+    for tag in tags {
+        let (mut release, _) = tag_version(&tag.name);
+        release.pop();
+        match &line {
+            Some((current, _)) if *current == release => {
+                pairs.extend(previous.map(|previous| (previous, tag)));
+            }
+            _ => {
+                pairs.extend(line.as_ref().map(|(_, first)| (*first, tag)));
+                line = Some((release, tag));
+            }
+        }
+        previous = Some(tag);
+    }
+    pairs
+}
+
+// Paths, renames and line counts of a commit. Merges are diffed against their
+// first parent, so only what the merge brought into the branch is counted.
+struct CommitDiff {
+    paths: Vec<String>,
+    renames: Vec<Rename>,
+    churn: Option<(i64, i64)>,
+}
+
+// Partial clones leave file contents to be fetched on demand, which libgit2
+// cannot do, so anything that reads blobs fails on them. Newer git marks the
+// remote as a promisor; older versions set `extensions.partialclone`.
+fn is_partial_clone(repo: &Repository) -> bool {
+    let Ok(config) = repo.config() else {
+        return false;
+    };
+    config.get_bool("remote.origin.promisor").unwrap_or(false)
+        || config.get_string("extensions.partialclone").is_ok()
+}
+
+fn diff_commit(repo: &Repository, commit: &git2::Commit, blobless: bool) -> Result<CommitDiff> {
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    // Similarity detection compares file contents; exact renames only need ids
+    diff.find_similar(Some(
+        DiffFindOptions::new()
+            .renames(true)
+            .exact_match_only(blobless),
+    ))?;
+
+    let lossy = |path: &Path| path.to_string_lossy().into_owned();
+    let mut paths = Vec::new();
+    let mut renames = Vec::new();
+    for delta in diff.deltas() {
+        let old_path = delta.old_file().path();
+        let new_path = delta.new_file().path();
+        if let (Delta::Renamed, Some(old_path), Some(new_path)) =
+            (delta.status(), old_path, new_path)
+        {
+            // The move touches both the source and the destination
+            paths.push(lossy(old_path));
+            renames.push((lossy(old_path), lossy(new_path)));
+        }
+        if let Some(path) = new_path.or(old_path) {
+            paths.push(lossy(path));
+        }
+    }
+    let churn = if blobless {
+        None
+    } else {
+        let stats = diff.stats()?;
+        Some((stats.insertions() as i64, stats.deletions() as i64))
+    };
+    Ok(CommitDiff {
+        paths,
+        renames,
+        churn,
+    })
+}
+
+// Commit a branch points at. The remote-tracking ref wins over a local branch
+// of the same name because fetches only advance the former; anything else
+// git can parse (a tag or SHA) is accepted as well.
+fn branch_tip(repo: &Repository, branch: &str) -> Result<Oid> {
+    for name in [
+        format!("refs/remotes/origin/{}", branch),
+        format!("refs/heads/{}", branch),
+    ] {
+        if let Ok(reference) = repo.find_reference(&name) {
+            return Ok(reference.peel_to_commit()?.id());
+        }
+    }
+    match repo.revparse_single(branch) {
+        Ok(object) => Ok(object.peel_to_commit()?.id()),
+        Err(e) => Err(DataError::ConfigError(format!(
+            "Branch {} not found in {:?}: {}",
+            branch,
+            repo.path(),
+            e
+        ))),
+    }
+}
+
+// True for freshly initialised repositories and ones whose HEAD points at a
+// branch that does not exist yet, where resolving HEAD would fail.
+fn has_no_commits(repo: &Repository) -> Result<bool> {
+    if repo.is_empty()? {
+        return Ok(true);
+    }
+    match repo.head() {
+        Ok(_) => Ok(false),
+        Err(e) if e.code() == ErrorCode::UnbornBranch => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn commit_file(repo: &Repository, message: &str) {
+        let sig = git2::Signature::now("Alice", "alice@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let parent = repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap();
+    }
+
+    // A strategy with the given SSH key files instead of those in ~/.ssh.
+    fn strategy<'a>(token: Option<&'a str>, keys: &[&str]) -> CredentialStrategy<'a> {
+        CredentialStrategy {
+            ssh_keys: keys.iter().rev().map(PathBuf::from).collect(),
+            ..CredentialStrategy::new(token)
+        }
+    }
+
+    // The kind of credential each call to `next` hands out, until it errors.
+    fn attempts(mut strategy: CredentialStrategy, allowed: CredentialType) -> (Vec<u32>, String) {
+        let mut kinds = Vec::new();
+        loop {
+            match strategy.next("url", Some("git"), allowed) {
+                Ok(cred) => kinds.push(cred.credtype()),
+                Err(e) => return (kinds, e.message().to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn https_tries_the_token_once_then_gives_up() {
+        let https = CredentialType::USER_PASS_PLAINTEXT;
+        let (kinds, error) = attempts(strategy(Some("ghp_x"), &[]), https);
+        assert_eq!(kinds, [https.bits()]);
+        assert!(error.starts_with("No usable credentials for url"));
+
+        let (kinds, _) = attempts(strategy(None, &[]), https);
+        assert!(kinds.is_empty());
+    }
+
+    #[test]
+    fn ssh_tries_the_agent_then_each_key_file() {
+        let ssh = CredentialType::SSH_KEY;
+        let (kinds, error) = attempts(
+            strategy(Some("ghp_x"), &["/keys/id_ed25519", "/keys/id_rsa"]),
+            ssh | CredentialType::USERNAME,
+        );
+        // The user name is sent once; the agent and both keys follow
+        assert_eq!(
+            kinds,
+            [
+                CredentialType::USERNAME.bits(),
+                ssh.bits(),
+                ssh.bits(),
+                ssh.bits()
+            ]
+        );
+        assert!(error.starts_with("No usable credentials"));
+    }
+
+    #[test]
+    fn attempts_are_capped() {
+        let keys: Vec<String> = (0..20).map(|i| format!("/keys/id_{}", i)).collect();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let (kinds, error) = attempts(strategy(None, &keys), CredentialType::SSH_KEY);
+        assert_eq!(kinds.len(), MAX_CREDENTIAL_ATTEMPTS);
+        assert_eq!(error, "Authentication failed for url after 8 attempts");
+    }
+
+    #[test]
+    fn token_credential_helper_answers_with_the_token() {
+        let mut child = Command::new("git")
+            .args(["-c", "credential.helper=", "-c", TOKEN_CREDENTIAL_HELPER])
+            .args(["credential", "fill"])
+            .env(TOKEN_CREDENTIAL_ENV, "ghp_secret")
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"protocol=https\nhost=github.com\n\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        let filled = String::from_utf8(output.stdout).unwrap();
+        assert!(filled.contains("username=x-access-token\n"));
+        assert!(filled.contains("password=ghp_secret\n"));
+    }
+
+    #[test]
+    fn extracting_an_empty_repo_yields_no_commits() {
+        let dir = TempDir::new().unwrap();
+        Repository::init(dir.path()).unwrap();
+
+        let mut batches = 0;
+        let (total, _) = extract_commits(dir.path(), None, None, 10, None, |_| {
+            batches += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(total, 0);
+        assert_eq!(batches, 0);
+        assert!(commits_between(dir.path(), "HEAD~1", "HEAD")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn clones_without_remotes_get_origin_and_are_fetched() {
+        let upstream = TempDir::new().unwrap();
+        let upstream_repo = Repository::init(upstream.path()).unwrap();
+        commit_file(&upstream_repo, "first");
+        let head = upstream_repo.head().unwrap().target().unwrap();
+
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let url = upstream.path().to_str().unwrap();
+        fetch_all_and_prune(&repo, url, None).unwrap();
+
+        assert_eq!(repo.find_remote("origin").unwrap().url(), Some(url));
+        assert!(repo.find_commit(head).is_ok());
+    }
+
+    #[test]
+    fn extracts_commits_once_history_exists() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_file(&repo, "first");
+        commit_file(&repo, "second");
+
+        let mut messages = Vec::new();
+        let (total, _) = extract_commits(dir.path(), None, None, 10, None, |batch| {
+            messages.extend(batch.iter().filter_map(|c| c.message.clone()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(messages, ["first", "second"]);
+    }
+
+    #[test]
+    fn local_clones_with_the_same_directory_name_get_distinct_names() {
+        let dir = TempDir::new().unwrap();
+        let [core, knots] = ["core", "knots"].map(|parent| {
+            let clone = dir.path().join(parent).join("bitcoin");
+            fs::create_dir_all(&clone).unwrap();
+            clone.to_str().unwrap().to_string()
+        });
+        let core_name = local_repo_name(&core).unwrap();
+        assert!(core_name.starts_with("bitcoin-"), "{}", core_name);
+        assert_ne!(core_name, local_repo_name(&knots).unwrap());
+        assert_eq!(
+            core_name,
+            local_repo_name(&format!("file://{}", core)).unwrap()
+        );
+    }
+
+    #[test]
+    fn local_directories_are_walked_in_place_from_head() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_file(&repo, "only");
+        let path = dir.path().to_str().unwrap();
+        assert!(is_local_repo(path));
+        assert!(!is_local_repo("bitcoinknots/bitcoin"));
+        assert_eq!(local_repo_path(path, "./repo_clones"), dir.path());
+        let url = format!("file://{}", path);
+        assert!(is_local_repo(&url));
+        assert_eq!(local_repo_path(&url, "./repo_clones"), dir.path());
+
+        // No origin remote: HEAD falls back to the checked-out branch
+        let (total, _) =
+            extract_commits(dir.path(), Some("HEAD"), None, 10, None, |_| Ok(())).unwrap();
+        assert_eq!(total, 1);
+    }
+
+    #[test]
+    fn commits_off_the_branch_are_not_reachable_from_it() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_file(&repo, "base");
+        let base = repo.head().unwrap().peel_to_commit().unwrap();
+        let main = repo.head().unwrap().shorthand().unwrap().to_string();
+        repo.branch("feature", &base, false).unwrap();
+        repo.set_head("refs/heads/feature").unwrap();
+        commit_file(&repo, "feature only");
+        let feature = repo.head().unwrap().peel_to_commit().unwrap();
+
+        let shas = [
+            base.id().to_string(),
+            feature.id().to_string(),
+            "0".repeat(40),
+        ];
+        let reachable = reachable_from_branch(dir.path(), &main, &shas).unwrap();
+        assert_eq!(
+            reachable,
+            [(shas[0].clone(), true), (shas[1].clone(), false)]
+        );
+    }
+
+    #[test]
+    fn patches_show_the_changes_of_a_commit() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_file(&repo, "empty");
+        fs::write(dir.path().join("a.txt"), "hello\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        commit_file(&repo, "add a.txt");
+        let head = repo.head().unwrap().peel_to_commit().unwrap().id();
+
+        let patch = commit_patch(dir.path(), &head.to_string())
+            .unwrap()
+            .unwrap();
+        assert!(
+            patch.starts_with("diff --git a/a.txt b/a.txt\n"),
+            "{}",
+            patch
+        );
+        assert!(patch.ends_with("+hello\n"), "{}", patch);
+        assert_eq!(commit_patch(dir.path(), &"0".repeat(40)).unwrap(), None);
+    }
+
+    #[test]
+    fn lists_matching_tags_and_the_commits_between_them() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let tag_head = |name: &str| {
+            let head = repo.head().unwrap().peel_to_commit().unwrap();
+            repo.tag_lightweight(name, head.as_object(), false).unwrap();
+        };
+        commit_file(&repo, "first");
+        tag_head("v1.0");
+        commit_file(&repo, "second\n\nwith a body");
+        commit_file(&repo, "third");
+        tag_head("v1.1rc1");
+        tag_head("v1.1");
+
+        let pattern = Regex::new(r"^v\d+(\.\d+)+$").unwrap();
+        let tags = list_tags(dir.path(), &pattern).unwrap();
+        let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["v1.0", "v1.1"]);
+
+        let subjects: Vec<String> = commit_subjects_between(dir.path(), &tags[0].sha, &tags[1].sha)
+            .unwrap()
+            .into_iter()
+            .map(|(_, subject)| subject)
+            .collect();
+        assert_eq!(subjects, ["third", "second"]);
+    }
+
+    #[test]
+    fn changelogs_follow_release_lines_in_version_order() {
+        let tag = |name: &str| GitTag {
+            name: name.to_string(),
+            sha: name.to_string(),
+            committed_at: None,
+        };
+        let mut tags: Vec<GitTag> = [
+            "v26.1",
+            "v0.21.1",
+            "v25.2",
+            "v22.0",
+            "v25.0",
+            "v0.21.0",
+            "v26.0",
+            "v25.1",
+            "v27.1.knots20240801",
+            "v27.1.knots20240621",
+            "v27.0",
+        ]
+        .into_iter()
+        .map(tag)
+        .collect();
+        tags.sort_by_cached_key(|tag| (tag_version(&tag.name), tag.name.clone()));
+
+        let pairs: Vec<(&str, &str)> = changelog_pairs(&tags)
+            .into_iter()
+            .map(|(from, to)| (from.name.as_str(), to.name.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("v0.21.0", "v0.21.1"),
+                ("v0.21.0", "v22.0"),
+                ("v22.0", "v25.0"),
+                ("v25.0", "v25.1"),
+                ("v25.1", "v25.2"),
+                ("v25.0", "v26.0"),
+                ("v26.0", "v26.1"),
+                ("v26.0", "v27.0"),
+                ("v27.0", "v27.1.knots20240621"),
+                ("v27.1.knots20240621", "v27.1.knots20240801"),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_commits_caps_extraction_and_resume_continues() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        for message in ["first", "second", "third"] {
+            commit_file(&repo, message);
+        }
+
+        let mut shas = Vec::new();
+        let (total, truncated) = extract_commits(dir.path(), None, None, 10, Some(2), |batch| {
+            shas.extend(batch.iter().map(|c| c.sha.clone()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!((total, truncated), (2, true));
+
+        let (total, truncated) = extract_commits(
+            dir.path(),
+            None,
+            shas.last().map(String::as_str),
+            10,
+            Some(2),
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert_eq!((total, truncated), (1, false));
+    }
+
+    #[test]
+    fn walks_the_requested_branch_instead_of_head() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_file(&repo, "first");
+        let first = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("main", &first, false).unwrap();
+        commit_file(&repo, "only on head");
+
+        let mut messages = Vec::new();
+        let (total, _) = extract_commits(dir.path(), Some("main"), None, 10, None, |batch| {
+            messages.extend(batch.iter().filter_map(|c| c.message.clone()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(messages, ["first"]);
+
+        let missing = extract_commits(dir.path(), Some("nope"), None, 10, None, |_| Ok(()));
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn records_renamed_files() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let content = "moved code\n".repeat(20);
+        fs::create_dir_all(dir.path().join("src/old")).unwrap();
+        fs::write(dir.path().join("src/old/file.cpp"), &content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/old/file.cpp")).unwrap();
+        index.write().unwrap();
+        commit_file(&repo, "add");
+
+        fs::create_dir_all(dir.path().join("src/new")).unwrap();
+        fs::rename(
+            dir.path().join("src/old/file.cpp"),
+            dir.path().join("src/new/file.cpp"),
+        )
+        .unwrap();
+        index.remove_path(Path::new("src/old/file.cpp")).unwrap();
+        index.add_path(Path::new("src/new/file.cpp")).unwrap();
+        index.write().unwrap();
+        commit_file(&repo, "move");
+
+        let mut commits = Vec::new();
+        extract_commits(dir.path(), None, None, 10, None, |batch| {
+            commits.extend_from_slice(batch);
+            Ok(())
+        })
+        .unwrap();
+        assert!(commits[0].renames.is_empty());
+        assert_eq!(
+            (commits[0].insertions, commits[0].deletions),
+            (Some(20), Some(0))
+        );
+        assert_eq!(
+            (commits[1].insertions, commits[1].deletions),
+            (Some(0), Some(0))
+        );
+        assert_eq!(
+            commits[1].renames,
+            [(
+                "src/old/file.cpp".to_string(),
+                "src/new/file.cpp".to_string()
+            )]
+        );
+        assert_eq!(
+            commits[1].changed_paths,
+            ["src/old/file.cpp", "src/new/file.cpp"]
+        );
+
+        // Backfill recounts stored commits and skips ones the clone lacks
+        let shas = [commits[0].sha.clone(), "0".repeat(40)];
+        assert_eq!(
+            commit_churn(dir.path(), &shas).unwrap(),
+            [(commits[0].sha.clone(), 20, 0)]
+        );
+    }
+}
@@ -1,17 +1,75 @@
+use crate::app_auth::AppAuth;
+use crate::config::{CommitOrder, ReviewsScope};
 use crate::errors::{DataError, Result};
+use crate::rawdump::{self, RawPage, RawResponses};
+use crate::retry::RetryBudget;
+use crate::throttle::Throttle;
 use chrono::{DateTime, Utc};
-use reqwest::blocking::Client;
+use regex::Regex;
+use reqwest::blocking::{Client, Response};
 use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-const GITHUB_API_BASE_URL: &str = "https://api.github.com";
+pub(crate) const GITHUB_API_BASE_URL: &str = "https://api.github.com";
+const GITHUB_GRAPHQL_URL: &str = "https://api.github.com/graphql";
+
+// Media types for the REST API's JSON and unified diff representations.
+const JSON_MEDIA_TYPE: &str = "application/vnd.github.v3+json";
+pub const DIFF_MEDIA_TYPE: &str = "application/vnd.github.v3.diff";
+
+// Review threads per PR, paginated over the repository's pull requests.
+// PRs with more than 100 threads are continued with PR_REVIEW_THREADS_QUERY.
+const REVIEW_THREADS_QUERY: &str = r"
+query($owner: String!, $name: String!, $cursor: String) {
+  repository(owner: $owner, name: $name) {
+    pullRequests(first: 50, after: $cursor) {
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        number
+        reviewThreads(first: 100) {
+          pageInfo { hasNextPage endCursor }
+          nodes { isResolved }
+        }
+      }
+    }
+  }
+}
+";
+
+// The review threads of one PR after `cursor`.
+const PR_REVIEW_THREADS_QUERY: &str = r"
+query($owner: String!, $name: String!, $number: Int!, $cursor: String) {
+  repository(owner: $owner, name: $name) {
+    pullRequest(number: $number) {
+      reviewThreads(first: 100, after: $cursor) {
+        pageInfo { hasNextPage endCursor }
+        nodes { isResolved }
+      }
+    }
+  }
+}
+";
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct GitHubUser {
     pub login: String,
     pub id: i64,
-    // Add other user fields if needed e.g., type, avatar_url
+    /// `User`, `Bot` or `Organization`
+    #[serde(rename = "type", default)]
+    pub user_type: Option<String>,
+}
+
+impl GitHubUser {
+    /// Whether this is an app or bot account. Some bot payloads omit `type`,
+    /// so the `[bot]` login suffix counts too.
+    pub fn is_bot(&self) -> bool {
+        self.user_type.as_deref() == Some("Bot") || self.login.ends_with("[bot]")
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -29,6 +87,14 @@ pub struct GitCommit {
     // pub tree: Option<GitTree>,
     pub url: Option<String>,
     pub comment_count: Option<i64>,
+    pub verification: Option<CommitVerification>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CommitVerification {
+    pub verified: bool,
+    pub reason: Option<String>, // e.g., "valid", "unsigned", "unknown_key"
+    pub signature: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -38,10 +104,220 @@ pub struct GitHubCommit {
     pub url: String, // API URL for this commit
     pub html_url: String,
     pub comments_url: String,
-    pub author: Option<GitHubUser>, // GitHub user if available
+    pub author: Option<GitHubUser>,    // GitHub user if available
     pub committer: Option<GitHubUser>, // GitHub user if available
-                                    // pub parents: Vec<CommitParent>,
-                                    // pub stats: Option<CommitStats>,
+    #[serde(default)]
+    pub parents: Vec<CommitParent>,
+    // pub stats: Option<CommitStats>,
+}
+
+/// Filters for listing commits; unset fields are left out of the request.
+#[derive(Debug, Clone, Default)]
+pub struct CommitFilter {
+    /// ISO 8601 timestamps (YYYY-MM-DDTHH:MM:SSZ)
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Branch or SHA to list from; the default branch when unset
+    pub sha: Option<String>,
+    /// GitHub login or email address of the commit author
+    pub author: Option<String>,
+}
+
+/// Filters for listing issues; unset fields are left out of the request.
+#[derive(Debug, Clone, Default)]
+pub struct IssueFilter {
+    /// "open", "closed" or "all"; all when unset
+    pub state: Option<String>,
+    /// "assigned", "created", "mentioned", "subscribed" or "all"
+    pub filter: Option<String>,
+    /// ISO 8601 timestamp; only issues updated since then are listed
+    pub since: Option<String>,
+    /// Only issues carrying every listed label are returned
+    pub labels: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CommitParent {
+    pub sha: String,
+}
+
+/// The head or base branch of a pull request.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BranchInfo {
+    pub sha: String,
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+}
+
+impl GitHubCommit {
+    /// Committer date of the underlying git commit, if present.
+    pub fn committer_date(&self) -> Option<DateTime<Utc>> {
+        self.commit.committer.as_ref().and_then(|c| c.date)
+    }
+
+    /// Author date of the underlying git commit, if present.
+    pub fn author_date(&self) -> Option<DateTime<Utc>> {
+        self.commit.author.as_ref().and_then(|a| a.date)
+    }
+
+    /// Timestamp stored for the commit: the committer date, since it usually
+    /// reflects when the commit entered the repository, or the author date
+    /// when the committer date is missing.
+    pub fn commit_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.committer_date().or_else(|| self.author_date())
+    }
+
+    /// Whether this is a merge commit (two or more parents).
+    pub fn is_merge(&self) -> bool {
+        self.parents.len() >= 2
+    }
+}
+
+/// Sorts fetched commits in place so that inserts happen in a reproducible order.
+pub fn sort_commits(commits: &mut [GitHubCommit], order: CommitOrder) {
+    match order {
+        CommitOrder::Api => {}
+        CommitOrder::Date => commits.sort_by(|a, b| {
+            a.commit_timestamp()
+                .cmp(&b.commit_timestamp())
+                .then_with(|| a.sha.cmp(&b.sha))
+        }),
+        CommitOrder::Sha => commits.sort_by(|a, b| a.sha.cmp(&b.sha)),
+    }
+}
+
+/// Response of the compare endpoint (`base...head`).
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubComparison {
+    pub status: String, // "ahead", "behind", "diverged" or "identical"
+    pub ahead_by: i64,
+    pub behind_by: i64,
+    pub total_commits: i64,
+    pub commits: Vec<GitHubCommit>,
+}
+
+impl GitHubComparison {
+    /// Whether fewer commits were fetched than the range contains.
+    pub fn is_truncated(&self) -> bool {
+        (self.commits.len() as i64) < self.total_commits
+    }
+}
+
+/// Combined commit status (`/commits/{sha}/status`): the legacy status API
+/// used by external CI services.
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubCombinedStatus {
+    pub state: String, // "success", "failure" or "pending"
+    pub statuses: Vec<GitHubStatus>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubStatus {
+    pub context: String,
+    pub state: String, // "error", "failure", "pending" or "success"
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Check runs of a commit (`/commits/{sha}/check-runs`), used by GitHub Actions.
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubCheckRuns {
+    pub total_count: i64,
+    pub check_runs: Vec<GitHubCheckRun>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubCheckRun {
+    pub name: String,
+    pub status: String,             // "queued", "in_progress" or "completed"
+    pub conclusion: Option<String>, // set once completed: "success", "failure", ...
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Resolved vs unresolved review thread counts for one pull request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewThreadCounts {
+    pub pr_number: i64,
+    pub resolved: i64,
+    pub unresolved: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    errors: Option<Vec<GraphQlError>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReviewThreadsData {
+    repository: Option<ReviewThreadsRepository>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadsRepository {
+    pull_requests: ReviewThreadsConnection,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadsConnection {
+    page_info: PageInfo,
+    nodes: Vec<ReviewThreadsPullRequest>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadsPullRequest {
+    number: i64,
+    review_threads: ReviewThreadNodes,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadNodes {
+    page_info: PageInfo,
+    nodes: Vec<ReviewThreadNode>,
+}
+
+impl ReviewThreadNodes {
+    fn resolved(&self) -> i64 {
+        self.nodes.iter().filter(|t| t.is_resolved).count() as i64
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct PullRequestThreadsData {
+    repository: Option<PullRequestThreadsRepository>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestThreadsRepository {
+    pull_request: Option<ReviewThreadsOfPullRequest>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadsOfPullRequest {
+    review_threads: ReviewThreadNodes,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadNode {
+    is_resolved: bool,
 }
 
 #[derive(Deserialize, Debug)]
@@ -60,7 +336,17 @@ pub struct RepoInfo {
     pub open_issues_count: i32,
     pub default_branch: String,
     pub language: Option<String>,
-    // Add more fields as needed, e.g., license
+    /// `None` when GitHub detects no license
+    pub license: Option<RepoLicense>,
+}
+
+/// License GitHub detected for a repository.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RepoLicense {
+    pub key: String,
+    pub name: String,
+    /// `NOASSERTION` for licenses GitHub can't identify; `None` if absent
+    pub spdx_id: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -81,8 +367,8 @@ pub struct GitHubPullRequest {
     // pub assignees: Vec<GitHubUser>,
     // pub requested_reviewers: Vec<GitHubUser>,
     // pub labels: Vec<GitHubLabel>,
-    // pub head: Option<BranchInfo>,
-    // pub base: Option<BranchInfo>,
+    pub head: Option<BranchInfo>,
+    pub base: Option<BranchInfo>,
     // pub comments: Option<i64>, // Often needs separate fetch
     // pub review_comments: Option<i64>, // Often needs separate fetch
     // pub commits: Option<i64>, // Often needs separate fetch
@@ -90,21 +376,38 @@ pub struct GitHubPullRequest {
     // pub deletions: Option<i64>, // Often needs separate fetch
     // pub changed_files: Option<i64>, // Often needs separate fetch
     pub merged: Option<bool>,
+    // The mergeability fields are only computed on single-PR fetches;
+    // the list endpoint returns them as null.
     pub mergeable: Option<bool>,
     pub rebaseable: Option<bool>,
     pub mergeable_state: Option<String>,
+    // Only returned by the single-PR endpoint, not the list
     pub merged_by: Option<GitHubUser>,
+    pub milestone: Option<GitHubMilestone>,
     pub comments_url: String,
     pub review_comments_url: String,
     pub statuses_url: String,
 }
 
+impl GitHubPullRequest {
+    /// Whether the per-PR reviews pass covers this PR under `scope`.
+    pub fn in_reviews_scope(&self, scope: ReviewsScope) -> bool {
+        match scope {
+            ReviewsScope::All => true,
+            ReviewsScope::Merged => self.merged_at.is_some(),
+            ReviewsScope::Closed => self.state == "closed",
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct GitHubIssue {
     pub id: i64,
     pub number: i64,
     pub html_url: String,
     pub state: String, // "open" or "closed"
+    /// "completed", "not_planned" or "reopened"; absent on older issues
+    pub state_reason: Option<String>,
     pub title: String,
     pub user: Option<GitHubUser>,
     pub labels: Vec<GitHubLabel>, // Assuming GitHubLabel struct exists or will be added
@@ -116,8 +419,62 @@ pub struct GitHubIssue {
     pub updated_at: DateTime<Utc>,
     pub closed_at: Option<DateTime<Utc>>,
     pub body: Option<String>,
-    pub closed_by: Option<GitHubUser>,
-    // pub pull_request: Option<IssuePullRequest>, // Link if issue is also a PR
+    pub closed_by: Option<GitHubUser>, // Usually only present on single-issue fetches
+    pub milestone: Option<GitHubMilestone>,
+    pub pull_request: Option<IssuePullRequest>, // Link if issue is also a PR
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubMilestone {
+    pub id: i64,
+    pub number: i64,
+    pub title: String,
+    pub state: String, // "open" or "closed"
+    pub open_issues: i64,
+    pub closed_issues: i64,
+    pub due_on: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IssuePullRequest {
+    pub url: Option<String>,
+}
+
+/// A page of search API results.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SearchResults<T> {
+    pub total_count: i64,
+    pub incomplete_results: bool,
+    pub items: Vec<T>,
+}
+
+/// Budgets returned by `/rate_limit`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitStatus {
+    pub resources: RateLimitResources,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitResources {
+    pub core: RateLimitBudget,
+    pub search: Option<RateLimitBudget>,
+    pub graphql: Option<RateLimitBudget>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct RateLimitBudget {
+    pub limit: i64,
+    pub remaining: i64,
+    /// Unix time at which the budget refills
+    pub reset: i64,
+}
+
+/// The fields of an issue search hit that are needed to look the item up.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SearchIssue {
+    pub number: i64,
 }
 
 #[derive(Deserialize, Debug, Clone)] // Simple label struct
@@ -128,6 +485,197 @@ pub struct GitHubLabel {
     pub description: Option<String>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubReview {
+    pub id: i64,
+    pub user: Option<GitHubUser>,
+    pub state: String, // e.g., "APPROVED", "CHANGES_REQUESTED", "COMMENTED"
+    // Pending reviews have not been submitted yet and carry no timestamp
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubComment {
+    pub id: i64,
+    pub user: Option<GitHubUser>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user with access to a repository, from `/repos/{owner}/{repo}/collaborators`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubCollaborator {
+    pub login: String,
+    pub id: i64,
+    pub permissions: Option<CollaboratorPermissions>,
+    pub role_name: Option<String>,
+}
+
+/// An entry of a repository's activity feed, from `/repos/{owner}/{repo}/events`.
+/// GitHub only keeps about the last 90 days, and at most 300 events.
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitHubEvent {
+    pub id: String,
+    pub actor: Option<GitHubUser>,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: RepoEventKind,
+}
+
+/// The type of an event and its payload, whose shape depends on the type.
+/// Types not modelled here are read as `Other`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum RepoEventKind {
+    PushEvent {
+        payload: PushPayload,
+    },
+    PullRequestEvent {
+        payload: PullRequestEventPayload,
+    },
+    PullRequestReviewEvent {
+        payload: ReviewEventPayload,
+    },
+    PullRequestReviewCommentEvent {
+        payload: ReviewEventPayload,
+    },
+    IssuesEvent {
+        payload: IssueEventPayload,
+    },
+    IssueCommentEvent {
+        payload: IssueEventPayload,
+    },
+    ReleaseEvent {
+        payload: ReleaseEventPayload,
+    },
+    CreateEvent {
+        payload: RefEventPayload,
+    },
+    DeleteEvent {
+        payload: RefEventPayload,
+    },
+    ForkEvent {},
+    WatchEvent {},
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PushPayload {
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PullRequestEventPayload {
+    pub action: String,
+    pub number: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReviewEventPayload {
+    pub action: String,
+    pub pull_request: EventItem,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IssueEventPayload {
+    pub action: String,
+    pub issue: EventItem,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseEventPayload {
+    pub action: String,
+    pub release: EventRelease,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RefEventPayload {
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    pub ref_type: String, // "branch", "tag" or "repository"
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EventItem {
+    pub number: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EventRelease {
+    pub tag_name: String,
+}
+
+impl RepoEventKind {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            RepoEventKind::PushEvent { .. } => "PushEvent",
+            RepoEventKind::PullRequestEvent { .. } => "PullRequestEvent",
+            RepoEventKind::PullRequestReviewEvent { .. } => "PullRequestReviewEvent",
+            RepoEventKind::PullRequestReviewCommentEvent { .. } => "PullRequestReviewCommentEvent",
+            RepoEventKind::IssuesEvent { .. } => "IssuesEvent",
+            RepoEventKind::IssueCommentEvent { .. } => "IssueCommentEvent",
+            RepoEventKind::ReleaseEvent { .. } => "ReleaseEvent",
+            RepoEventKind::CreateEvent { .. } => "CreateEvent",
+            RepoEventKind::DeleteEvent { .. } => "DeleteEvent",
+            RepoEventKind::ForkEvent {} => "ForkEvent",
+            RepoEventKind::WatchEvent {} => "WatchEvent",
+            RepoEventKind::Other => "Other",
+        }
+    }
+
+    /// What happened, e.g. "opened" or "closed"; `None` for pushes, refs,
+    /// forks and stars.
+    pub fn action(&self) -> Option<&str> {
+        match self {
+            RepoEventKind::PullRequestEvent { payload } => Some(&payload.action),
+            RepoEventKind::PullRequestReviewEvent { payload }
+            | RepoEventKind::PullRequestReviewCommentEvent { payload } => Some(&payload.action),
+            RepoEventKind::IssuesEvent { payload }
+            | RepoEventKind::IssueCommentEvent { payload } => Some(&payload.action),
+            RepoEventKind::ReleaseEvent { payload } => Some(&payload.action),
+            _ => None,
+        }
+    }
+
+    /// Number of the PR or issue the event is about.
+    pub fn number(&self) -> Option<i64> {
+        match self {
+            RepoEventKind::PullRequestEvent { payload } => Some(payload.number),
+            RepoEventKind::PullRequestReviewEvent { payload }
+            | RepoEventKind::PullRequestReviewCommentEvent { payload } => {
+                Some(payload.pull_request.number)
+            }
+            RepoEventKind::IssuesEvent { payload }
+            | RepoEventKind::IssueCommentEvent { payload } => Some(payload.issue.number),
+            _ => None,
+        }
+    }
+
+    /// The pushed, created or deleted ref, or the tag of a release.
+    pub fn ref_name(&self) -> Option<&str> {
+        match self {
+            RepoEventKind::PushEvent { payload } => payload.git_ref.as_deref(),
+            RepoEventKind::CreateEvent { payload } | RepoEventKind::DeleteEvent { payload } => {
+                payload.git_ref.as_deref()
+            }
+            RepoEventKind::ReleaseEvent { payload } => Some(&payload.release.tag_name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct CollaboratorPermissions {
+    pub admin: bool,
+    #[serde(default)]
+    pub maintain: bool,
+    pub push: bool,
+    #[serde(default)]
+    pub triage: bool,
+    pub pull: bool,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct GitHubContributor {
     pub login: String,
@@ -139,157 +687,700 @@ pub struct GitHubContributor {
     // Add other fields if needed (avatar_url, etc.)
 }
 
+/// An API path template routed somewhere other than api.github.com, given as
+/// `TEMPLATE=TARGET`. `{name}` segments of the template match any one path
+/// segment, e.g. `/repos/{owner}/{repo}/commits`. The target is either a URL
+/// template using the same placeholders or a base URL that the request path
+/// is appended to. Query strings are kept. The token is only sent to other
+/// hosts when the target ends in `+auth`, and then only over https.
+#[derive(Debug, Clone)]
+pub struct EndpointOverride {
+    template: String,
+    pattern: Regex,
+    target: String,
+    auth: bool,
+}
+
+impl EndpointOverride {
+    pub fn parse(spec: &str) -> std::result::Result<Self, String> {
+        let (template, target) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected TEMPLATE=URL, got '{}'", spec))?;
+        let target = target.trim();
+        let (target, auth) = match target.strip_suffix("+auth") {
+            Some(target) => (target, true),
+            None => (target, false),
+        };
+        let (template, target) = (template.trim(), target.trim_end_matches('/'));
+        if !template.starts_with('/') || target.is_empty() {
+            return Err(format!(
+                "expected an API path starting with / and a URL, got '{}'",
+                spec
+            ));
+        }
+        if auth && !target.starts_with("https://") {
+            return Err(format!(
+                "refusing to send the token to '{}' over plain http; use an https:// URL",
+                target
+            ));
+        }
+        let placeholder = Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+        let mut pattern = String::from("^");
+        let mut last = 0;
+        for found in placeholder.captures_iter(template) {
+            let whole = found.get(0).unwrap();
+            pattern.push_str(&regex::escape(&template[last..whole.start()]));
+            pattern.push_str(&format!("(?P<{}>[^/]+)", &found[1]));
+            last = whole.end();
+        }
+        pattern.push_str(&regex::escape(&template[last..]));
+        pattern.push('$');
+        let pattern =
+            Regex::new(&pattern).map_err(|e| format!("invalid template '{}': {}", template, e))?;
+        Ok(Self {
+            template: template.to_string(),
+            pattern,
+            target: target.to_string(),
+            auth,
+        })
+    }
+
+    // The overridden URL of an API request, if its path matches the template.
+    fn apply(&self, url: &str) -> Option<String> {
+        let rest = url.strip_prefix(GITHUB_API_BASE_URL)?;
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+        let captures = self.pattern.captures(path)?;
+        let mut routed = if self.target.contains('{') {
+            let mut target = self.target.clone();
+            for name in self.pattern.capture_names().flatten() {
+                target = target.replace(&format!("{{{}}}", name), &captures[name]);
+            }
+            target
+        } else {
+            format!("{}{}", self.target, path)
+        };
+        if let Some(query) = query {
+            routed.push('?');
+            routed.push_str(query);
+        }
+        Some(routed)
+    }
+}
+
 pub struct GitHubClient {
     client: Client,
     token: Option<String>,
+    app_auth: Option<AppAuth>,
+    max_rate_limit_wait: Duration,
+    throttle: Throttle,
+    raw: Option<RawResponses>,
+    lenient: bool,
+    // List records dropped by lenient parsing
+    skipped_records: AtomicUsize,
+    retry_budget: Arc<RetryBudget>,
+    endpoint_overrides: Vec<EndpointOverride>,
+    max_response_bytes: Option<u64>,
+    // Endpoint paths already warned about as deprecated, to warn once per run
+    deprecations_seen: Mutex<HashSet<String>>,
 }
 
+// The search API never returns more than this many results for a query.
+const SEARCH_RESULT_LIMIT: i64 = 1000;
+
+// How many times a single request is retried after waiting out a rate limit.
+const MAX_RATE_LIMIT_RETRIES: usize = 3;
+
 impl GitHubClient {
     pub fn new(token: Option<String>) -> Result<Self> {
-        let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
-        Ok(Self { client, token })
+        // GitHub answers requests for renamed/transferred repositories with a
+        // 301 to the new location; follow those explicitly rather than relying
+        // on the default policy.
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .redirect(reqwest::redirect::Policy::limited(10))
+            .build()?;
+        if let Some(token) = &token {
+            crate::redact::register(token);
+        }
+        Ok(Self {
+            client,
+            token,
+            app_auth: None,
+            max_rate_limit_wait: Duration::ZERO,
+            throttle: Throttle::unlimited(),
+            raw: None,
+            lenient: false,
+            skipped_records: AtomicUsize::new(0),
+            retry_budget: Arc::new(RetryBudget::unlimited()),
+            endpoint_overrides: Vec::new(),
+            max_response_bytes: None,
+            deprecations_seen: Mutex::new(HashSet::new()),
+        })
     }
 
-    fn get<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T> {
-        log::debug!("Sending GET request to: {}", url);
-        let mut request_builder = self
-            .client
-            .get(url)
-            .header(USER_AGENT, "corevsknots-data-loader")
-            .header(ACCEPT, "application/vnd.github.v3+json");
-
-        if let Some(token) = &self.token {
-            request_builder = request_builder.header(AUTHORIZATION, format!("token {}", token));
-        }
+    /// The personal access token, if any; also used for git over HTTPS.
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
 
-        let response = request_builder.send()?;
+    /// Authenticates as a GitHub App installation instead of with the
+    /// personal access token.
+    pub fn with_app_auth(mut self, app_auth: AppAuth) -> Self {
+        self.app_auth = Some(app_auth);
+        self
+    }
 
-        if response.status().is_success() {
-            let body = response.json::<T>()?;
-            Ok(body)
-        } else {
-            let status = response.status();
-            let error_text = response
-                .text()
-                .unwrap_or_else(|_| "Failed to read error body".to_string());
-            log::error!("GitHub API Error: {} - {}", status, error_text);
-            if status == reqwest::StatusCode::FORBIDDEN
-                && error_text.contains("rate limit exceeded")
-            {
-                Err(DataError::RateLimitError)
-            } else if status == reqwest::StatusCode::NOT_FOUND {
-                Err(DataError::NotFoundError)
-            } else {
-                Err(DataError::GitHubApiError {
-                    status,
-                    message: error_text,
-                })
-            }
+    // Token sent with each request: the current installation token when
+    // authenticating as an app, otherwise the personal access token.
+    fn auth_token(&self) -> Result<Option<String>> {
+        match &self.app_auth {
+            Some(app_auth) => app_auth.token(&self.client).map(Some),
+            None => Ok(self.token.clone()),
         }
     }
 
-    fn get_paginated<T: for<'de> Deserialize<'de> + Clone>(&self, url: &str) -> Result<Vec<T>> {
-        let mut all_items: Vec<T> = Vec::new();
-        let mut next_page_url = Some(url.to_string());
-        let per_page = 100; // Max allowed by GitHub
+    /// Paces requests (concurrency, rate and jitter) through `throttle`.
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
 
-        while let Some(current_url) = next_page_url {
-            let full_url = format!("{}?per_page={}", current_url, per_page);
-            log::debug!("Fetching paginated data from: {}", full_url);
+    /// Saves raw response bodies to a directory, or replays them from one
+    /// instead of calling the API. Replayed requests must match the recorded
+    /// URLs exactly, so pin `--since` to a fixed date when recording.
+    pub fn with_raw_responses(mut self, raw: RawResponses) -> Self {
+        self.raw = Some(raw);
+        self
+    }
 
-            let mut request_builder = self
-                .client
-                .get(&full_url)
-                .header(USER_AGENT, "corevsknots-data-loader")
-                .header(ACCEPT, "application/vnd.github.v3+json");
+    /// Skips list records that fail to deserialize instead of failing the page.
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
 
-            if let Some(token) = &self.token {
-                request_builder = request_builder.header(AUTHORIZATION, format!("token {}", token));
-            }
+    /// How many list records lenient parsing has dropped so far.
+    pub fn skipped_records(&self) -> usize {
+        self.skipped_records.load(Ordering::Relaxed)
+    }
 
-            let response = request_builder.send()?;
+    /// Sleeps through rate limits whose reset is at most `max_wait` away
+    /// instead of failing with `RateLimitError`.
+    pub fn with_max_rate_limit_wait(mut self, max_wait: Duration) -> Self {
+        self.max_rate_limit_wait = max_wait;
+        self
+    }
 
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response
-                    .text()
-                    .unwrap_or_else(|_| "Failed to read error body".to_string());
-                log::error!(
-                    "GitHub API Error on paginated request: {} - {}",
-                    status,
-                    error_text
-                );
-                return if status == reqwest::StatusCode::FORBIDDEN
-                    && error_text.contains("rate limit exceeded")
-                {
-                    Err(DataError::RateLimitError)
-                } else if status == reqwest::StatusCode::NOT_FOUND {
-                    Err(DataError::NotFoundError)
-                } else {
-                    Err(DataError::GitHubApiError {
-                        status,
-                        message: error_text,
-                    })
-                };
-            }
+    /// Draws every retry wait from `budget`, which may be shared with other
+    /// clients of the same run.
+    pub fn with_retry_budget(mut self, budget: Arc<RetryBudget>) -> Self {
+        self.retry_budget = budget;
+        self
+    }
 
-            // Extract next page URL from Link header
-            next_page_url = response
-                .headers()
-                .get(reqwest::header::LINK)
-                .and_then(|link_header| link_header.to_str().ok())
-                .and_then(parse_link_header);
+    /// Routes requests matching these templates to other hosts; the first
+    /// match wins. Credentials only go to overrides that opted in with `+auth`.
+    pub fn with_endpoint_overrides(mut self, overrides: Vec<EndpointOverride>) -> Self {
+        self.endpoint_overrides = overrides;
+        self
+    }
 
-            let items = response.json::<Vec<T>>()?;
-            if items.is_empty() {
-                break; // No more items to fetch
-            }
-            all_items.extend(items.into_iter());
-        }
-        Ok(all_items)
+    /// Fails responses with bodies larger than `max_bytes` instead of
+    /// buffering them whole; unlimited when `None`.
+    pub fn with_max_response_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
     }
 
-    pub fn get_repo_info(&self, repo_owner: &str, repo_name: &str) -> Result<RepoInfo> {
+    // Reads the body of a response to `url` as text, stopping with
+    // `ResponseTooLarge` once it exceeds `max_response_bytes`.
+    fn read_body(&self, url: &str, response: Response) -> Result<String> {
+        let Some(limit) = self.max_response_bytes else {
+            return Ok(response.text()?);
+        };
+        let too_large = || DataError::ResponseTooLarge {
+            url: url.to_string(),
+            limit,
+        };
+        // Refuse announced oversized bodies without reading them
+        if response.content_length().is_some_and(|len| len > limit) {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        response.take(limit + 1).read_to_end(&mut body)?;
+        if body.len() as u64 > limit {
+            return Err(too_large());
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    // Warns, once per endpoint path, when GitHub announces that the endpoint
+    // answering `url` is deprecated or will be removed.
+    fn warn_deprecation(&self, url: &str, headers: &reqwest::header::HeaderMap) {
+        let Some(notice) = deprecation_notice(headers) else {
+            return;
+        };
+        let path = url.split('?').next().unwrap_or(url).to_string();
+        if self.deprecations_seen.lock().unwrap().insert(path.clone()) {
+            log::warn!(
+                "GitHub marks {} as {}; update the loader before it stops working",
+                path,
+                notice
+            );
+        }
+    }
+
+    // The URL a request for `url` is actually sent to, and whether the token
+    // may go with it: always for api.github.com, for other hosts only when
+    // the override opted in with `+auth`.
+    fn route(&self, url: &str) -> (String, bool) {
+        for endpoint in &self.endpoint_overrides {
+            if let Some(routed) = endpoint.apply(url) {
+                log::debug!("Routing {} to {} ({})", url, routed, endpoint.template);
+                let auth =
+                    endpoint.auth || routed.starts_with(&format!("{}/", GITHUB_API_BASE_URL));
+                return (routed, auth);
+            }
+        }
+        (url.to_string(), true)
+    }
+
+    // Sends a GET request asking for the `accept` media type and returns the
+    // successful response. Rate-limited requests are retried after the
+    // advertised reset when it is close enough; other error statuses are
+    // mapped to `DataError`s.
+    fn send(&self, url: &str, accept: &str) -> Result<Response> {
+        let (url, send_token) = self.route(url);
+        let url = &url;
+        let mut retries = 0;
+        loop {
+            log::debug!("Sending GET request to: {}", url);
+            let mut request_builder = self
+                .client
+                .get(url)
+                .header(USER_AGENT, "corevsknots-data-loader")
+                .header(ACCEPT, accept);
+
+            if send_token {
+                if let Some(token) = self.auth_token()? {
+                    request_builder =
+                        request_builder.header(AUTHORIZATION, format!("token {}", token));
+                }
+            }
+
+            let permit = self.throttle.acquire();
+            let response = request_builder.send()?;
+            drop(permit);
+            log_redirect(url, &response);
+            self.warn_deprecation(url, response.headers());
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+
+            let wait = rate_limit_reset_wait(response.headers());
+            let error_text = self
+                .read_body(url, response)
+                .unwrap_or_else(|_| "Failed to read error body".to_string());
+
+            if is_rate_limited(status, &error_text) {
+                match wait {
+                    Some(wait)
+                        if wait <= self.max_rate_limit_wait
+                            && retries < MAX_RATE_LIMIT_RETRIES
+                            && self.retry_budget.try_spend(wait) =>
+                    {
+                        retries += 1;
+                        log::warn!(
+                            "Rate limited on {}; waiting {}s before retrying",
+                            url,
+                            wait.as_secs()
+                        );
+                        std::thread::sleep(wait);
+                        continue;
+                    }
+                    _ => {
+                        log::error!("GitHub API rate limit exceeded: {}", error_text);
+                        return Err(DataError::RateLimitError);
+                    }
+                }
+            }
+
+            log::error!("GitHub API Error: {} - {}", status, error_text);
+            return Err(match status {
+                reqwest::StatusCode::NOT_FOUND => DataError::NotFoundError,
+                reqwest::StatusCode::GONE => DataError::GoneError,
+                reqwest::StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => {
+                    DataError::LegalUnavailableError
+                }
+                _ => DataError::GitHubApiError {
+                    status,
+                    message: error_text,
+                },
+            });
+        }
+    }
+
+    // Fetches the body and next-page link of a GET request, saving or
+    // replaying it when raw responses are configured.
+    fn fetch_page(&self, url: &str) -> Result<RawPage> {
+        if let Some(RawResponses::Replay(dir)) = &self.raw {
+            log::debug!("Replaying response for {}", url);
+            return rawdump::load(dir, url);
+        }
+        let response = self.send(url, JSON_MEDIA_TYPE)?;
+        let next = response
+            .headers()
+            .get(reqwest::header::LINK)
+            .and_then(|link_header| link_header.to_str().ok())
+            .and_then(parse_link_header);
+        let body = self.read_body(url, response)?;
+        let path = match &self.raw {
+            Some(RawResponses::Dump(dir)) => Some(rawdump::save(dir, url, &body, next.as_deref())?),
+            _ => None,
+        };
+        Ok(RawPage { body, next, path })
+    }
+
+    fn get<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T> {
+        self.fetch_page(url)?.parse()
+    }
+
+    /// Fetches a non-JSON representation, such as a diff (`accept` is its
+    /// media type), and returns the body as text. Like JSON responses, the
+    /// body is saved or replayed when raw responses are configured; the key
+    /// includes the media type, since the same URL also serves JSON.
+    pub fn get_text(&self, url: &str, accept: &str) -> Result<String> {
+        let key = format!("{} {}", url, accept);
+        if let Some(RawResponses::Replay(dir)) = &self.raw {
+            log::debug!("Replaying response for {}", key);
+            return Ok(rawdump::load(dir, &key)?.body);
+        }
+        let body = self.read_body(url, self.send(url, accept)?)?;
+        if let Some(RawResponses::Dump(dir)) = &self.raw {
+            rawdump::save(dir, &key, &body, None)?;
+        }
+        Ok(body)
+    }
+
+    fn get_paginated<T: for<'de> Deserialize<'de> + Clone>(&self, url: &str) -> Result<Vec<T>> {
+        self.get_paginated_limited(url, None)
+            .map(|(items, _)| items)
+    }
+
+    // Like `get_paginated`, but stops once `max_items` items have been
+    // collected. The flag is set when items beyond the cap were left unfetched.
+    fn get_paginated_limited<T: for<'de> Deserialize<'de> + Clone>(
+        &self,
+        url: &str,
+        max_items: Option<usize>,
+    ) -> Result<(Vec<T>, bool)> {
+        let mut all_items: Vec<T> = Vec::new();
+        let per_page = 100; // Max allowed by GitHub
+        let separator = if url.contains('?') { '&' } else { '?' };
+        // Subsequent pages come from the Link header, which keeps per_page
+        let mut next_page_url = Some(format!("{}{}per_page={}", url, separator, per_page));
+
+        while let Some(current_url) = next_page_url {
+            log::debug!("Fetching paginated data from: {}", current_url);
+            let page = self.fetch_page(&current_url)?;
+            // Next page URL from the Link header
+            next_page_url = page.next.clone();
+
+            let (items, skipped) = page.parse_items::<T>(self.lenient)?;
+            if items.is_empty() && skipped == 0 {
+                break; // No more items to fetch
+            }
+            // A page whose records were all malformed still has a next page
+            self.skipped_records.fetch_add(skipped, Ordering::Relaxed);
+            all_items.extend(items);
+
+            if let Some(max) = max_items {
+                if all_items.len() >= max {
+                    let truncated = all_items.len() > max || next_page_url.is_some();
+                    all_items.truncate(max);
+                    return Ok((all_items, truncated));
+                }
+            }
+        }
+        Ok((all_items, false))
+    }
+
+    // Runs a GraphQL query. The GraphQL API always requires authentication.
+    fn graphql<T: for<'de> Deserialize<'de>>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T> {
+        let request = serde_json::json!({ "query": query, "variables": variables });
+        // Every query goes to the same URL, so recordings are keyed by the request too
+        let key = format!("{} {}", GITHUB_GRAPHQL_URL, request);
+        let page = match &self.raw {
+            Some(RawResponses::Replay(dir)) => rawdump::load(dir, &key)?,
+            raw => {
+                let body = self.post_graphql(&request)?;
+                let path = match raw {
+                    Some(RawResponses::Dump(dir)) => Some(rawdump::save(dir, &key, &body, None)?),
+                    _ => None,
+                };
+                RawPage {
+                    body,
+                    next: None,
+                    path,
+                }
+            }
+        };
+
+        let body = page.parse::<GraphQlResponse<T>>()?;
+        if let Some(errors) = body.errors.filter(|e| !e.is_empty()) {
+            let messages: Vec<String> = errors.into_iter().map(|e| e.message).collect();
+            return Err(DataError::Other(format!(
+                "GraphQL error: {}",
+                messages.join("; ")
+            )));
+        }
+        body.data
+            .ok_or_else(|| DataError::Other("GraphQL response had no data".to_string()))
+    }
+
+    // Sends a GraphQL request and returns the raw body of a successful response.
+    fn post_graphql(&self, request: &serde_json::Value) -> Result<String> {
+        let (url, send_token) = self.route(GITHUB_GRAPHQL_URL);
+        let mut request_builder = self
+            .client
+            .post(&url)
+            .header(USER_AGENT, "corevsknots-data-loader")
+            .json(request);
+        if send_token {
+            let token = self.auth_token()?.ok_or_else(|| {
+                DataError::ConfigError("The GitHub GraphQL API requires a token".to_string())
+            })?;
+            request_builder = request_builder.header(AUTHORIZATION, format!("token {}", token));
+        }
+        log::debug!("Sending GraphQL request to: {}", url);
+        let permit = self.throttle.acquire();
+        let response = request_builder.send()?;
+        drop(permit);
+        self.warn_deprecation(&url, response.headers());
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = self
+                .read_body(&url, response)
+                .unwrap_or_else(|_| "Failed to read error body".to_string());
+            log::error!("GitHub GraphQL Error: {} - {}", status, error_text);
+            return Err(DataError::GitHubApiError {
+                status,
+                message: error_text,
+            });
+        }
+        self.read_body(&url, response)
+    }
+
+    // Fetches resolved/unresolved review thread counts for every pull request.
+    // REST does not expose thread resolution, hence GraphQL.
+    pub fn get_review_thread_counts(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+    ) -> Result<Vec<ReviewThreadCounts>> {
+        let mut counts = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let data: ReviewThreadsData = self.graphql(
+                REVIEW_THREADS_QUERY,
+                serde_json::json!({ "owner": repo_owner, "name": repo_name, "cursor": cursor }),
+            )?;
+            let prs = data
+                .repository
+                .ok_or(DataError::NotFoundError)?
+                .pull_requests;
+            for pr in prs.nodes {
+                let mut threads = pr.review_threads;
+                let mut resolved = threads.resolved();
+                let mut total = threads.nodes.len() as i64;
+                while threads.page_info.has_next_page {
+                    threads = self.get_more_review_threads(
+                        repo_owner,
+                        repo_name,
+                        pr.number,
+                        threads.page_info.end_cursor.as_deref(),
+                    )?;
+                    resolved += threads.resolved();
+                    total += threads.nodes.len() as i64;
+                }
+                counts.push(ReviewThreadCounts {
+                    pr_number: pr.number,
+                    resolved,
+                    unresolved: total - resolved,
+                });
+            }
+            if !prs.page_info.has_next_page {
+                break;
+            }
+            cursor = prs.page_info.end_cursor;
+        }
+        Ok(counts)
+    }
+
+    // The next page of a PR's review threads.
+    fn get_more_review_threads(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        number: i64,
+        cursor: Option<&str>,
+    ) -> Result<ReviewThreadNodes> {
+        let data: PullRequestThreadsData = self.graphql(
+            PR_REVIEW_THREADS_QUERY,
+            serde_json::json!({
+                "owner": repo_owner, "name": repo_name, "number": number, "cursor": cursor
+            }),
+        )?;
+        Ok(data
+            .repository
+            .and_then(|repo| repo.pull_request)
+            .ok_or(DataError::NotFoundError)?
+            .review_threads)
+    }
+
+    pub fn get_repo_info(&self, repo_owner: &str, repo_name: &str) -> Result<RepoInfo> {
         let url = format!("{}/repos/{}/{}", GITHUB_API_BASE_URL, repo_owner, repo_name);
         self.get(&url)
     }
 
-    // Fetches commits for a repository.
-    // `since` and `until` should be ISO 8601 timestamps (YYYY-MM-DDTHH:MM:SSZ)
+    // Fetches commits for a repository matching `filter`.
+    // At most `max_commits` (newest first) are returned; the flag reports
+    // whether the cap cut the listing short.
     pub fn get_commits(
         &self,
         repo_owner: &str,
         repo_name: &str,
-        since: Option<String>,
-        until: Option<String>,
-        branch_or_sha: Option<String>,
-    ) -> Result<Vec<GitHubCommit>> {
+        filter: &CommitFilter,
+        max_commits: Option<usize>,
+    ) -> Result<(Vec<GitHubCommit>, bool)> {
         let mut url = format!(
             "{}/repos/{}/{}/commits",
             GITHUB_API_BASE_URL, repo_owner, repo_name
         );
         let mut params: Vec<String> = Vec::new();
-        if let Some(s) = since {
+        if let Some(s) = &filter.since {
             params.push(format!("since={}", s));
         }
-        if let Some(u) = until {
+        if let Some(u) = &filter.until {
             params.push(format!("until={}", u));
         }
-        if let Some(b) = branch_or_sha {
+        if let Some(b) = &filter.sha {
             params.push(format!("sha={}", b));
         }
+        if let Some(a) = &filter.author {
+            params.push(format!(
+                "author={}",
+                url::form_urlencoded::byte_serialize(a.as_bytes()).collect::<String>()
+            ));
+        }
 
         if !params.is_empty() {
             url.push('?');
             url.push_str(&params.join("&"));
         }
-        self.get_paginated(&url)
+        self.get_paginated_limited(&url, max_commits)
+    }
+
+    // Fetches the combined status for a commit. Only the latest status per
+    // context is included.
+    pub fn get_commit_statuses(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        sha: &str,
+    ) -> Result<GitHubCombinedStatus> {
+        let url = format!(
+            "{}/repos/{}/{}/commits/{}/status?per_page=100",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, sha
+        );
+        self.get(&url)
+    }
+
+    // Fetches the check runs for a commit. The API no longer needs the
+    // `antiope-preview` media type, so the standard v3 header is enough.
+    pub fn get_check_runs(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        sha: &str,
+    ) -> Result<Vec<GitHubCheckRun>> {
+        let url = format!(
+            "{}/repos/{}/{}/commits/{}/check-runs?per_page=100",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, sha
+        );
+        let runs: GitHubCheckRuns = self.get(&url)?;
+        if (runs.check_runs.len() as i64) < runs.total_count {
+            log::warn!(
+                "Commit {} has {} check runs; only the first {} were stored",
+                sha,
+                runs.total_count,
+                runs.check_runs.len()
+            );
+        }
+        Ok(runs.check_runs)
+    }
+
+    // Fetches the commits in `head` that are not in `base` via the compare endpoint.
+    // A single response embeds at most 250 commits, so the commit list is
+    // paginated; the comparison is truncated if pages still run out early.
+    pub fn compare_commits(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<GitHubComparison> {
+        let mut next_page_url = Some(format!(
+            "{}/repos/{}/{}/compare/{}...{}?per_page=100",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, base, head
+        ));
+        let mut comparison: Option<GitHubComparison> = None;
+        while let Some(current_url) = next_page_url {
+            let page = self.fetch_page(&current_url)?;
+            next_page_url = page.next.clone();
+            let part: GitHubComparison = page.parse()?;
+            if part.commits.is_empty() {
+                comparison.get_or_insert(part);
+                break;
+            }
+            match comparison.as_mut() {
+                Some(comparison) => comparison.commits.extend(part.commits),
+                None => comparison = Some(part),
+            }
+            if comparison.as_ref().is_some_and(|c| !c.is_truncated()) {
+                break;
+            }
+        }
+        let comparison = comparison.expect("the first page is always fetched");
+        if comparison.is_truncated() {
+            log::warn!(
+                "Compare {}...{} for {}/{} returned {} of {} commits",
+                base,
+                head,
+                repo_owner,
+                repo_name,
+                comparison.commits.len(),
+                comparison.total_commits
+            );
+        }
+        Ok(comparison)
     }
 
     // Fetches pull requests for a repository.
     // state can be "open", "closed", or "all"
     // sort can be "created", "updated", "popularity", "long-running"
     // direction can be "asc" or "desc"
+    // Stops after `max_prs` PRs; the flag is set when more were left unfetched.
     pub fn get_pull_requests(
         &self,
         repo_owner: &str,
@@ -297,7 +1388,8 @@ impl GitHubClient {
         state: Option<String>,
         sort: Option<String>,
         direction: Option<String>,
-    ) -> Result<Vec<GitHubPullRequest>> {
+        max_prs: Option<usize>,
+    ) -> Result<(Vec<GitHubPullRequest>, bool)> {
         let mut url = format!(
             "{}/repos/{}/{}/pulls",
             GITHUB_API_BASE_URL, repo_owner, repo_name
@@ -319,21 +1411,19 @@ impl GitHubClient {
             url.push('?');
             url.push_str(&params.join("&"));
         }
-        self.get_paginated(&url)
+        self.get_paginated_limited(&url, max_prs)
     }
 
-    // Fetches issues for a repository.
-    // state can be "open", "closed", or "all"
-    // filter can be "assigned", "created", "mentioned", "subscribed", "all"
-    // since: ISO 8601 timestamp
+    // Fetches issues for a repository. With `max_issues`, only that many of
+    // the most recently created are fetched; the flag is set when more were
+    // left unfetched.
     pub fn get_issues(
         &self,
         repo_owner: &str,
         repo_name: &str,
-        state: Option<String>,
-        filter: Option<String>,
-        since: Option<String>,
-    ) -> Result<Vec<GitHubIssue>> {
+        filter: &IssueFilter,
+        max_issues: Option<usize>,
+    ) -> Result<(Vec<GitHubIssue>, bool)> {
         let mut url = format!(
             "{}/repos/{}/{}/issues",
             GITHUB_API_BASE_URL, repo_owner, repo_name
@@ -342,20 +1432,239 @@ impl GitHubClient {
         // Default state is open, let's fetch all for comprehensive data
         params.push(format!(
             "state={}",
-            state.unwrap_or_else(|| "all".to_string())
+            filter.state.as_deref().unwrap_or("all")
         ));
-        if let Some(f) = filter {
+        // With `since`, GitHub sorts by update time unless told otherwise, so
+        // items updated mid-fetch move between pages and get skipped or
+        // repeated. Creation order is stable: new items only append. A capped
+        // sample wants the newest instead; repeats are dropped below.
+        if max_issues.is_some() {
+            params.push("sort=created&direction=desc".to_string());
+        } else {
+            params.push("sort=created&direction=asc".to_string());
+        }
+        if let Some(f) = &filter.filter {
             params.push(format!("filter={}", f));
         }
-        if let Some(s) = since {
+        if let Some(s) = &filter.since {
             params.push(format!("since={}", s));
         }
+        if !filter.labels.is_empty() {
+            let joined = filter.labels.join(",");
+            params.push(format!(
+                "labels={}",
+                url::form_urlencoded::byte_serialize(joined.as_bytes()).collect::<String>()
+            ));
+        }
 
         if !params.is_empty() {
             url.push('?');
             url.push_str(&params.join("&"));
         }
 
+        let (mut issues, truncated): (Vec<GitHubIssue>, bool) =
+            self.get_paginated_limited(&url, max_issues)?;
+        let mut seen = HashSet::new();
+        let fetched = issues.len();
+        issues.retain(|issue| seen.insert(issue.id));
+        if issues.len() < fetched {
+            log::warn!(
+                "Dropped {} issues of {}/{} listed on more than one page",
+                fetched - issues.len(),
+                repo_owner,
+                repo_name
+            );
+        }
+        Ok((issues, truncated))
+    }
+
+    // Fetches a single pull request, including its mergeability fields.
+    pub fn get_pull_request(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        number: i64,
+    ) -> Result<GitHubPullRequest> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls/{}",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, number
+        );
+        self.get(&url)
+    }
+
+    // Fetches the commits of a pull request, oldest first. GitHub lists at
+    // most 250.
+    pub fn get_pull_request_commits(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        number: i64,
+    ) -> Result<Vec<GitHubCommit>> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls/{}/commits?per_page=100",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, number
+        );
+        self.get_paginated(&url)
+    }
+
+    // Fetches a single commit by SHA.
+    pub fn get_commit(&self, repo_owner: &str, repo_name: &str, sha: &str) -> Result<GitHubCommit> {
+        let url = format!(
+            "{}/repos/{}/{}/commits/{}",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, sha
+        );
+        self.get(&url)
+    }
+
+    /// Unified diff of a commit against its first parent.
+    pub fn get_commit_patch(&self, repo_owner: &str, repo_name: &str, sha: &str) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}/commits/{}",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, sha
+        );
+        self.get_text(&url, DIFF_MEDIA_TYPE)
+    }
+
+    // Finds the numbers of pull requests carrying every one of `labels` (and
+    // opened by `author`, if given) via the search API.
+    pub fn search_pull_request_numbers(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        labels: &[String],
+        author: Option<&str>,
+    ) -> Result<Vec<i64>> {
+        let query = search_query(repo_owner, repo_name, "is:pr", labels, author);
+        let items: Vec<SearchIssue> = self.search_all("issues", &query)?;
+        Ok(items.into_iter().map(|item| item.number).collect())
+    }
+
+    // Finds the issues and PRs opened by `author` that carry every one of
+    // `labels` and were updated since `since`, via the search API. Results
+    // have the same shape as the issues list endpoint's.
+    pub fn search_issues_by_author(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        since: &str,
+        labels: &[String],
+        author: &str,
+    ) -> Result<Vec<GitHubIssue>> {
+        let qualifier = format!("updated:>={}", since);
+        let query = search_query(repo_owner, repo_name, &qualifier, labels, Some(author));
+        self.search_all("issues", &query)
+    }
+
+    // Every result of a search, page by page. Search returns at most 1000
+    // results per query.
+    fn search_all<T: for<'de> Deserialize<'de>>(&self, kind: &str, query: &str) -> Result<Vec<T>> {
+        let mut next_page_url = Some(format!(
+            "{}/search/{}?q={}&per_page=100",
+            GITHUB_API_BASE_URL,
+            kind,
+            url::form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>()
+        ));
+
+        let mut items = Vec::new();
+        while let Some(current_url) = next_page_url {
+            let page = self.fetch_page(&current_url)?;
+            next_page_url = page.next.clone();
+
+            let results = page.parse::<SearchResults<T>>()?;
+            if items.is_empty() && results.total_count > SEARCH_RESULT_LIMIT {
+                log::warn!(
+                    "Search '{}' matched {} results; only the first {} can be fetched",
+                    query,
+                    results.total_count,
+                    SEARCH_RESULT_LIMIT
+                );
+            }
+            if results.items.is_empty() {
+                break;
+            }
+            items.extend(results.items);
+        }
+        Ok(items)
+    }
+
+    // Number of results a search matches, from a single one-item page. `kind`
+    // is the search endpoint, e.g. "issues" or "commits". Search requests
+    // count against the separate (smaller) search rate limit.
+    pub fn search_total_count(&self, kind: &str, query: &str) -> Result<i64> {
+        let url = format!(
+            "{}/search/{}?q={}&per_page=1",
+            GITHUB_API_BASE_URL,
+            kind,
+            url::form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>()
+        );
+        let results: SearchResults<serde_json::Value> = self.get(&url)?;
+        Ok(results.total_count)
+    }
+
+    // Current rate limit budgets. Calling this endpoint is free.
+    pub fn get_rate_limit(&self) -> Result<RateLimitStatus> {
+        self.get(&format!("{}/rate_limit", GITHUB_API_BASE_URL))
+    }
+
+    // Fetches all reviews submitted on a pull request, oldest first.
+    pub fn get_pull_request_reviews(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        number: i64,
+    ) -> Result<Vec<GitHubReview>> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls/{}/reviews",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, number
+        );
+        self.get_paginated(&url)
+    }
+
+    // Fetches the conversation comments of an issue or PR, oldest first. Review
+    // comments on a PR's diff are not included.
+    pub fn get_issue_comments(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+        number: i64,
+    ) -> Result<Vec<GitHubComment>> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/comments",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, number
+        );
+        self.get_paginated(&url)
+    }
+
+    // Fetches a single issue. Unlike the list endpoint this includes `closed_by`.
+    pub fn get_issue(&self, repo_owner: &str, repo_name: &str, number: i64) -> Result<GitHubIssue> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}",
+            GITHUB_API_BASE_URL, repo_owner, repo_name, number
+        );
+        self.get(&url)
+    }
+
+    // Fetches the recent activity feed of a repository: about the last 90
+    // days, at most 300 events, newest first.
+    pub fn get_repo_events(&self, repo_owner: &str, repo_name: &str) -> Result<Vec<GitHubEvent>> {
+        let url = format!(
+            "{}/repos/{}/{}/events",
+            GITHUB_API_BASE_URL, repo_owner, repo_name
+        );
+        self.get_paginated(&url)
+    }
+
+    // Fetches the users with access to a repository and their permissions.
+    // Needs push access; others get a 403 (or 401 without a token).
+    pub fn get_collaborators(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+    ) -> Result<Vec<GitHubCollaborator>> {
+        let url = format!(
+            "{}/repos/{}/{}/collaborators?affiliation=all",
+            GITHUB_API_BASE_URL, repo_owner, repo_name
+        );
         self.get_paginated(&url)
     }
 
@@ -376,23 +1685,659 @@ impl GitHubClient {
         // GitHub docs mention a 202 Accepted response sometimes.
         // Need robust handling if contributor data is critical.
     }
+
+    // Fetches the bytes of code per language GitHub detected in a repository.
+    pub fn get_languages(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+    ) -> Result<BTreeMap<String, i64>> {
+        let url = format!(
+            "{}/repos/{}/{}/languages",
+            GITHUB_API_BASE_URL, repo_owner, repo_name
+        );
+        self.get(&url)
+    }
+
+    // Fetches all milestones of a repository, open and closed.
+    pub fn get_milestones(
+        &self,
+        repo_owner: &str,
+        repo_name: &str,
+    ) -> Result<Vec<GitHubMilestone>> {
+        let url = format!(
+            "{}/repos/{}/{}/milestones?state=all",
+            GITHUB_API_BASE_URL, repo_owner, repo_name
+        );
+        self.get_paginated(&url)
+    }
 }
 
-// Helper function to parse GitHub's Link header for pagination
+// Search query scoped to a repository: `qualifier` (e.g. "is:pr") plus an
+// AND-ed label qualifier per label and the author, if any.
+fn search_query(
+    repo_owner: &str,
+    repo_name: &str,
+    qualifier: &str,
+    labels: &[String],
+    author: Option<&str>,
+) -> String {
+    let mut query = format!("repo:{}/{} {}", repo_owner, repo_name, qualifier);
+    for label in labels {
+        query.push_str(&format!(" label:\"{}\"", label));
+    }
+    if let Some(author) = author {
+        query.push_str(&format!(" author:{}", author));
+    }
+    query
+}
+
+// GitHub signals both the primary and the secondary (abuse) rate limits with
+// 403 or 429 and a message mentioning the rate limit.
+fn is_rate_limited(status: reqwest::StatusCode, error_text: &str) -> bool {
+    (status == reqwest::StatusCode::FORBIDDEN || status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+        && error_text.to_lowercase().contains("rate limit")
+}
+
+// Time until a rate limit lifts, from `Retry-After` (secondary limits) or
+// `X-RateLimit-Reset` when no requests remain (primary limit).
+fn rate_limit_reset_wait(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(secs) = header("retry-after").and_then(|v| v.parse::<u64>().ok()) {
+        return Some(Duration::from_secs(secs));
+    }
+    if header("x-ratelimit-remaining") == Some("0") {
+        let reset = header("x-ratelimit-reset")?.parse::<i64>().ok()?;
+        let secs = (reset - Utc::now().timestamp()).max(0) as u64;
+        return Some(Duration::from_secs(secs + 1));
+    }
+    None
+}
+
+// Describes the `Deprecation` and `Sunset` headers of a response, e.g.
+// "deprecated since 2023-07-01, to be removed on 2024-01-01", or `None` when
+// neither is present. Deprecation is an `@<unix time>` date (RFC 9745) or
+// `true` in older drafts; Sunset is an HTTP date (RFC 8594). Values that
+// don't parse are shown as sent.
+fn deprecation_notice(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let deprecation = header("deprecation").map(|value| {
+        let since = value
+            .strip_prefix('@')
+            .and_then(|secs| secs.parse::<i64>().ok())
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|date| date.format("%Y-%m-%d").to_string());
+        match since {
+            Some(date) => format!("deprecated since {}", date),
+            None if value.eq_ignore_ascii_case("true") => "deprecated".to_string(),
+            None => format!("deprecated since {}", value),
+        }
+    });
+    let sunset = header("sunset").map(|value| {
+        let date = DateTime::parse_from_rfc2822(value)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|_| value.to_string());
+        format!("to be removed on {}", date)
+    });
+    match (deprecation, sunset) {
+        (Some(deprecation), Some(sunset)) => Some(format!("{}, {}", deprecation, sunset)),
+        (Some(notice), None) | (None, Some(notice)) => Some(notice),
+        (None, None) => None,
+    }
+}
+
+// Logs when a request ended up somewhere other than where it was sent,
+// which usually means the repository was renamed or moved.
+fn log_redirect(requested_url: &str, response: &reqwest::blocking::Response) {
+    let redirected = reqwest::Url::parse(requested_url)
+        .map(|url| &url != response.url())
+        .unwrap_or(false);
+    if redirected {
+        log::warn!(
+            "Request to {} was redirected to {}",
+            requested_url,
+            response.url()
+        );
+    }
+}
+
+// Finds the `rel="next"` URL in GitHub's Link header. Links are scanned by
+// their `<...>` delimiters rather than split on commas, since URLs may carry
+// commas (e.g. `labels=a,b`) and parameters may be spaced or quoted loosely.
 fn parse_link_header(link_header: &str) -> Option<String> {
-    link_header.split(',').find_map(|link_part| {
-        let parts: Vec<&str> = link_part.split(';').map(str::trim).collect();
-        if parts.len() == 2 && parts[1] == "rel=\"next\"" {
-            parts[0]
-                .trim_start_matches('<')
-                .trim_end_matches('>')
-                .to_string()
-                .into()
-        } else {
-            None
+    let mut rest = link_header;
+    while let Some(start) = rest.find('<') {
+        let end = start + rest[start..].find('>')?;
+        let url = &rest[start + 1..end];
+        let params_end = rest[end..].find('<').map_or(rest.len(), |i| end + i);
+        let is_next = rest[end + 1..params_end].split(';').any(|param| {
+            let Some((key, value)) = param.split_once('=') else {
+                return false;
+            };
+            let value = value.trim().trim_end_matches(',').trim().trim_matches('"');
+            key.trim() == "rel" && value.split_whitespace().any(|rel| rel == "next")
+        });
+        if is_next {
+            return Some(url.to_string());
         }
-    })
+        rest = &rest[params_end..];
+    }
+    None
 }
 
 // TODO: Add functions to fetch PRs, Issues, Reviews, Comments, Contributors, etc.
 // Each will need its own struct for deserialization and potentially specific query parameters.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit_json(author_date: Option<&str>, committer_date: Option<&str>) -> GitHubCommit {
+        let signature = |date: Option<&str>| serde_json::json!({ "name": "Alice", "email": "alice@example.com", "date": date });
+        serde_json::from_value(serde_json::json!({
+            "sha": "abc123",
+            "commit": {
+                "author": signature(author_date),
+                "committer": signature(committer_date),
+                "message": "test commit",
+                "url": null,
+                "comment_count": 0,
+                "verification": null
+            },
+            "url": "https://api.github.com/repos/bitcoin/bitcoin/commits/abc123",
+            "html_url": "https://github.com/bitcoin/bitcoin/commit/abc123",
+            "comments_url": "https://api.github.com/repos/bitcoin/bitcoin/commits/abc123/comments",
+            "author": null,
+            "committer": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn bots_are_recognized_by_type_or_login_suffix() {
+        let user = |json| serde_json::from_value::<GitHubUser>(json).unwrap();
+        assert!(user(serde_json::json!({"login": "ci", "id": 1, "type": "Bot"})).is_bot());
+        assert!(user(serde_json::json!({"login": "dependabot[bot]", "id": 2})).is_bot());
+        assert!(!user(serde_json::json!({"login": "alice", "id": 3, "type": "User"})).is_bot());
+    }
+
+    #[test]
+    fn commit_timestamp_prefers_committer_date() {
+        let commit = commit_json(Some("2024-01-01T00:00:00Z"), Some("2024-01-02T00:00:00Z"));
+        assert_eq!(
+            commit.commit_timestamp().unwrap().to_rfc3339(),
+            "2024-01-02T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn commit_timestamp_falls_back_to_author_date() {
+        let commit = commit_json(Some("2024-01-01T00:00:00Z"), None);
+        assert_eq!(commit.committer_date(), None);
+        assert_eq!(
+            commit.commit_timestamp().unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn commit_timestamp_is_none_without_any_date() {
+        assert_eq!(commit_json(None, None).commit_timestamp(), None);
+    }
+
+    #[test]
+    fn link_header_yields_the_next_url() {
+        let header = r#"<https://api.github.com/repositories/1181927/pulls?state=all&per_page=100&page=1>; rel="prev", <https://api.github.com/repositories/1181927/pulls?state=all&per_page=100&page=3>; rel="next", <https://api.github.com/repositories/1181927/pulls?state=all&per_page=100&page=52>; rel="last", <https://api.github.com/repositories/1181927/pulls?state=all&per_page=100&page=1>; rel="first""#;
+        assert_eq!(
+            parse_link_header(header).as_deref(),
+            Some("https://api.github.com/repositories/1181927/pulls?state=all&per_page=100&page=3")
+        );
+
+        // Commas in URLs, extra parameters, loose spacing and unquoted values
+        let header = "<https://api.github.com/repos/a/b/issues?labels=bug,ui&page=2> ;rel = next ; type=\"text/html\",<https://api.github.com/repos/a/b/issues?labels=bug,ui&page=9>;rel=\"last\"";
+        assert_eq!(
+            parse_link_header(header).as_deref(),
+            Some("https://api.github.com/repos/a/b/issues?labels=bug,ui&page=2")
+        );
+    }
+
+    #[test]
+    fn link_header_without_next_is_none() {
+        let header = r#"<https://api.github.com/repositories/1181927/pulls?page=51>; rel="prev", <https://api.github.com/repositories/1181927/pulls?page=1>; rel="first""#;
+        assert_eq!(parse_link_header(header), None);
+        assert_eq!(parse_link_header(""), None);
+    }
+
+    #[test]
+    fn repo_info_license_may_be_missing() {
+        let mut repo = serde_json::json!({
+            "id": 1, "name": "bitcoin", "full_name": "bitcoin/bitcoin",
+            "description": null, "html_url": "https://github.com/bitcoin/bitcoin",
+            "created_at": "2010-12-19T15:16:43Z", "updated_at": "2024-01-01T00:00:00Z",
+            "pushed_at": "2024-01-01T00:00:00Z", "stargazers_count": 1,
+            "watchers_count": 1, "forks_count": 1, "open_issues_count": 1,
+            "default_branch": "master", "language": "C++",
+            "license": { "key": "mit", "name": "MIT License", "spdx_id": "MIT", "url": null }
+        });
+        let info: RepoInfo = serde_json::from_value(repo.clone()).unwrap();
+        assert_eq!(info.license.and_then(|l| l.spdx_id).as_deref(), Some("MIT"));
+
+        repo["license"] = serde_json::Value::Null;
+        let info: RepoInfo = serde_json::from_value(repo).unwrap();
+        assert_eq!(info.license, None);
+    }
+
+    #[test]
+    fn search_query_ands_labels_and_author() {
+        let labels = vec!["Bug".to_string(), "good first issue".to_string()];
+        assert_eq!(
+            search_query("bitcoin", "bitcoin", "is:pr", &labels, Some("alice")),
+            r#"repo:bitcoin/bitcoin is:pr label:"Bug" label:"good first issue" author:alice"#
+        );
+        assert_eq!(
+            search_query("bitcoin", "bitcoin", "is:pr", &[], None),
+            "repo:bitcoin/bitcoin is:pr"
+        );
+    }
+
+    // Collects formatted log lines, so a test can inspect what was logged.
+    struct Capture(std::sync::Mutex<Vec<String>>);
+
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+        fn flush(&self) {}
+    }
+
+    // Answers one HTTP request on a local port with `status` and `body`.
+    // Returns the server's base URL and a handle yielding the raw request.
+    fn serve_once(status: &str, body: &str) -> (String, std::thread::JoinHandle<String>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let read = stream.read(&mut request).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&request[..read]).into_owned()
+        });
+        (base, server)
+    }
+
+    #[test]
+    fn text_responses_are_fetched_classified_and_replayed() {
+        let diff = "diff --git a/f b/f\n+hi\n";
+        let dir = tempfile::TempDir::new().unwrap();
+        let (base, server) = serve_once("200 OK", diff);
+        let url = format!("{}/repos/a/b/commits/abc", base);
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_raw_responses(RawResponses::Dump(dir.path().to_path_buf()));
+        assert_eq!(client.get_text(&url, DIFF_MEDIA_TYPE).unwrap(), diff);
+        let request = server.join().unwrap().to_lowercase();
+        assert!(request.contains(&format!("accept: {}", DIFF_MEDIA_TYPE)));
+
+        // Replayed without a server, and not confused with the JSON response
+        let replay = GitHubClient::new(None)
+            .unwrap()
+            .with_raw_responses(RawResponses::Replay(dir.path().to_path_buf()));
+        assert_eq!(replay.get_text(&url, DIFF_MEDIA_TYPE).unwrap(), diff);
+        assert!(replay.get::<serde_json::Value>(&url).is_err());
+
+        let (base, server) = serve_once("404 Not Found", r#"{"message": "Not Found"}"#);
+        let missing = GitHubClient::new(None)
+            .unwrap()
+            .get_text(&format!("{}/repos/a/b/commits/def", base), DIFF_MEDIA_TYPE);
+        server.join().unwrap();
+        assert!(matches!(missing, Err(DataError::NotFoundError)));
+    }
+
+    #[test]
+    fn oversized_responses_fail_instead_of_being_buffered() {
+        let body = r#"{"login": "a-very-long-login-name", "id": 1}"#;
+        let (base, server) = serve_once("200 OK", body);
+        let url = format!("{}/users/a", base);
+        let capped = GitHubClient::new(None)
+            .unwrap()
+            .with_max_response_bytes(Some(16));
+        let result = capped.get::<GitHubUser>(&url);
+        server.join().unwrap();
+        assert!(
+            matches!(result, Err(DataError::ResponseTooLarge { limit: 16, .. })),
+            "{:?}",
+            result.map(|u| u.login)
+        );
+
+        let (base, server) = serve_once("200 OK", body);
+        let roomy = GitHubClient::new(None)
+            .unwrap()
+            .with_max_response_bytes(Some(body.len() as u64));
+        let user: GitHubUser = roomy.get(&format!("{}/users/a", base)).unwrap();
+        server.join().unwrap();
+        assert_eq!(user.login, "a-very-long-login-name");
+    }
+
+    #[test]
+    fn deprecation_and_sunset_headers_are_described() {
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut map = reqwest::header::HeaderMap::new();
+            for (name, value) in pairs {
+                map.insert(*name, value.parse().unwrap());
+            }
+            map
+        };
+        assert_eq!(deprecation_notice(&headers(&[])), None);
+        assert_eq!(
+            deprecation_notice(&headers(&[
+                ("deprecation", "@1688169600"),
+                ("sunset", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            ])),
+            Some("deprecated since 2023-07-01, to be removed on 2024-01-01".to_string())
+        );
+        assert_eq!(
+            deprecation_notice(&headers(&[("deprecation", "true")])),
+            Some("deprecated".to_string())
+        );
+        assert_eq!(
+            deprecation_notice(&headers(&[("sunset", "soon")])),
+            Some("to be removed on soon".to_string())
+        );
+    }
+
+    #[test]
+    fn endpoint_overrides_route_matching_paths() {
+        let commits = EndpointOverride::parse(
+            "/repos/{owner}/{repo}/commits=http://mirror:8080/{owner}-{repo}/commits",
+        )
+        .unwrap();
+        assert_eq!(
+            commits
+                .apply("https://api.github.com/repos/bitcoin/bitcoin/commits?page=2")
+                .as_deref(),
+            Some("http://mirror:8080/bitcoin-bitcoin/commits?page=2")
+        );
+        assert_eq!(
+            commits.apply("https://api.github.com/repos/bitcoin/bitcoin/commits/abc"),
+            None
+        );
+        assert!(EndpointOverride::parse("commits").is_err());
+
+        // The token only goes to other hosts on request, and never over http
+        let client = GitHubClient::new(Some("ghp_override_token".to_string()))
+            .unwrap()
+            .with_endpoint_overrides(vec![
+                EndpointOverride::parse("/rate_limit=https://mirror+auth").unwrap(),
+                EndpointOverride::parse("/graphql=https://api.github.com/other").unwrap(),
+            ]);
+        let rate_limit = format!("{}/rate_limit", GITHUB_API_BASE_URL);
+        assert_eq!(
+            client.route(&rate_limit),
+            ("https://mirror/rate_limit".to_string(), true)
+        );
+        assert!(client.route(GITHUB_GRAPHQL_URL).1);
+        assert!(EndpointOverride::parse("/rate_limit=http://mirror+auth").is_err());
+
+        // A bare base URL keeps the request path
+        let (base, server) = serve_once("200 OK", "diff");
+        let client = GitHubClient::new(Some("ghp_secret".to_string()))
+            .unwrap()
+            .with_endpoint_overrides(vec![EndpointOverride::parse(&format!(
+                "/repos/{{owner}}/{{repo}}/commits/{{sha}}={}/",
+                base
+            ))
+            .unwrap()]);
+        assert_eq!(client.get_commit_patch("a", "b", "abc").unwrap(), "diff");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /repos/a/b/commits/abc HTTP/1.1"));
+        assert!(!request.to_lowercase().contains("authorization"));
+    }
+
+    #[test]
+    fn events_are_read_by_type_and_stored() {
+        let events: Vec<GitHubEvent> = serde_json::from_value(serde_json::json!([
+            { "id": "1", "type": "PushEvent", "actor": { "login": "alice", "id": 1 },
+              "created_at": "2024-05-01T00:00:00Z",
+              "payload": { "ref": "refs/heads/master", "head": "abc" } },
+            { "id": "2", "type": "PullRequestEvent", "actor": { "login": "bob", "id": 2 },
+              "created_at": "2024-05-02T00:00:00Z",
+              "payload": { "action": "opened", "number": 42, "pull_request": {} } },
+            { "id": "3", "type": "IssueCommentEvent", "actor": null,
+              "created_at": "2024-05-03T00:00:00Z",
+              "payload": { "action": "created", "issue": { "number": 7 }, "comment": {} } },
+            { "id": "4", "type": "GollumEvent", "actor": { "login": "carol", "id": 3 },
+              "created_at": "2024-05-04T00:00:00Z", "payload": { "pages": [] } }
+        ]))
+        .unwrap();
+        let kinds: Vec<_> = events
+            .iter()
+            .map(|e| {
+                (
+                    e.kind.event_type(),
+                    e.kind.action(),
+                    e.kind.number(),
+                    e.kind.ref_name(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("PushEvent", None, None, Some("refs/heads/master")),
+                ("PullRequestEvent", Some("opened"), Some(42), None),
+                ("IssueCommentEvent", Some("created"), Some(7), None),
+                ("Other", None, None, None),
+            ]
+        );
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::create_tables(&conn).unwrap();
+        crate::db::insert_repo_events(&conn, &events, "a/b", 10).unwrap();
+        crate::db::insert_repo_events(&conn, &events[..1], "a/b", 10).unwrap();
+        let counts = crate::queries::event_type_counts(&conn, "a/b", None).unwrap();
+        assert_eq!(counts.values().sum::<i64>(), 4);
+        let oldest = crate::queries::oldest_event_at(&conn, "a/b").unwrap();
+        assert_eq!(oldest.as_deref(), Some("2024-05-01T00:00:00+00:00"));
+        let recent =
+            crate::queries::event_type_counts(&conn, "a/b", Some("2024-05-02T00:00:00Z")).unwrap();
+        assert_eq!(recent.values().sum::<i64>(), 3);
+        assert_eq!(recent.get("PushEvent"), None);
+    }
+
+    #[test]
+    fn capped_issue_listing_takes_the_newest_first() {
+        let issue = |id: i64| {
+            serde_json::json!({
+                "id": id, "number": id, "html_url": "", "state": "open", "title": "t",
+                "labels": [], "assignees": [], "locked": false, "comments": 0,
+                "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-02T00:00:00Z"
+            })
+        };
+        let dir = tempfile::TempDir::new().unwrap();
+        let first =
+            "https://api.github.com/repos/a/b/issues?state=all&sort=created&direction=desc&per_page=100";
+        let body = serde_json::Value::from_iter([issue(9), issue(8), issue(7)]).to_string();
+        rawdump::save(dir.path(), first, &body, None).unwrap();
+
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_raw_responses(RawResponses::Replay(dir.path().to_path_buf()));
+        let (issues, truncated) = client
+            .get_issues("a", "b", &IssueFilter::default(), Some(2))
+            .unwrap();
+        assert_eq!(issues.iter().map(|i| i.id).collect::<Vec<_>>(), [9, 8]);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn lenient_pagination_continues_past_pages_of_malformed_records() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = "https://api.github.com/repos/a/b/contributors?per_page=100";
+        let second = "https://api.github.com/repos/a/b/contributors?page=2";
+        let contributor = serde_json::json!({
+            "id": 1, "login": "alice", "contributions": 3, "type": "User", "html_url": ""
+        });
+        let malformed = serde_json::json!({"id": "one"});
+        rawdump::save(dir.path(), first, &format!("[{}]", malformed), Some(second)).unwrap();
+        rawdump::save(dir.path(), second, &format!("[{}]", contributor), None).unwrap();
+
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_lenient(true)
+            .with_raw_responses(RawResponses::Replay(dir.path().to_path_buf()));
+        let contributors: Vec<GitHubContributor> = client
+            .get_paginated("https://api.github.com/repos/a/b/contributors")
+            .unwrap();
+        assert_eq!(contributors.len(), 1);
+        assert_eq!(client.skipped_records(), 1);
+    }
+
+    #[test]
+    fn comparisons_page_through_the_commit_list() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = "https://api.github.com/repos/a/b/compare/v1...v2?per_page=100";
+        let second = "https://api.github.com/repos/a/b/compare/v1...v2?per_page=100&page=2";
+        let page = |shas: &[&str]| {
+            serde_json::json!({
+                "status": "ahead", "ahead_by": 3, "behind_by": 0, "total_commits": 3,
+                "commits": shas.iter().map(|sha| serde_json::json!({
+                    "sha": sha, "url": "", "html_url": "", "comments_url": "",
+                    "commit": {"message": "m", "url": "",
+                        "author": {"name": "n", "email": "e", "date": "2024-01-01T00:00:00Z"},
+                        "committer": {"name": "n", "email": "e", "date": "2024-01-01T00:00:00Z"}}
+                })).collect::<Vec<_>>()
+            })
+            .to_string()
+        };
+        rawdump::save(dir.path(), first, &page(&["a", "b"]), Some(second)).unwrap();
+        rawdump::save(dir.path(), second, &page(&["c"]), None).unwrap();
+
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_raw_responses(RawResponses::Replay(dir.path().to_path_buf()));
+        let comparison = client.compare_commits("a", "b", "v1", "v2").unwrap();
+        let shas: Vec<&str> = comparison.commits.iter().map(|c| c.sha.as_str()).collect();
+        assert_eq!(shas, ["a", "b", "c"]);
+        assert!(!comparison.is_truncated());
+    }
+
+    #[test]
+    fn review_threads_beyond_the_first_page_are_counted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let save = |query: &str, variables: serde_json::Value, data: serde_json::Value| {
+            let request = serde_json::json!({ "query": query, "variables": variables });
+            let key = format!("{} {}", GITHUB_GRAPHQL_URL, request);
+            let body = serde_json::json!({ "data": data }).to_string();
+            rawdump::save(dir.path(), &key, &body, None).unwrap();
+        };
+        let threads = |resolved: &[bool], next: Option<&str>| {
+            serde_json::json!({
+                "pageInfo": { "hasNextPage": next.is_some(), "endCursor": next },
+                "nodes": resolved.iter().map(|r| serde_json::json!({ "isResolved": r }))
+                    .collect::<Vec<_>>()
+            })
+        };
+        save(
+            REVIEW_THREADS_QUERY,
+            serde_json::json!({ "owner": "a", "name": "b", "cursor": null }),
+            serde_json::json!({ "repository": { "pullRequests": {
+                "pageInfo": { "hasNextPage": false, "endCursor": null },
+                "nodes": [{ "number": 7, "reviewThreads": threads(&[true, false], Some("c1")) }]
+            }}}),
+        );
+        save(
+            PR_REVIEW_THREADS_QUERY,
+            serde_json::json!({ "owner": "a", "name": "b", "number": 7, "cursor": "c1" }),
+            serde_json::json!({ "repository": { "pullRequest": {
+                "reviewThreads": threads(&[true, true, false], None)
+            }}}),
+        );
+
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_raw_responses(RawResponses::Replay(dir.path().to_path_buf()));
+        assert_eq!(
+            client.get_review_thread_counts("a", "b").unwrap(),
+            [ReviewThreadCounts {
+                pr_number: 7,
+                resolved: 3,
+                unresolved: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn issues_repeated_across_pages_are_stored_once() {
+        let issue = |id: i64| {
+            serde_json::json!({
+                "id": id, "number": id, "html_url": "", "state": "open", "title": "t",
+                "labels": [], "assignees": [], "locked": false, "comments": 0,
+                "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-02T00:00:00Z"
+            })
+        };
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = "https://api.github.com/repos/a/b/issues?state=all&sort=created&direction=asc&since=2024-01-01&per_page=100";
+        let second = "https://api.github.com/repos/a/b/issues?page=2";
+        let body =
+            |ids: &[i64]| serde_json::Value::from_iter(ids.iter().map(|&id| issue(id))).to_string();
+        rawdump::save(dir.path(), first, &body(&[1, 2]), Some(second)).unwrap();
+        rawdump::save(dir.path(), second, &body(&[2, 3]), None).unwrap();
+
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_raw_responses(RawResponses::Replay(dir.path().to_path_buf()));
+        let filter = IssueFilter {
+            since: Some("2024-01-01".to_string()),
+            ..IssueFilter::default()
+        };
+        let (issues, truncated) = client.get_issues("a", "b", &filter, None).unwrap();
+        assert!(!truncated);
+        assert_eq!(issues.iter().map(|i| i.id).collect::<Vec<_>>(), [1, 2, 3]);
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::create_tables(&conn).unwrap();
+        crate::db::insert_github_issues(&conn, &issues, "a/b", 10).unwrap();
+        let rows: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM github_issues WHERE id = 2",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[test]
+    fn authenticated_requests_log_no_token() {
+        let token = "ghp_s3cretTokenValue";
+        let captured: &'static Capture = Box::leak(Box::new(Capture(Default::default())));
+        log::set_boxed_logger(Box::new(crate::redact::Redacting::new(captured))).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let (base, server) = serve_once("200 OK", "[]");
+        let url = format!("{}/repos/a/b/pulls?access_token={}&page=2", base, token);
+        let client = GitHubClient::new(Some(token.to_string())).unwrap();
+        client.send(&url, JSON_MEDIA_TYPE).unwrap();
+        log::debug!("Token as configured: {}", token);
+        let request = server.join().unwrap();
+        assert!(request.contains(&format!("token {}", token)));
+
+        let lines = captured.0.lock().unwrap();
+        assert!(lines.iter().any(|line| line.contains("/repos/a/b/pulls")));
+        assert!(
+            lines.iter().all(|line| !line.contains(token)),
+            "{:?}",
+            lines
+        );
+    }
+}
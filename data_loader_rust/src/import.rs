@@ -0,0 +1,154 @@
+use crate::db;
+use crate::errors::{DataError, Result};
+use crate::export::table_names;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Loads the `<table>.ndjson` files written by `export --export-ndjson` into
+/// the database, which should have its tables created already. Rows replace
+/// existing rows with the same primary key, so importing the same export
+/// twice leaves the database unchanged. Keys that aren't columns of the
+/// table are ignored and missing ones are stored as NULL. Files are read line
+/// by line and committed every `batch_size` rows. Returns the rows imported.
+pub fn import_ndjson(conn: &Connection, dir: &Path, batch_size: usize) -> Result<usize> {
+    let tables = table_names(conn)?;
+    let mut files: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ndjson"))
+        .collect();
+    files.sort();
+
+    let mut total = 0;
+    for path in files {
+        let Some(table) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        if !tables.iter().any(|t| t == table) {
+            log::warn!("Skipping {:?}: there is no {} table", path, table);
+            continue;
+        }
+        let rows = import_table(conn, table, &path, batch_size)?;
+        log::info!("Imported {} rows into {} from {:?}", rows, table, path);
+        total += rows;
+    }
+    Ok(total)
+}
+
+fn import_table(conn: &Connection, table: &str, path: &Path, batch_size: usize) -> Result<usize> {
+    let mut columns_stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = columns_stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut insert = conn.prepare(&format!(
+        "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+        table,
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    ))?;
+
+    let batch_size = batch_size.max(1);
+    let mut rows = 0;
+    let mut tx = db::write_transaction(conn)?;
+    for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let serde_json::Value::Object(mut object) = serde_json::from_str(&line)? else {
+            return Err(DataError::Other(format!(
+                "Line {} of {:?} is not a JSON object",
+                index + 1,
+                path
+            )));
+        };
+        let values = columns
+            .iter()
+            .map(|column| sql_value(object.remove(column).unwrap_or_default()));
+        insert.execute(params_from_iter(values))?;
+        rows += 1;
+        if rows % batch_size == 0 {
+            tx.commit()?;
+            tx = db::write_transaction(conn)?;
+        }
+    }
+    tx.commit()?;
+    Ok(rows)
+}
+
+// Booleans become 0/1 like SQLite stores them; nested values are kept as JSON text.
+fn sql_value(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(b.into()),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => n.as_f64().map(Value::Real).unwrap_or(Value::Null),
+        },
+        serde_json::Value::String(s) => Value::Text(s),
+        nested => Value::Text(nested.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_tables;
+    use crate::export::export_ndjson;
+    use tempfile::TempDir;
+
+    fn fresh_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    fn read_export(dir: &Path) -> Vec<(String, String)> {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, fs::read_to_string(path).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn import_export_import_round_trips() {
+        let source = fresh_db();
+        source
+            .execute_batch(
+                r"
+                INSERT INTO git_commits (sha, repo_name, author_email, message) VALUES
+                    ('a1', 'x/y', 'alice@example.com', 'Fix\n\nwith a body'),
+                    ('a2', 'x/y', NULL, NULL);
+                INSERT INTO github_pull_requests (id, number, repo_name, mergeable) VALUES
+                    (10, 1, 'x/y', 1);
+                INSERT INTO pr_review_threads (repo_name, pr_number, resolved, unresolved)
+                    VALUES ('x/y', 1, 3, 0);
+                ",
+            )
+            .unwrap();
+        let first = TempDir::new().unwrap();
+        export_ndjson(&source, first.path(), None).unwrap();
+
+        let imported = fresh_db();
+        assert_eq!(import_ndjson(&imported, first.path(), 2).unwrap(), 4);
+        let second = TempDir::new().unwrap();
+        export_ndjson(&imported, second.path(), None).unwrap();
+        assert_eq!(read_export(first.path()), read_export(second.path()));
+
+        // Importing again replaces rows instead of duplicating them
+        import_ndjson(&imported, second.path(), 2).unwrap();
+        let third = TempDir::new().unwrap();
+        export_ndjson(&imported, third.path(), None).unwrap();
+        assert_eq!(read_export(first.path()), read_export(third.path()));
+    }
+}
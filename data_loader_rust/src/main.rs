@@ -1,20 +1,81 @@
+pub mod app_auth;
+pub mod classify;
 pub mod config;
 pub mod db;
+pub mod dbdiff;
 pub mod errors;
+pub mod export;
 pub mod git_ops;
 pub mod github;
+pub mod import;
+pub mod metrics;
+pub mod preflight;
+pub mod queries;
+pub mod rawdump;
+pub mod redact;
+pub mod report;
+pub mod retry;
+pub mod search;
+pub mod storage;
+pub mod summary;
+pub mod table;
+pub mod throttle;
+pub mod timeparse;
+pub mod trailers;
+pub mod verify;
 
-use chrono::{Duration, Utc};
-use url;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
 
-use crate::config::parse_config;
-use crate::db::{create_tables, establish_connection};
+use crate::app_auth::AppAuth;
+use crate::classify::{CommitClassifier, IntegrationType};
+use crate::config::{
+    parse_config, Command, CommitMessage, Config, DiffArgs, Entity, ExportArgs, ImportArgs,
+    LogFormat, RangeArgs, RefsArgs, ReportArgs, ReviewsScope, SearchArgs, TableFormat,
+};
+use crate::db::establish_readonly_connection;
 use crate::errors::Result;
-use crate::git_ops::ensure_repo_cloned_or_updated;
-use crate::github::GitHubClient;
+use crate::git_ops::{
+    commits_between, ensure_repo_cloned_or_updated, extract_commits, list_remote_refs,
+    local_repo_path,
+};
+use crate::github::{CommitFilter, GitHubClient, GitHubCommit, IssueFilter, RepoInfo};
+use crate::metrics::MetricRegistry;
+use crate::queries::Window;
+use crate::rawdump::RawResponses;
+use crate::retry::RetryBudget;
+use crate::storage::{SqliteStorage, Storage};
+use crate::summary::{RepoSummary, RunSummary};
+use crate::table::TableStyle;
+use crate::throttle::Throttle;
+use crate::verify::CommitWindow;
+
+// Overlap with the previous run for --since-last-run, so items updated while
+// it was running (or with slightly skewed timestamps) aren't missed.
+const SINCE_LAST_RUN_MARGIN_HOURS: i64 = 1;
+
+// How often the --interval loop checks for a shutdown signal while sleeping.
+const SHUTDOWN_POLL: StdDuration = StdDuration::from_secs(1);
+
+// Owner under which local-directory repositories are stored, e.g.
+// `local/knots-1a2b3c4d`.
+const LOCAL_OWNER: &str = "local";
 
 // Helper function to extract owner/repo from URL or path
 fn parse_repo_url(url_or_path: &str) -> Result<(String, String)> {
+    // A local directory is named after its path under a `local` owner
+    if git_ops::is_local_repo(url_or_path) {
+        return Ok((
+            LOCAL_OWNER.to_string(),
+            git_ops::local_repo_name(url_or_path)?,
+        ));
+    }
     // Attempt to parse as https URL first
     if let Ok(url) = url::Url::parse(url_or_path) {
         if let Some(mut segments) = url.path_segments() {
@@ -45,130 +106,1927 @@ fn parse_repo_url(url_or_path: &str) -> Result<(String, String)> {
     )))
 }
 
-fn main() -> Result<()> {
-    env_logger::init();
-    log::info!("Starting Rust data loader...");
+// URL git can connect to for a configured repository. URLs and local
+// directories pass through; `owner/repo` is expanded to its GitHub URL.
+fn remote_url(url_or_path: &str) -> Result<String> {
+    if git_ops::is_local_repo(url_or_path) || url_or_path.contains(':') {
+        return Ok(url_or_path.to_string());
+    }
+    let (owner, name) = parse_repo_url(url_or_path)?;
+    Ok(format!("https://github.com/{}/{}.git", owner, name))
+}
+
+// Builds the GitHub client for one repository with the options from the
+// command line. A repository with its own `@TOKEN_ENV` token uses just that;
+// the others use the global token or GitHub App.
+fn build_github_client(
+    config: &Config,
+    repo_path: &str,
+    retry_budget: &Arc<RetryBudget>,
+) -> Result<GitHubClient> {
+    let own_token = config
+        .repo_token_envs
+        .iter()
+        .any(|(path, _)| path == repo_path);
+    let mut client = GitHubClient::new(config.github_token_for(repo_path)?)?;
+    if own_token {
+        log::info!("Using the repository's own token for {}", repo_path);
+    } else if let (Some(app_id), Some(installation_id), Some(key_path)) = (
+        &config.app_id,
+        &config.app_installation_id,
+        &config.app_private_key,
+    ) {
+        let pem = std::fs::read(key_path)?;
+        client = client.with_app_auth(AppAuth::new(app_id, installation_id, &pem)?);
+        log::info!(
+            "Authenticating as GitHub App {} installation {}",
+            app_id,
+            installation_id
+        );
+    }
+    if let Some(dir) = &config.raw_dump {
+        client = client.with_raw_responses(RawResponses::Dump(PathBuf::from(dir)));
+    } else if let Some(dir) = &config.replay {
+        log::info!("Replaying API responses from {}", dir);
+        client = client.with_raw_responses(RawResponses::Replay(PathBuf::from(dir)));
+    }
+    Ok(client
+        .with_lenient(config.lenient)
+        .with_max_rate_limit_wait(StdDuration::from_secs(config.max_rate_limit_wait_secs))
+        .with_max_response_bytes(config.max_response_bytes)
+        .with_retry_budget(Arc::clone(retry_budget))
+        .with_endpoint_overrides(config.endpoint_overrides.clone())
+        .with_throttle(Throttle::new(
+            config.concurrency,
+            config.requests_per_second,
+            StdDuration::from_millis(config.jitter_ms),
+        )))
+}
+
+// Retry budget shared by the clients of one run (--max-total-retry-secs).
+fn retry_budget(config: &Config) -> Arc<RetryBudget> {
+    Arc::new(RetryBudget::new(
+        config.max_total_retry_secs.map(StdDuration::from_secs),
+    ))
+}
+
+// Formats a timestamp for GitHub query parameters. The `Z` suffix avoids a
+// `+00:00` offset, whose `+` would be read as a space in the query string.
+fn api_timestamp(dt: DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// Resolves the canonical owner/name for a repository. Renamed or transferred
+// repositories are redirected by the API, and the response carries the current
+// `full_name`, which is what gets stored as `repo_name`. The repository info is
+// returned as well so the caller can record a snapshot of it.
+fn resolve_canonical_repo(
+    github_client: &GitHubClient,
+    owner: &str,
+    name: &str,
+) -> Result<(String, String, RepoInfo)> {
+    let info = github_client.get_repo_info(owner, name)?;
+    let configured = format!("{}/{}", owner, name);
+    if !info.full_name.eq_ignore_ascii_case(&configured) {
+        log::warn!(
+            "Repository {} is now {}; storing data under the canonical name. \
+             Update the configured repository so reports match.",
+            configured,
+            info.full_name
+        );
+    }
+    let (owner, name) = info.full_name.split_once('/').unwrap_or((owner, name));
+    Ok((owner.to_string(), name.to_string(), info))
+}
+
+// Turns a 410/451 for a single item into `None` after logging it, so one
+// deleted or taken-down item doesn't abort the whole pass.
+fn skip_unavailable<T>(result: Result<T>, what: &str) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.is_unavailable() => {
+            log::warn!("Skipping {}: {}", what, e);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Fetches closed issues one by one to fill in `closed_by`, which the list
+// endpoint usually leaves out. Pull requests in the issues list are skipped.
+fn enrich_closed_by(
+    github_client: &GitHubClient,
+    conn: &rusqlite::Connection,
+    owner: &str,
+    name: &str,
+    issues: &[github::GitHubIssue],
+) -> Result<()> {
+    let repo_full_name = format!("{}/{}", owner, name);
+    let pending: Vec<_> = issues
+        .iter()
+        .filter(|i| i.state == "closed" && i.closed_by.is_none() && i.pull_request.is_none())
+        .collect();
+    log::info!(
+        "Fetching closed_by for {} closed issues of {}",
+        pending.len(),
+        repo_full_name
+    );
+    for issue in pending {
+        let what = format!("issue #{} of {}", issue.number, repo_full_name);
+        let Some(detail) =
+            skip_unavailable(github_client.get_issue(owner, name, issue.number), &what)?
+        else {
+            continue;
+        };
+        let closed_by = detail.closed_by.as_ref().map(|u| u.login.as_str());
+        db::update_issue_closed_by(conn, &repo_full_name, issue.number, closed_by)?;
+    }
+    Ok(())
+}
+
+// Fetches the reviews of each PR and records when the first one was submitted
+// and who reviewed it. Reviews left by the PR author (e.g. replies in review
+// threads) or by bots don't count.
+fn enrich_reviews(
+    github_client: &GitHubClient,
+    conn: &rusqlite::Connection,
+    owner: &str,
+    name: &str,
+    prs: &[github::GitHubPullRequest],
+    scope: ReviewsScope,
+) -> Result<()> {
+    let repo_full_name = format!("{}/{}", owner, name);
+    let in_scope: Vec<_> = prs.iter().filter(|pr| pr.in_reviews_scope(scope)).collect();
+    log::info!(
+        "Fetching reviews for {} of {} PRs of {} ({:?} scope)",
+        in_scope.len(),
+        prs.len(),
+        repo_full_name,
+        scope
+    );
+    for pr in in_scope {
+        let author = pr.user.as_ref().map(|u| u.login.as_str());
+        let what = format!("reviews of PR #{} of {}", pr.number, repo_full_name);
+        let Some(reviews) = skip_unavailable(
+            github_client.get_pull_request_reviews(owner, name, pr.number),
+            &what,
+        )?
+        else {
+            continue;
+        };
+        let first_review_at = reviews
+            .iter()
+            .filter(|r| r.user.as_ref().map(|u| u.login.as_str()) != author)
+            .filter(|r| !r.user.as_ref().is_some_and(|u| u.is_bot()))
+            .filter_map(|r| r.submitted_at)
+            .min();
+        db::update_pr_first_review_at(conn, &repo_full_name, pr.number, first_review_at.as_ref())?;
+        if let Some(author) = author {
+            let reviewers: Vec<&str> = reviews
+                .iter()
+                .filter_map(|r| r.user.as_ref().map(|u| u.login.as_str()))
+                .filter(|&reviewer| reviewer != author)
+                .collect();
+            db::replace_review_edges(conn, &repo_full_name, pr.number, author, &reviewers)?;
+        }
+    }
+    Ok(())
+}
+
+// Fetches the conversation comments of each commented issue and of every PR,
+// recording the first one left by someone other than the author or a bot. The issues
+// list also returns PRs; those are covered by `prs` instead.
+fn enrich_first_comments(
+    github_client: &GitHubClient,
+    conn: &rusqlite::Connection,
+    owner: &str,
+    name: &str,
+    issues: &[github::GitHubIssue],
+    prs: &[github::GitHubPullRequest],
+) -> Result<()> {
+    let repo_full_name = format!("{}/{}", owner, name);
+    let commented_issues = issues
+        .iter()
+        .filter(|i| i.pull_request.is_none() && i.comments > 0)
+        .map(|i| (i.number, i.user.as_ref(), false));
+    let items: Vec<_> = commented_issues
+        .chain(prs.iter().map(|pr| (pr.number, pr.user.as_ref(), true)))
+        .collect();
+    log::info!(
+        "Fetching comments for {} issues and PRs of {}",
+        items.len(),
+        repo_full_name
+    );
+    for (number, user, pull_request) in items {
+        let author = user.map(|u| u.login.as_str());
+        let what = format!("comments of #{} of {}", number, repo_full_name);
+        let Some(comments) =
+            skip_unavailable(github_client.get_issue_comments(owner, name, number), &what)?
+        else {
+            continue;
+        };
+        let first_comment_at = comments
+            .iter()
+            .filter(|c| c.user.as_ref().map(|u| u.login.as_str()) != author)
+            .filter(|c| !c.user.as_ref().is_some_and(|u| u.is_bot()))
+            .map(|c| c.created_at)
+            .min();
+        db::update_first_comment_at(
+            conn,
+            &repo_full_name,
+            number,
+            pull_request,
+            first_comment_at.as_ref(),
+        )?;
+    }
+    Ok(())
+}
+
+// Fetches the CI statuses and check runs of each commit. Commits whose CI had
+// finished when last fetched are skipped unless `re_enrich` is set; those
+// with pending statuses or check runs are fetched again next time.
+fn fetch_commit_statuses(
+    github_client: &GitHubClient,
+    conn: &rusqlite::Connection,
+    owner: &str,
+    name: &str,
+    commits: &[github::GitHubCommit],
+    re_enrich: bool,
+) -> Result<()> {
+    let repo_full_name = format!("{}/{}", owner, name);
+    let done = if re_enrich {
+        HashSet::new()
+    } else {
+        db::enriched_shas(conn, &repo_full_name, db::ENRICH_STATUSES)?
+    };
+    let pending: Vec<_> = commits.iter().filter(|c| !done.contains(&c.sha)).collect();
+    log::info!(
+        "Fetching CI statuses for {} of {} commits of {} ({} already fetched)",
+        pending.len(),
+        commits.len(),
+        repo_full_name,
+        commits.len() - pending.len()
+    );
+    for commit in pending {
+        let what = format!("CI statuses of {} in {}", commit.sha, repo_full_name);
+        let Some((combined, check_runs)) = skip_unavailable(
+            github_client
+                .get_commit_statuses(owner, name, &commit.sha)
+                .and_then(|combined| {
+                    Ok((
+                        combined,
+                        github_client.get_check_runs(owner, name, &commit.sha)?,
+                    ))
+                }),
+            &what,
+        )?
+        else {
+            continue;
+        };
+        db::insert_commit_statuses(
+            conn,
+            &repo_full_name,
+            &commit.sha,
+            &combined.statuses,
+            &check_runs,
+        )?;
+        let finished = combined.statuses.iter().all(|s| s.state != "pending")
+            && check_runs.iter().all(|r| r.status == "completed");
+        if finished {
+            db::mark_enriched(conn, &repo_full_name, &commit.sha, db::ENRICH_STATUSES)?;
+        }
+    }
+    Ok(())
+}
+
+// Fetches and stores review thread resolution counts. Requires a token since
+// it goes through GraphQL; without one the pass is skipped.
+fn fetch_review_threads(
+    github_client: &GitHubClient,
+    conn: &rusqlite::Connection,
+    owner: &str,
+    name: &str,
+    batch_size: usize,
+) -> Result<()> {
+    let repo_full_name = format!("{}/{}", owner, name);
+    match github_client.get_review_thread_counts(owner, name) {
+        Ok(counts) => {
+            log::info!(
+                "Fetched review threads for {} PRs of {}",
+                counts.len(),
+                repo_full_name
+            );
+            db::insert_review_thread_counts(conn, &counts, &repo_full_name, batch_size)
+        }
+        Err(errors::DataError::ConfigError(msg)) => {
+            log::warn!("Skipping review threads for {}: {}", repo_full_name, msg);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Extracts the history of `branch` from a local clone, persisting every
+// `autosave_every` commits so a failure only loses the current batch. Stored
+// commits without line counts get them first.
+fn extract_local_commits(
+    conn: &rusqlite::Connection,
+    repo_path: &Path,
+    branch: &str,
+    repo_full_name: &str,
+    classifier: &CommitClassifier,
+    config: &Config,
+) -> Result<(usize, bool)> {
+    // Resuming skips commits that are already stored, so count the lines of
+    // those saved before line counts were recorded (or from a partial clone)
+    let missing = db::commits_missing_churn(conn, repo_full_name)?;
+    if !missing.is_empty() {
+        let churn = git_ops::commit_churn(repo_path, &missing)?;
+        let updated = db::update_commit_churn(conn, repo_full_name, &churn)?;
+        log::info!(
+            "Backfilled line counts for {} of {} local commits of {}",
+            updated,
+            missing.len(),
+            repo_full_name
+        );
+    }
+    let resume_after = db::last_extracted_sha(conn, repo_full_name)?;
+    let mut saved = 0;
+    extract_commits(
+        repo_path,
+        Some(branch),
+        resume_after.as_deref(),
+        config.autosave_every,
+        config.max_commits_per_repo,
+        |batch| {
+            db::save_extracted_commits(
+                conn,
+                batch,
+                repo_full_name,
+                classifier,
+                config.commit_message,
+                config.commit_date,
+            )?;
+            saved += batch.len();
+            log::info!("Saved {} local commits for {}", saved, repo_full_name);
+            Ok(())
+        },
+    )
+}
+
+// Installs the logger. `RUST_LOG` still controls the level; the JSON format
+// emits one object per line for log pipelines.
+fn init_logger(format: LogFormat) {
+    let mut builder = env_logger::Builder::from_default_env();
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let line = serde_json::json!({
+                "timestamp": Utc::now().to_rfc3339(),
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+    let logger = builder.build();
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(redact::Redacting::new(logger)))
+        .expect("the logger is installed once");
+}
 
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::from(errors::exit_code::SUCCESS),
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<()> {
     // 1. Parse configuration
-    let config = parse_config();
+    let mut config = parse_config();
+    for secret in config.secrets() {
+        redact::register(&secret);
+    }
+    init_logger(config.log_format);
+    log::info!("Starting Rust data loader...");
     log::debug!("Configuration loaded: {:?}", config);
+    git_ops::allow_partial_clones();
 
-    // 2. Initialize DB connection
-    let conn = establish_connection(&config.db_path)?;
+    config.resolve_paths()?;
+    // `diff` works on its own pair of databases
+    if let Some(Command::Diff(args)) = &config.command {
+        return run_diff(&config, args);
+    }
+    config.load_maintainers_file()?;
 
-    // 3. Create tables if they don't exist
-    create_tables(&conn)?;
+    // 2. Initialize DB connection, creating tables if they don't exist
+    let busy_timeout = StdDuration::from_millis(config.busy_timeout_ms);
+    let conn = if config.db_readonly {
+        if config.writes() {
+            return Err(errors::DataError::ConfigError(
+                "--db-readonly only works with the report, export and refs commands".to_string(),
+            ));
+        }
+        SqliteStorage::open_readonly(&config.db_path, busy_timeout)?
+    } else {
+        let conn = SqliteStorage::open(&config.db_path, busy_timeout)?;
+        conn.create_tables()?;
+        conn
+    };
 
-    // 4. Ensure repositories are cloned/updated
-    log::info!("Ensuring repository 1 is available locally...");
-    let repo1_local_path = ensure_repo_cloned_or_updated(
-        &config.repo1_path,
-        &config.clone_dir,
-        config.github_token.as_deref(),
-    )?;
-    log::info!("Repository 1 path: {:?}", repo1_local_path);
+    match &config.command {
+        None | Some(Command::Fetch) => {
+            if config.replay.is_none() && !config.no_api {
+                check_authentication(&config)?;
+            }
+            if config.dry_run {
+                if !run_preflight(&config)? {
+                    return Err(errors::DataError::RateLimitError);
+                }
+                return Ok(());
+            }
+            match config.interval {
+                Some(interval) => run_daemon(&config, &conn, interval)?,
+                None => run_fetch_cycle(&config, &conn)?,
+            }
+        }
+        Some(Command::Report(args)) => run_report(&config, &conn, args)?,
+        Some(Command::Refs(args)) => run_refs(&config, args)?,
+        Some(Command::Export(args)) => run_export(&conn, args)?,
+        Some(Command::Range(args)) => run_range(&config, &conn, args)?,
+        // Handled above, before a database is opened
+        Some(Command::Diff(_)) => {
+            return Err(errors::DataError::Other(
+                "diff must run before the database is opened".to_string(),
+            ))
+        }
+        Some(Command::Import(args)) => run_import(&config, &conn, args)?,
+        Some(Command::Search(args)) => run_search(&config, &conn, args)?,
+        Some(Command::Vacuum(args)) => run_vacuum(&config, &conn, args.prune_before.as_ref())?,
+    }
 
-    log::info!("Ensuring repository 2 is available locally...");
-    let repo2_local_path = ensure_repo_cloned_or_updated(
-        &config.repo2_path,
-        &config.clone_dir,
-        config.github_token.as_deref(),
-    )?;
-    log::info!("Repository 2 path: {:?}", repo2_local_path);
+    Ok(())
+}
+
+// Warns about repositories fetched without credentials and refuses to fetch
+// them unless --allow-unauthenticated: the anonymous limit of 60 requests per
+// hour runs out within the first pages of a repository like Bitcoin Core.
+fn check_authentication(config: &Config) -> Result<()> {
+    let repos = config.unauthenticated_repos()?;
+    if repos.is_empty() {
+        return Ok(());
+    }
+    log::warn!(
+        "No GitHub token for {}: unauthenticated API requests are limited to 60 per hour",
+        repos.join(", ")
+    );
+    if config.allow_unauthenticated || config.dry_run {
+        log::warn!(
+            "Expect the fetch to wait for rate limit resets (up to {}s each, \
+             see --max-rate-limit-wait-secs)",
+            config.max_rate_limit_wait_secs
+        );
+        return Ok(());
+    }
+    Err(errors::DataError::ConfigError(
+        "Set GITHUB_TOKEN (or --github-token) to fetch from the GitHub API, or pass \
+         --allow-unauthenticated to fetch within the anonymous rate limit"
+            .to_string(),
+    ))
+}
 
-    // 5. Initialize GitHub Client
-    let github_client = GitHubClient::new(config.github_token.clone())?;
+// One fetch: the optional backup, the fetch itself and the run record. Fails
+// if any repository failed or verification found anomalies.
+fn run_fetch_cycle(config: &Config, conn: &SqliteStorage) -> Result<()> {
+    let backup = if config.backup {
+        let backup = db::backup_database(conn, &config.db_path)?;
+        db::prune_backups(&config.db_path, config.keep_backups)?;
+        Some(backup)
+    } else {
+        None
+    };
+    let summary = match run_fetch(config, conn) {
+        Ok(summary) => summary,
+        Err(e) => {
+            log_restore_hint(backup.as_deref(), &config.db_path);
+            return Err(e);
+        }
+    };
+    conn.record_run(&summary, &run_filters(config))?;
+    println!("{}", summary);
+    let failed = summary.failed_repos();
+    if failed > 0 {
+        log_restore_hint(backup.as_deref(), &config.db_path);
+        return Err(errors::DataError::PartialFailure(format!(
+            "{} of {} repositories failed to load",
+            failed,
+            summary.repos.len()
+        )));
+    }
+    let anomalies = summary.anomaly_count();
+    if anomalies > 0 {
+        return Err(errors::DataError::Other(format!(
+            "Verification found {} anomalies",
+            anomalies
+        )));
+    }
+    if config.vacuum_after_load {
+        run_vacuum(config, conn, None)?;
+    }
+    Ok(())
+}
 
-    // === Data Fetching and Storing ===
-    // Define the time period for fetching (e.g., last 12 months)
-    let analysis_period_months = 12;
-    let since_date = Utc::now() - Duration::days(30 * analysis_period_months);
-    let since_iso = since_date.to_rfc3339();
+// Runs a fetch cycle every `interval` until SIGTERM or Ctrl-C. A failed cycle
+// is logged, any transaction it left open is rolled back, and it is retried on
+// the next one. A signal that arrives mid-cycle lets the cycle finish, so no
+// write is cut short.
+fn run_daemon(config: &Config, conn: &SqliteStorage, interval: StdDuration) -> Result<()> {
+    let shutdown = shutdown_flag()?;
+    for cycle in 1.. {
+        log::info!("Starting fetch cycle {}", cycle);
+        if let Err(e) = run_fetch_cycle(config, conn) {
+            log::error!("Fetch cycle {} failed: {}", cycle, e);
+            if db::rollback_open_transaction(conn)? {
+                log::warn!("Rolled back the writes of fetch cycle {}", cycle);
+            }
+        }
+        log::info!("Next fetch cycle in {}s", interval.as_secs());
+        let deadline = Instant::now() + interval;
+        while !shutdown.load(Ordering::SeqCst) {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            std::thread::sleep(SHUTDOWN_POLL.min(deadline - now));
+        }
+        if shutdown.load(Ordering::SeqCst) {
+            log::info!("Shutting down after {} fetch cycles", cycle);
+            break;
+        }
+    }
+    Ok(())
+}
 
-    // Extract repo owner/name from config
+// Returns a flag that is set once the process receives SIGTERM or Ctrl-C.
+// The signals are awaited on a background thread with its own runtime.
+fn shutdown_flag() -> Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let set = flag.clone();
+    std::thread::spawn(move || {
+        runtime.block_on(async {
+            #[cfg(unix)]
+            {
+                use tokio::signal::unix::{signal, SignalKind};
+                match signal(SignalKind::terminate()) {
+                    Ok(mut terminate) => tokio::select! {
+                        _ = terminate.recv() => log::info!("Received SIGTERM"),
+                        _ = tokio::signal::ctrl_c() => log::info!("Received Ctrl-C"),
+                    },
+                    Err(e) => {
+                        log::warn!(
+                            "Could not listen for SIGTERM ({}); only Ctrl-C stops the loop",
+                            e
+                        );
+                        let _ = tokio::signal::ctrl_c().await;
+                    }
+                }
+            }
+            #[cfg(not(unix))]
+            {
+                let _ = tokio::signal::ctrl_c().await;
+                log::info!("Received Ctrl-C");
+            }
+        });
+        set.store(true, Ordering::SeqCst);
+    });
+    Ok(flag)
+}
+
+// Tells the user how to roll back a failed fetch when `--backup` made a copy.
+fn log_restore_hint(backup: Option<&str>, db_path: &str) {
+    if let Some(backup) = backup {
+        log::error!(
+            "To restore the database as it was before this run, stop any other process \
+             using it and copy {} over {}",
+            backup,
+            db_path
+        );
+    }
+}
+
+fn run_report(config: &Config, conn: &rusqlite::Connection, args: &ReportArgs) -> Result<()> {
     let (repo1_owner, repo1_name) = parse_repo_url(&config.repo1_path)?;
     let (repo2_owner, repo2_name) = parse_repo_url(&config.repo2_path)?;
     let repo1_full_name = format!("{}/{}", repo1_owner, repo1_name);
     let repo2_full_name = format!("{}/{}", repo2_owner, repo2_name);
 
+    let mut registry = MetricRegistry::with_builtins(args.exclude_merges);
+    registry.register_contributor_metrics(Window {
+        start: config.since,
+        end: config.until.unwrap_or_else(Utc::now),
+    });
+    let metrics = registry.select(&args.metrics)?;
+    let format = if args.plain {
+        TableFormat::Plain
+    } else {
+        args.format
+    };
+    let style = TableStyle::new(format, args.no_color);
+    let commit_prefix = regex::Regex::new(&args.commit_prefix_pattern).map_err(|e| {
+        errors::DataError::ConfigError(format!(
+            "Invalid --commit-prefix-pattern '{}': {}",
+            args.commit_prefix_pattern, e
+        ))
+    })?;
+    report::print_totals_comparison(conn, &metrics, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_mergeable_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_integration_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_subsystem_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_churn_comparison(
+        conn,
+        &repo1_full_name,
+        &repo2_full_name,
+        args.exclude_merges,
+    )?;
+    report::print_first_review_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_first_response_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_latency_percentile_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_review_pair_comparison(conn, &repo1_full_name, &repo2_full_name)?;
+    report::print_merger_comparison(conn, &repo1_full_name, &repo2_full_name)?;
+    report::print_self_merge_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_maintainer_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_collaborator_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_event_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_backport_comparison(
+        conn,
+        &repo1_full_name,
+        &repo2_full_name,
+        args.backport_similarity,
+    )?;
+    if let Some(path) = &args.review_edges_csv {
+        report::write_review_edges_csv(conn, path, &[&repo1_full_name, &repo2_full_name])?;
+        log::info!("Wrote review edge list CSV to {}", path);
+    }
+    report::print_label_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_closure_reason_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_milestone_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_ci_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_default_branch_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_trailer_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    report::print_commit_message_comparison(
+        conn,
+        &repo1_full_name,
+        &repo2_full_name,
+        &commit_prefix,
+        style,
+    )?;
+    report::print_changelog_comparison(conn, &repo1_full_name, &repo2_full_name, style)?;
+    for repo_full_name in [&repo1_full_name, &repo2_full_name] {
+        let snapshots = report::repo_snapshots(conn, repo_full_name)?;
+        report::print_snapshot_trend(repo_full_name, &snapshots);
+        let languages =
+            queries::language_snapshots(conn, repo_full_name, args.repo_languages_since.as_ref())?;
+        report::print_language_trend(repo_full_name, &languages);
+    }
+
+    let activity1 =
+        report::activity_by_bucket(conn, &repo1_full_name, args.bucket, args.exclude_merges)?;
+    let activity2 =
+        report::activity_by_bucket(conn, &repo2_full_name, args.bucket, args.exclude_merges)?;
+
+    if let Some(csv_path) = &args.csv {
+        report::write_activity_csv(
+            csv_path,
+            &[
+                (&repo1_full_name, &activity1),
+                (&repo2_full_name, &activity2),
+            ],
+        )?;
+        log::info!("Wrote activity trend CSV to {}", csv_path);
+    } else {
+        report::print_activity_comparison(
+            &repo1_full_name,
+            &activity1,
+            &repo2_full_name,
+            &activity2,
+        );
+    }
+    Ok(())
+}
+
+fn run_refs(config: &Config, args: &RefsArgs) -> Result<()> {
+    let urls = match &args.url {
+        Some(url) => vec![url.as_str()],
+        None => vec![config.repo1_path.as_str(), config.repo2_path.as_str()],
+    };
+    for url in urls {
+        let remote = remote_url(url)?;
+        let refs = list_remote_refs(&remote, config.github_token_for(url)?.as_deref())?;
+        println!("# {} ({} refs)", url, refs.len());
+        for (name, oid) in refs {
+            println!("{}\t{}", oid, name);
+        }
+    }
+    Ok(())
+}
+
+fn run_export(conn: &rusqlite::Connection, args: &ExportArgs) -> Result<()> {
+    let anonymizer = match (&args.anonymize_salt, args.anonymize) {
+        (Some(salt), true) => Some(export::Anonymizer::new(salt)),
+        _ => None,
+    };
+    if args.export_parquet.is_none() && args.export_ndjson.is_none() {
+        return Err(errors::DataError::ConfigError(
+            "Nothing to export; pass --export-parquet <dir> or --export-ndjson <dir>".to_string(),
+        ));
+    }
+    if let Some(dir) = &args.export_parquet {
+        let files = export::export_parquet(conn, Path::new(dir), anonymizer.as_ref())?;
+        log::info!("Wrote {} Parquet files to {}", files, dir);
+    }
+    if let Some(dir) = &args.export_ndjson {
+        let files = export::export_ndjson(conn, Path::new(dir), anonymizer.as_ref())?;
+        log::info!("Wrote {} NDJSON files to {}", files, dir);
+    }
+    Ok(())
+}
+
+fn run_import(config: &Config, conn: &rusqlite::Connection, args: &ImportArgs) -> Result<()> {
+    let rows = import::import_ndjson(conn, Path::new(&args.from), config.batch_size)?;
     log::info!(
-        "Fetching data for {} since {}...",
-        repo1_full_name,
-        since_iso
+        "Imported {} rows from {} into {}",
+        rows,
+        args.from,
+        config.db_path
     );
-    // Fetch commits for repo 1
-    let commits1 = github_client.get_commits(
-        &repo1_owner,
-        &repo1_name,
-        Some(since_iso.clone()),
-        None,
-        None,
+    search::refresh_all(conn)?;
+    Ok(())
+}
+
+fn run_search(config: &Config, conn: &rusqlite::Connection, args: &SearchArgs) -> Result<()> {
+    for repo_path in [&config.repo1_path, &config.repo2_path] {
+        let (owner, name) = parse_repo_url(repo_path)?;
+        let repo_full_name = format!("{}/{}", owner, name);
+        let hits = search::search(conn, &repo_full_name, &args.query, args.limit)?;
+        println!("# {} ({} matches)", repo_full_name, hits.len());
+        for hit in hits {
+            println!("{:<6} #{:<6} {}", hit.kind, hit.number, hit.title);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn run_vacuum(
+    config: &Config,
+    conn: &rusqlite::Connection,
+    prune_before: Option<&DateTime<Utc>>,
+) -> Result<()> {
+    if let Some(prune_before) = prune_before {
+        let pruned = db::prune_history(conn, prune_before)?;
+        log::info!(
+            "Pruned {} history rows from before {}",
+            pruned,
+            prune_before
+        );
+    }
+    let (before, after) = db::vacuum_database(conn, &config.db_path)?;
+    log::info!(
+        "Vacuumed {}: {} -> {} bytes ({} reclaimed)",
+        config.db_path,
+        before,
+        after,
+        before.saturating_sub(after)
+    );
+    Ok(())
+}
+
+fn run_diff(config: &Config, args: &DiffArgs) -> Result<()> {
+    let busy_timeout = StdDuration::from_millis(config.busy_timeout_ms);
+    let a = establish_readonly_connection(&args.db_a, busy_timeout)?;
+    let b = establish_readonly_connection(&args.db_b, busy_timeout)?;
+    let diffs = dbdiff::diff_databases(&a, &b)?;
+    println!("Diff: A = {}, B = {}", args.db_a, args.db_b);
+    dbdiff::print_diff(&diffs, args.max_keys);
+    Ok(())
+}
+
+fn run_range(config: &Config, conn: &rusqlite::Connection, args: &RangeArgs) -> Result<()> {
+    let range_spec = format!("{}...{}", args.base, args.head);
+    let retry_budget = retry_budget(config);
+    let classifier = commit_classifier(config)?;
+
+    for repo_path in [&config.repo1_path, &config.repo2_path] {
+        let github_client = build_github_client(config, repo_path, &retry_budget)?;
+        let (owner, name) = parse_repo_url(repo_path)?;
+        let repo_full_name = format!("{}/{}", owner, name);
+
+        let count = if args.api {
+            let comparison =
+                github_client.compare_commits(&owner, &name, &args.base, &args.head)?;
+            db::insert_github_commits(
+                conn,
+                &comparison.commits,
+                &repo_full_name,
+                config.batch_size,
+                &classifier,
+                config.commit_message,
+                config.commit_date,
+            )?;
+            let shas: Vec<&str> = comparison.commits.iter().map(|c| c.sha.as_str()).collect();
+            db::insert_commit_range(conn, &repo_full_name, &range_spec, "api", &shas)?;
+            if comparison.is_truncated() {
+                println!(
+                    "{}: only {} of {} commits in {} could be fetched; use the local clones \
+                     for the full range",
+                    repo_full_name,
+                    shas.len(),
+                    comparison.total_commits,
+                    range_spec
+                );
+            }
+            shas.len()
+        } else {
+            let local_path = local_repo_path(repo_path, &config.clone_dir);
+            if !local_path.exists() {
+                return Err(errors::DataError::ConfigError(format!(
+                    "No local clone of {} at {:?}; run `fetch` first or pass --api",
+                    repo_full_name, local_path
+                )));
+            }
+            let commits = commits_between(&local_path, &args.base, &args.head)?;
+            db::insert_git_commits(
+                conn,
+                &commits,
+                &repo_full_name,
+                config.batch_size,
+                &classifier,
+                config.commit_message,
+                config.commit_date,
+            )?;
+            let shas: Vec<&str> = commits.iter().map(|c| c.sha.as_str()).collect();
+            db::insert_commit_range(conn, &repo_full_name, &range_spec, "git", &shas)?;
+            shas.len()
+        };
+        println!("{}: {} commits in {}", repo_full_name, count, range_spec);
+    }
+    Ok(())
+}
+
+// Estimates the REST requests each repository's fetch will need and compares
+// them with the remaining core rate limit. Repositories sharing a token share a
+// budget. Returns false if any budget falls short.
+fn run_preflight(config: &Config) -> Result<bool> {
+    // token source ("" for the global token) -> (remaining, needed, reset)
+    let mut budgets: BTreeMap<&str, (i64, i64, i64)> = BTreeMap::new();
+    let retry_budget = retry_budget(config);
+    for repo_path in [&config.repo1_path, &config.repo2_path] {
+        if git_ops::is_local_repo(repo_path) {
+            continue;
+        }
+        let (owner, name) = parse_repo_url(repo_path)?;
+        let client = build_github_client(config, repo_path, &retry_budget)?;
+        let estimate = preflight::estimate_repo(&client, config, &owner, &name)?;
+        preflight::print_estimate(&estimate);
+
+        let source = config
+            .repo_token_envs
+            .iter()
+            .find(|(path, _)| path == repo_path)
+            .map_or("", |(_, var)| var.as_str());
+        if !budgets.contains_key(source) {
+            let core = client.get_rate_limit()?.resources.core;
+            budgets.insert(source, (core.remaining, 0, core.reset));
+        }
+        if let Some(budget) = budgets.get_mut(source) {
+            budget.1 += estimate.total();
+        }
+    }
+
+    let mut sufficient = true;
+    for (source, (remaining, needed, reset)) in budgets {
+        let token = if source.is_empty() {
+            "the default token".to_string()
+        } else {
+            format!("${}", source)
+        };
+        let reset = DateTime::from_timestamp(reset, 0).map_or(String::new(), |dt| dt.to_rfc3339());
+        println!(
+            "Rate limit for {}: {} requests needed, {} remaining (resets at {})",
+            token, needed, remaining, reset
+        );
+        if needed > remaining {
+            log::warn!(
+                "Estimated {} requests exceed the {} remaining for {}",
+                needed,
+                remaining,
+                token
+            );
+            sufficient = false;
+        }
+    }
+    Ok(sufficient)
+}
+
+fn commit_classifier(config: &Config) -> Result<CommitClassifier> {
+    CommitClassifier::new(
+        &config.revert_pattern,
+        &config.backport_pattern,
+        &config.subsystems,
+    )
+}
+
+fn run_fetch(config: &Config, conn: &SqliteStorage) -> Result<RunSummary> {
+    let classifier = commit_classifier(config)?;
+
+    let retry_budget = retry_budget(config);
+    let mut summary = RunSummary::new();
+    let repo_paths = [&config.repo1_path, &config.repo2_path];
+    if config.repo_concurrency > 1 && config.db_path != ":memory:" {
+        db::enable_wal(conn)?;
+        let busy_timeout = StdDuration::from_millis(config.busy_timeout_ms);
+        for group in repo_paths.chunks(config.repo_concurrency) {
+            let summaries: Vec<RepoSummary> = std::thread::scope(|scope| {
+                let handles: Vec<_> = group
+                    .iter()
+                    .map(|repo_path| {
+                        let (classifier, retry_budget) = (&classifier, &retry_budget);
+                        scope.spawn(move || {
+                            // Connections can't be shared between threads
+                            match SqliteStorage::open(&config.db_path, busy_timeout) {
+                                Ok(conn) => {
+                                    load_repo(config, &conn, classifier, repo_path, retry_budget)
+                                }
+                                Err(e) => {
+                                    log::error!("Failed to load {}: {}", repo_path, e);
+                                    RepoSummary {
+                                        error: Some(e.to_string()),
+                                        ..RepoSummary::new(repo_path)
+                                    }
+                                }
+                            }
+                        })
+                    })
+                    .collect();
+                group
+                    .iter()
+                    .zip(handles)
+                    .map(|(repo_path, handle)| joined_summary(repo_path, handle.join()))
+                    .collect()
+            });
+            summary.repos.extend(summaries);
+        }
+    } else {
+        for repo_path in repo_paths {
+            let repo_summary = load_repo(config, conn, &classifier, repo_path, &retry_budget);
+            summary.repos.push(repo_summary);
+        }
+    }
+    summary.finish();
+    let (retries, waited) = retry_budget.spent();
+    if retries > 0 {
+        log::info!(
+            "Retried {} requests, waiting {}s in total",
+            retries,
+            waited.as_secs()
+        );
+    }
+
+    log::info!("Data loading process completed.");
+    Ok(summary)
+}
+
+// The summary a repository loader thread returned. A loader that panicked
+// is recorded as a failed repository, so the other repositories' results
+// survive and the run ends as a partial failure.
+fn joined_summary(repo_path: &str, joined: std::thread::Result<RepoSummary>) -> RepoSummary {
+    joined.unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        log::error!("Loader for {} panicked: {}", repo_path, message);
+        RepoSummary {
+            error: Some(format!("loader panicked: {}", message)),
+            ..RepoSummary::new(repo_path)
+        }
+    })
+}
+
+// Loads one repository, from the API and its clone or from git alone, and
+// reports the outcome in its summary instead of failing the run.
+fn load_repo(
+    config: &Config,
+    conn: &SqliteStorage,
+    classifier: &CommitClassifier,
+    repo_path: &str,
+    retry_budget: &Arc<RetryBudget>,
+) -> RepoSummary {
+    let mut repo_summary = RepoSummary::new(repo_path);
+    let result = if config.no_api || git_ops::is_local_repo(repo_path) {
+        fetch_git_only(config, conn, classifier, repo_path, &mut repo_summary)
+    } else {
+        build_github_client(config, repo_path, retry_budget).and_then(|github_client| {
+            let result = fetch_repo(
+                config,
+                conn,
+                &github_client,
+                classifier,
+                repo_path,
+                &mut repo_summary,
+            );
+            repo_summary.skipped_records = github_client.skipped_records();
+            result
+        })
+    };
+    if let Err(e) = result {
+        if e.is_unavailable() {
+            log::warn!("Skipping {}: {}", repo_path, e);
+            repo_summary.skipped = Some(e.to_string());
+        } else {
+            log::error!("Failed to load {}: {}", repo_path, e);
+            repo_summary.error = Some(e.to_string());
+        }
+    }
+    repo_summary
+}
+
+// End of the API commit window: --until, or with --backfill the earliest
+// commit already stored if that is earlier, so only older history is fetched.
+// The bound is inclusive, so that commit is fetched again and replaced.
+fn commits_until(
+    conn: &rusqlite::Connection,
+    config: &Config,
+    repo_full_name: &str,
+) -> Result<Option<DateTime<Utc>>> {
+    if !config.backfill {
+        return Ok(config.until);
+    }
+    let earliest = db::earliest_commit_timestamp(conn, repo_full_name)?;
+    match earliest {
+        Some(earliest) => log::info!(
+            "Backfilling commits of {} older than {}",
+            repo_full_name,
+            earliest
+        ),
+        None => log::info!(
+            "No commits of {} stored yet; backfill fetches the whole window",
+            repo_full_name
+        ),
+    }
+    Ok(match (earliest, config.until) {
+        (Some(earliest), Some(until)) => Some(earliest.min(until)),
+        (earliest, until) => earliest.or(until),
+    })
+}
+
+// The --author values as search filters, or a single unfiltered search.
+fn author_filters(config: &Config) -> Vec<Option<&str>> {
+    if config.authors.is_empty() {
+        vec![None]
+    } else {
+        config.authors.iter().map(|a| Some(a.as_str())).collect()
+    }
+}
+
+// Lists the commits matching `filter`, once per --author when any are given.
+// --max-commits-per-repo caps the combined list; the flag reports whether it
+// cut the listing short.
+fn list_commits(
+    github_client: &GitHubClient,
+    config: &Config,
+    owner: &str,
+    name: &str,
+    filter: CommitFilter,
+) -> Result<(Vec<GitHubCommit>, bool)> {
+    let max_commits = config.max_commits_per_repo;
+    if config.authors.is_empty() {
+        return github_client.get_commits(owner, name, &filter, max_commits);
+    }
+    let mut commits: Vec<GitHubCommit> = Vec::new();
+    for author in &config.authors {
+        let remaining = max_commits.map(|max| max.saturating_sub(commits.len()));
+        if remaining == Some(0) {
+            return Ok((commits, true));
+        }
+        let filter = CommitFilter {
+            author: Some(author.clone()),
+            ..filter.clone()
+        };
+        let (found, truncated) = github_client.get_commits(owner, name, &filter, remaining)?;
+        // An author given by both login and email would list commits twice
+        for commit in found {
+            if !commits.iter().any(|c| c.sha == commit.sha) {
+                commits.push(commit);
+            }
+        }
+        if truncated {
+            return Ok((commits, true));
+        }
+    }
+    Ok((commits, false))
+}
+
+// Records how each merged PR reached the base branch. The merge commit comes
+// from the stored commits when possible and from the API otherwise; the PR's
+// commits are only fetched to tell a rebase from a squash. PRs classified in
+// earlier runs are skipped unless `re_enrich` is set.
+fn classify_integrations(
+    github_client: &GitHubClient,
+    conn: &rusqlite::Connection,
+    owner: &str,
+    name: &str,
+    prs: &[github::GitHubPullRequest],
+    re_enrich: bool,
+) -> Result<()> {
+    let repo_full_name = format!("{}/{}", owner, name);
+    let classified = if re_enrich {
+        HashSet::new()
+    } else {
+        db::classified_pr_numbers(conn, &repo_full_name)?
+    };
+    let merged: Vec<_> = prs
+        .iter()
+        .filter(|pr| pr.merged_at.is_some() && !classified.contains(&pr.number))
+        .filter_map(|pr| Some((pr, pr.merge_commit_sha.as_deref()?)))
+        .collect();
+    log::info!(
+        "Classifying the integration of {} merged PRs of {} ({} already classified)",
+        merged.len(),
+        repo_full_name,
+        classified.len()
+    );
+    for (pr, merge_sha) in merged {
+        let (is_merge, message) = match db::stored_commit_shape(conn, &repo_full_name, merge_sha)? {
+            Some(shape) => shape,
+            None => {
+                let what = format!("merge commit {} of {}", merge_sha, repo_full_name);
+                let Some(commit) =
+                    skip_unavailable(github_client.get_commit(owner, name, merge_sha), &what)?
+                else {
+                    continue;
+                };
+                (commit.is_merge(), commit.commit.message.unwrap_or_default())
+            }
+        };
+        let last_pr_subject = || {
+            let commits = github_client.get_pull_request_commits(owner, name, pr.number)?;
+            Ok(commits
+                .last()
+                .and_then(|c| c.commit.message.as_deref()?.lines().next())
+                .map(str::to_string))
+        };
+        let integration = IntegrationType::classify(
+            merge_sha,
+            is_merge,
+            &message,
+            pr.head.as_ref().map(|b| b.sha.as_str()),
+            last_pr_subject,
+        )?;
+        db::update_pr_integration_type(conn, &repo_full_name, pr.number, integration.as_str())?;
+    }
+    Ok(())
+}
+
+// Extracts the history of a repository without the GitHub API, for --no-api
+// and local directories. Remote repositories are cloned/updated first. The
+// walked branch is --default-branch, else origin/HEAD, else the checked-out HEAD.
+fn fetch_git_only(
+    config: &Config,
+    conn: &rusqlite::Connection,
+    classifier: &CommitClassifier,
+    repo_path: &str,
+    summary: &mut RepoSummary,
+) -> Result<()> {
+    let (owner, name) = parse_repo_url(repo_path)?;
+    let repo_full_name = format!("{}/{}", owner, name);
+    summary.repo_name = repo_full_name.clone();
+    if !config.fetches(Entity::LocalCommits) {
+        log::warn!(
+            "Nothing to load for {} without the API; --only excludes local-commits",
+            repo_full_name
+        );
+        return Ok(());
+    }
+
+    let local_path = if git_ops::is_local_repo(repo_path) {
+        git_ops::local_dir(repo_path).to_path_buf()
+    } else {
+        let max_clone_age = if config.force_fetch {
+            None
+        } else {
+            config.max_clone_age
+        };
+        ensure_repo_cloned_or_updated(
+            repo_path,
+            &config.clone_dir,
+            config.github_token_for(repo_path)?.as_deref(),
+            max_clone_age,
+            config.partial_clone,
+        )?
+    };
+    let branch = config.default_branch.as_deref().unwrap_or("HEAD");
+    let (local_commits, truncated) = extract_local_commits(
+        conn,
+        &local_path,
+        branch,
+        &repo_full_name,
+        classifier,
+        config,
     )?;
-    log::info!("Fetched {} commits for {}", commits1.len(), repo1_full_name);
-    db::insert_github_commits(&conn, &commits1, &repo1_full_name)?;
-
-    // Fetch PRs for repo 1
-    let prs1 = github_client.get_pull_requests(&repo1_owner, &repo1_name, None, None, None)?;
-    log::info!("Fetched {} PRs for {}", prs1.len(), repo1_full_name);
-    db::insert_github_pull_requests(&conn, &prs1, &repo1_full_name)?;
-
-    // Fetch Issues for repo 1
-    let issues1 = github_client.get_issues(
-        &repo1_owner,
-        &repo1_name,
-        None,
+    summary.local_commits = local_commits;
+    summary.truncated = truncated;
+    log::info!(
+        "Extracted {} local commits for {} from {:?} (git only)",
+        local_commits,
+        repo_full_name,
+        local_path
+    );
+    record_default_branch_reachability(conn, &local_path, branch, &repo_full_name)?;
+    if config.tag_changelogs {
+        record_tag_changelogs(conn, &local_path, &repo_full_name, &config.tag_pattern)?;
+    }
+    record_commit_patches(
+        conn,
         None,
-        Some(since_iso.clone()),
+        Some(&local_path),
+        &owner,
+        &name,
+        &config.patch_shas,
+        config.re_enrich,
     )?;
-    log::info!("Fetched {} issues for {}", issues1.len(), repo1_full_name);
-    db::insert_github_issues(&conn, &issues1, &repo1_full_name)?;
+    if config.commit_message == CommitMessage::Full {
+        db::tag_commit_messages(conn, classifier, &repo_full_name)?;
+    }
+    Ok(())
+}
 
-    // Fetch Contributors for repo 1
-    // TODO: Call github_client.get_contributors(&repo1_owner, &repo1_name)?;
-    // TODO: Call db::insert_github_contributors(&conn, &contributors1, &repo1_full_name)?;
+// Stores the patches requested with --patch: from the local clone when it
+// has the commit, otherwise from the API. Commits neither has are skipped.
+fn record_commit_patches(
+    conn: &rusqlite::Connection,
+    github_client: Option<&GitHubClient>,
+    local_path: Option<&Path>,
+    owner: &str,
+    name: &str,
+    shas: &[String],
+    re_enrich: bool,
+) -> Result<()> {
+    let repo_full_name = format!("{}/{}", owner, name);
+    let done = if re_enrich {
+        HashSet::new()
+    } else {
+        db::enriched_shas(conn, &repo_full_name, db::ENRICH_PATCH)?
+    };
+    for sha in shas {
+        if done.contains(sha) {
+            log::info!(
+                "The patch of {} in {} is already stored",
+                sha,
+                repo_full_name
+            );
+            continue;
+        }
+        let local = match local_path {
+            Some(path) => git_ops::commit_patch(path, sha)?,
+            None => None,
+        };
+        let (source, patch) = match (local, github_client) {
+            (Some(patch), _) => ("git", patch),
+            (None, Some(client)) => match client.get_commit_patch(owner, name, sha) {
+                Ok(patch) => ("api", patch),
+                Err(errors::DataError::NotFoundError)
+                | Err(errors::DataError::GitHubApiError { .. }) => {
+                    log::warn!("{} has no commit {}; no patch stored", repo_full_name, sha);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            },
+            (None, None) => {
+                log::warn!("{} has no commit {}; no patch stored", repo_full_name, sha);
+                continue;
+            }
+        };
+        db::insert_commit_patch(conn, &repo_full_name, sha, source, &patch)?;
+        db::mark_enriched(conn, &repo_full_name, sha, db::ENRICH_PATCH)?;
+        log::info!(
+            "Stored the patch of {} in {} ({} bytes, from {})",
+            sha,
+            repo_full_name,
+            patch.len(),
+            source
+        );
+    }
+    Ok(())
+}
 
-    // TODO: Fetch other data (Reviews, Comments) for repo 1 and insert into DB
+// Marks which stored commits of a repository (from the clone, the API or
+// `range`) have reached its default branch, so branch-only work can be told
+// apart from merged work.
+fn record_default_branch_reachability(
+    conn: &rusqlite::Connection,
+    repo_path: &Path,
+    branch: &str,
+    repo_full_name: &str,
+) -> Result<()> {
+    let shas = db::commits_off_default_branch(conn, repo_full_name)?;
+    let reachability = git_ops::reachable_from_branch(repo_path, branch, &shas)?;
+    db::update_on_default_branch(conn, repo_full_name, &reachability)?;
+    log::info!(
+        "{} of {} commits of {} not yet known to be on {} are reachable from it",
+        reachability.iter().filter(|(_, on)| *on).count(),
+        shas.len(),
+        repo_full_name,
+        branch
+    );
+    Ok(())
+}
+
+// Stores the release tags of a clone and, for each release, the commits since
+// the release before it (see `git_ops::changelog_pairs`), replacing the
+// changelogs stored by earlier runs.
+fn record_tag_changelogs(
+    conn: &rusqlite::Connection,
+    repo_path: &Path,
+    repo_full_name: &str,
+    tag_pattern: &str,
+) -> Result<()> {
+    let pattern = regex::Regex::new(tag_pattern).map_err(|e| {
+        errors::DataError::ConfigError(format!("Invalid --tag-pattern '{}': {}", tag_pattern, e))
+    })?;
+    let tags = git_ops::list_tags(repo_path, &pattern)?;
+    db::replace_git_tags(conn, repo_full_name, &tags)?;
+    let mut changelogs = Vec::new();
+    for (from, to) in git_ops::changelog_pairs(&tags) {
+        let commits = git_ops::commit_subjects_between(repo_path, &from.sha, &to.sha)?;
+        changelogs.push(db::TagChangelog {
+            from_tag: &from.name,
+            to_tag: &to.name,
+            commits,
+        });
+    }
+    db::replace_tag_changelogs(conn, repo_full_name, &changelogs)?;
+    log::info!(
+        "Found {} release tags in {}; stored {} changelogs",
+        tags.len(),
+        repo_full_name,
+        changelogs.len()
+    );
+    Ok(())
+}
+
+// The filters a fetch with `config` runs with, as recorded per run.
+fn run_filters(config: &Config) -> db::RunFilters<'_> {
+    db::RunFilters {
+        labels: &config.labels,
+        authors: &config.authors,
+        only: &config.only,
+    }
+}
+
+// Start of the fetch window of one repository: --since, or with
+// --since-last-run the end of the last run that loaded it completely with the
+// same filters (or none), less a safety margin.
+fn fetch_since(
+    conn: &SqliteStorage,
+    config: &Config,
+    repo_full_name: &str,
+) -> Result<DateTime<Utc>> {
+    if !config.since_last_run {
+        return Ok(config.since);
+    }
+    match conn.last_successful_run_finished_at(repo_full_name, &run_filters(config))? {
+        Some(finished_at) => {
+            log::info!(
+                "Fetching changes to {} since the last run ({})",
+                repo_full_name,
+                finished_at
+            );
+            Ok(finished_at - Duration::hours(SINCE_LAST_RUN_MARGIN_HOURS))
+        }
+        None => {
+            log::info!(
+                "No previous successful run for {}; fetching the full window",
+                repo_full_name
+            );
+            Ok(config.since)
+        }
+    }
+}
+
+// Clones/updates one repository, fetches its GitHub data and extracts its
+// local history, recording counts in `summary` as each step completes.
+fn fetch_repo(
+    config: &Config,
+    conn: &SqliteStorage,
+    github_client: &GitHubClient,
+    classifier: &CommitClassifier,
+    repo_path: &str,
+    summary: &mut RepoSummary,
+) -> Result<()> {
+    // Extract repo owner/name from config
+    let (owner, name) = parse_repo_url(repo_path)?;
+    summary.repo_name = format!("{}/{}", owner, name);
+
+    let (owner, name, repo_info) = resolve_canonical_repo(github_client, &owner, &name)?;
+    let repo_full_name = format!("{}/{}", owner, name);
+    summary.repo_name = repo_full_name.clone();
+    let since = fetch_since(conn, config, &repo_full_name)?;
+    let since_iso = api_timestamp(since);
+    let fetched_at = Utc::now();
+    db::insert_repo_snapshot(conn, &repo_info, &repo_full_name, &fetched_at)?;
+
+    // A repository nobody pushed to since the last complete fetch has no new
+    // commits or PR merges, so those (and local extraction) are skipped, as
+    // long as the fetches so far reach back to --since.
+    let last_pushed_at = db::last_seen_pushed_at(conn, &repo_full_name, &since)?;
+    let unchanged =
+        !config.force_fetch && last_pushed_at.is_some_and(|seen| repo_info.pushed_at <= seen);
+    summary.unchanged = unchanged;
+    if unchanged {
+        log::info!(
+            "{} has not been pushed to since {}; skipping commits and PRs \
+             (use --force-fetch to fetch anyway)",
+            repo_full_name,
+            repo_info.pushed_at.to_rfc3339()
+        );
+    }
+    let fetches = |entity: Entity| {
+        config.fetches(entity)
+            && !(unchanged
+                && matches!(
+                    entity,
+                    Entity::Commits | Entity::PullRequests | Entity::LocalCommits
+                ))
+    };
+
+    // Ensure the repository is cloned/updated; only local extraction needs it
+    let local_path = if fetches(Entity::LocalCommits) {
+        log::info!("Ensuring repository {} is available locally...", repo_path);
+        let max_clone_age = if config.force_fetch {
+            None
+        } else {
+            config.max_clone_age
+        };
+        let local_path = ensure_repo_cloned_or_updated(
+            repo_path,
+            &config.clone_dir,
+            github_client.token(),
+            max_clone_age,
+            config.partial_clone,
+        )?;
+        log::info!("Repository path: {:?}", local_path);
+        Some(local_path)
+    } else {
+        None
+    };
 
     log::info!(
         "Fetching data for {} since {}...",
-        repo2_full_name,
+        repo_full_name,
         since_iso
     );
-    // Fetch commits for repo 2
-    let commits2 = github_client.get_commits(
-        &repo2_owner,
-        &repo2_name,
-        Some(since_iso.clone()),
-        None,
-        None,
-    )?;
-    log::info!("Fetched {} commits for {}", commits2.len(), repo2_full_name);
-    db::insert_github_commits(&conn, &commits2, &repo2_full_name)?;
-
-    // Fetch PRs for repo 2
-    let prs2 = github_client.get_pull_requests(&repo2_owner, &repo2_name, None, None, None)?;
-    log::info!("Fetched {} PRs for {}", prs2.len(), repo2_full_name);
-    db::insert_github_pull_requests(&conn, &prs2, &repo2_full_name)?;
-
-    // Fetch Issues for repo 2
-    let issues2 = github_client.get_issues(
-        &repo2_owner,
-        &repo2_name,
-        None,
-        None,
-        Some(since_iso.clone()),
+    // Fetch commits
+    let mut commits = Vec::new();
+    let commits_until = commits_until(conn, config, &repo_full_name)?;
+    let backfilled = config.backfill && commits_until.is_some_and(|until| until <= since);
+    if backfilled {
+        log::info!(
+            "Commits of {} are stored back to --since already; nothing to backfill",
+            repo_full_name
+        );
+    }
+    // The commit times the stored commits cover, for --verify; unknown when
+    // commits aren't fetched
+    let mut commit_window = (backfilled && fetches(Entity::Commits)).then_some(CommitWindow {
+        since,
+        until: config.until,
+    });
+    if fetches(Entity::Commits) && !backfilled {
+        let filter = CommitFilter {
+            since: Some(since_iso.clone()),
+            until: commits_until.map(api_timestamp),
+            ..CommitFilter::default()
+        };
+        let truncated;
+        (commits, truncated) = list_commits(github_client, config, &owner, &name, filter)?;
+        if truncated {
+            log::warn!(
+                "Stopped fetching commits for {} at the --max-commits-per-repo cap of {}; \
+                 the API commit data is truncated",
+                repo_full_name,
+                commits.len()
+            );
+            summary.truncated = true;
+        }
+        // A capped fetch keeps the newest commits, so it only covers the
+        // window back to the oldest one it got
+        let oldest = commits
+            .iter()
+            .filter_map(|commit| commit.commit_timestamp())
+            .min()
+            .filter(|_| truncated);
+        commit_window = Some(CommitWindow {
+            since: oldest.map_or(since, |oldest| oldest.max(since)),
+            until: config.until,
+        });
+        github::sort_commits(&mut commits, config.commit_order);
+        log::info!("Fetched {} commits for {}", commits.len(), repo_full_name);
+        conn.insert_github_commits(
+            &commits,
+            &repo_full_name,
+            config.batch_size,
+            classifier,
+            config.commit_message,
+            config.commit_date,
+        )?;
+        summary.commits = commits.len();
+
+        // Record CI results per commit
+        if config.commit_statuses {
+            fetch_commit_statuses(
+                github_client,
+                conn,
+                &owner,
+                &name,
+                &commits,
+                config.re_enrich,
+            )?;
+        }
+    }
+
+    let mut prs = Vec::new();
+    if fetches(Entity::PullRequests) {
+        // Fetch PRs. The list endpoint can't filter by label or author, so
+        // filtered PRs are found through search and then fetched one by one.
+        prs = if config.labels.is_empty() && config.authors.is_empty() {
+            // A capped sample should be the newest PRs, so ask for them first
+            let (sort, direction) = match config.limit_prs {
+                Some(_) => (Some("created".to_string()), Some("desc".to_string())),
+                None => (None, None),
+            };
+            let (prs, truncated) = github_client.get_pull_requests(
+                &owner,
+                &name,
+                None,
+                sort,
+                direction,
+                config.limit_prs,
+            )?;
+            if truncated {
+                log::info!(
+                    "Kept the {} most recent PRs of {} (--limit-prs)",
+                    prs.len(),
+                    repo_full_name
+                );
+            }
+            prs
+        } else {
+            let mut numbers = Vec::new();
+            for author in author_filters(config) {
+                numbers.extend(github_client.search_pull_request_numbers(
+                    &owner,
+                    &name,
+                    &config.labels,
+                    author,
+                )?);
+            }
+            numbers.sort_unstable();
+            numbers.dedup();
+            // Higher numbers are newer
+            if let Some(limit) = config.limit_prs {
+                numbers.drain(..numbers.len().saturating_sub(limit));
+            }
+            let mut prs = Vec::with_capacity(numbers.len());
+            for number in numbers {
+                let what = format!("PR #{} of {}", number, repo_full_name);
+                prs.extend(skip_unavailable(
+                    github_client.get_pull_request(&owner, &name, number),
+                    &what,
+                )?);
+            }
+            prs
+        };
+        log::info!("Fetched {} PRs for {}", prs.len(), repo_full_name);
+        conn.insert_github_pull_requests(&prs, &repo_full_name, config.batch_size)?;
+        summary.pull_requests = prs.len();
+    }
+
+    let mut issues = Vec::new();
+    if fetches(Entity::Issues) {
+        if config.authors.is_empty() {
+            let filter = IssueFilter {
+                since: Some(since_iso.to_string()),
+                labels: config.labels.clone(),
+                ..IssueFilter::default()
+            };
+            let truncated;
+            (issues, truncated) =
+                github_client.get_issues(&owner, &name, &filter, config.limit_issues)?;
+            if truncated {
+                log::info!(
+                    "Kept the {} most recent issues of {} (--limit-issues)",
+                    issues.len(),
+                    repo_full_name
+                );
+            }
+        } else {
+            for author in &config.authors {
+                let found = github_client.search_issues_by_author(
+                    &owner,
+                    &name,
+                    &since_iso,
+                    &config.labels,
+                    author,
+                )?;
+                issues.extend(found);
+            }
+            issues.sort_by_key(|issue| issue.number);
+            issues.dedup_by_key(|issue| issue.number);
+            if let Some(limit) = config.limit_issues {
+                issues.drain(..issues.len().saturating_sub(limit));
+            }
+        }
+        log::info!("Fetched {} issues for {}", issues.len(), repo_full_name);
+        conn.insert_github_issues(&issues, &repo_full_name, config.batch_size)?;
+        summary.issues = issues.len();
+
+        // Look up who closed each closed issue (the list endpoint omits it)
+        if config.enrich_closed_by {
+            enrich_closed_by(github_client, conn, &owner, &name, &issues)?;
+        }
+    }
+
+    if fetches(Entity::Contributors) {
+        let contributors = github_client.get_contributors(&owner, &name)?;
+        log::info!(
+            "Fetched {} contributors for {}",
+            contributors.len(),
+            repo_full_name
+        );
+        conn.insert_github_contributors(&contributors, &repo_full_name, config.batch_size)?;
+        summary.contributors = contributors.len();
+    }
+
+    // Who can push, as a governance signal; only visible with push access
+    if config.collaborators {
+        match github_client.get_collaborators(&owner, &name) {
+            Ok(collaborators) => {
+                db::replace_collaborators(conn, &collaborators, &repo_full_name, &fetched_at)?
+            }
+            Err(errors::DataError::GitHubApiError { status, .. })
+                if status == reqwest::StatusCode::FORBIDDEN
+                    || status == reqwest::StatusCode::UNAUTHORIZED =>
+            {
+                log::warn!(
+                    "Skipping collaborators of {}: listing them needs push access ({})",
+                    repo_full_name,
+                    status
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if config.events {
+        let events = github_client.get_repo_events(&owner, &name)?;
+        db::insert_repo_events(conn, &events, &repo_full_name, config.batch_size)?;
+    }
+
+    if fetches(Entity::Milestones) {
+        let milestones = github_client.get_milestones(&owner, &name)?;
+        log::info!(
+            "Fetched {} milestones for {}",
+            milestones.len(),
+            repo_full_name
+        );
+        db::insert_milestones(conn, &milestones, &repo_full_name, config.batch_size)?;
+    }
+
+    if fetches(Entity::Languages) {
+        let languages = github_client.get_languages(&owner, &name)?;
+        db::insert_repo_languages(
+            conn,
+            &languages,
+            &repo_full_name,
+            &fetched_at,
+            config.batch_size,
+        )?;
+    }
+
+    if fetches(Entity::PullRequests) {
+        // Fetch review thread resolution
+        if config.review_threads {
+            fetch_review_threads(github_client, conn, &owner, &name, config.batch_size)?;
+        }
+
+        // Record the first review and the reviewers of each PR
+        if config.reviews {
+            enrich_reviews(
+                github_client,
+                conn,
+                &owner,
+                &name,
+                &prs,
+                config.reviews_scope,
+            )?;
+        }
+    }
+
+    if config.comments {
+        enrich_first_comments(github_client, conn, &owner, &name, &issues, &prs)?;
+    }
+    // Only the comments and reviews passes change its inputs
+    let reviews_ran = fetches(Entity::PullRequests) && config.reviews;
+    if config.comments || reviews_ran {
+        db::derive_first_response(conn, &repo_full_name)?;
+    }
+
+    // Extract commit history from the local clone
+    if let Some(local_path) = &local_path {
+        // The clone's HEAD is whatever was checked out at clone time and is
+        // never advanced by later fetches, so walk the default branch instead
+        let branch = config
+            .default_branch
+            .as_deref()
+            .unwrap_or(&repo_info.default_branch);
+        let (local_commits, truncated) = extract_local_commits(
+            conn,
+            local_path,
+            branch,
+            &repo_full_name,
+            classifier,
+            config,
+        )?;
+        summary.local_commits = local_commits;
+        if truncated {
+            log::warn!(
+                "Stopped extracting local commits for {} at the --max-commits-per-repo cap; \
+                 the next run continues from there",
+                repo_full_name
+            );
+            summary.truncated = true;
+        }
+        log::info!(
+            "Extracted {} local commits for {}",
+            summary.local_commits,
+            repo_full_name
+        );
+        record_default_branch_reachability(conn, local_path, branch, &repo_full_name)?;
+        if config.tag_changelogs {
+            record_tag_changelogs(conn, local_path, &repo_full_name, &config.tag_pattern)?;
+        }
+    }
+    record_commit_patches(
+        conn,
+        Some(github_client),
+        local_path.as_deref(),
+        &owner,
+        &name,
+        &config.patch_shas,
+        config.re_enrich,
     )?;
-    log::info!("Fetched {} issues for {}", issues2.len(), repo2_full_name);
-    db::insert_github_issues(&conn, &issues2, &repo2_full_name)?;
 
-    // Fetch Contributors for repo 2
-    // TODO: Call github_client.get_contributors(&repo2_owner, &repo2_name)?;
-    // TODO: Call db::insert_github_contributors(&conn, &contributors2, &repo2_full_name)?;
+    // Classify merged PRs once their merge commits are stored locally
+    if fetches(Entity::PullRequests) && config.integration_type {
+        classify_integrations(github_client, conn, &owner, &name, &prs, config.re_enrich)?;
+    }
+
+    // Only a complete, unfiltered commit and PR fetch makes later runs
+    // comparable against this pushed_at
+    if fetches(Entity::Commits)
+        && fetches(Entity::PullRequests)
+        && config.labels.is_empty()
+        && config.authors.is_empty()
+        && config.until.is_none()
+        && !config.backfill
+        && !summary.truncated
+    {
+        db::record_pushed_at(conn, &repo_full_name, &repo_info.pushed_at, &since)?;
+    }
+
+    // Re-tag reverts/backports of earlier commits from their stored messages,
+    // so pattern changes apply to them too. Trimmed messages may have lost
+    // the markers, so then only the flags set at insert are kept.
+    if config.commit_message == CommitMessage::Full {
+        db::tag_commit_messages(conn, classifier, &repo_full_name)?;
+    }
 
-    // TODO: Fetch other data (Reviews, Comments) for repo 2 and insert into DB
+    // Tag authorship from the maintainer list, so list changes also apply to
+    // items stored by earlier runs
+    let maintainers = config.maintainers_for(&repo_full_name);
+    if !maintainers.is_empty() {
+        db::tag_maintainers(conn, &repo_full_name, &maintainers)?;
+    }
 
-    // TODO: Fetch git-specific data if needed (git_ops + db)
+    if fetches(Entity::Issues) || fetches(Entity::PullRequests) {
+        search::refresh_index(conn, &repo_full_name)?;
+    }
 
-    log::info!("Data loading process completed successfully.");
+    // Cross-check what was fetched against what was stored
+    if config.verify {
+        summary.anomalies = verify::verify_repo(
+            conn,
+            &repo_full_name,
+            commit_window.as_ref(),
+            &commits,
+            &prs,
+            &issues,
+        )?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use git2::{Repository, Signature};
+    use tempfile::TempDir;
+
+    // A repository with `commits` empty commits on its HEAD.
+    fn repo_with_commits(commits: usize) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = Signature::now("Alice", "alice@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        for i in 0..commits {
+            let parent = repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+            let parents: Vec<_> = parent.iter().collect();
+            repo.commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                &format!("c{}", i),
+                &tree,
+                &parents,
+            )
+            .unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn panicked_loaders_become_failed_repositories() {
+        let panicked = std::panic::catch_unwind(|| -> RepoSummary {
+            panic!("index out of bounds");
+        });
+        let summary = joined_summary("a/b", panicked);
+        assert_eq!(summary.repo_name, "a/b");
+        assert_eq!(
+            summary.error.as_deref(),
+            Some("loader panicked: index out of bounds")
+        );
+
+        let loaded = joined_summary("c/d", Ok(RepoSummary::new("c/d")));
+        assert!(loaded.error.is_none());
+    }
+
+    #[test]
+    fn repo_concurrency_loads_both_repositories_on_their_own_connections() {
+        let (repo1, repo2) = (repo_with_commits(2), repo_with_commits(3));
+        let work = TempDir::new().unwrap();
+        let db_path = work.path().join("data.db");
+        let config = Config::try_parse_from([
+            "data_loader_rust",
+            "--repo1-path",
+            repo1.path().to_str().unwrap(),
+            "--repo2-path",
+            repo2.path().to_str().unwrap(),
+            "--db-path",
+            db_path.to_str().unwrap(),
+            "--clone-dir",
+            work.path().to_str().unwrap(),
+            "--repo-concurrency",
+            "2",
+        ])
+        .unwrap();
+        let conn = SqliteStorage::open(&config.db_path, StdDuration::from_secs(5)).unwrap();
+        conn.create_tables().unwrap();
+
+        let summary = run_fetch(&config, &conn).unwrap();
+        let loaded: Vec<_> = summary
+            .repos
+            .iter()
+            .map(|repo| (repo.error.clone(), repo.local_commits))
+            .collect();
+        assert_eq!(loaded, [(None, 2), (None, 3)]);
+        let stored: i64 = conn
+            .query_row("SELECT COUNT(*) FROM git_commits", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, 5);
+        let journal: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal, "wal");
+    }
+}
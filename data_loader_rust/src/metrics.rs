@@ -0,0 +1,206 @@
+use crate::errors::{DataError, Result};
+use crate::queries::{self, Window};
+use rusqlite::Connection;
+use std::fmt;
+
+/// The value of a metric for one repository.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricValue {
+    Count(i64),
+    Ratio(f64),
+    /// Not enough data to compute the metric.
+    Missing,
+}
+
+impl fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Padded so table column widths apply
+        let text = match self {
+            MetricValue::Count(n) => n.to_string(),
+            MetricValue::Ratio(r) => format!("{:.2}", r),
+            MetricValue::Missing => "-".to_string(),
+        };
+        f.pad(&text)
+    }
+}
+
+/// A per-repository number shown in the report's comparison table.
+/// Implement this and register it to add a custom comparison metric.
+pub trait Metric {
+    /// Short identifier, also used to select the metric with `--metric`.
+    fn name(&self) -> &str;
+
+    fn compute(&self, conn: &Connection, repo_name_full: &str) -> Result<MetricValue>;
+}
+
+type CountQuery = fn(&Connection, &str) -> Result<i64>;
+
+// A metric that is a single count query.
+struct CountMetric {
+    name: &'static str,
+    query: CountQuery,
+}
+
+impl Metric for CountMetric {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn compute(&self, conn: &Connection, repo_name_full: &str) -> Result<MetricValue> {
+        Ok(MetricValue::Count((self.query)(conn, repo_name_full)?))
+    }
+}
+
+type WindowQuery = fn(&Connection, &str, &Window) -> Result<i64>;
+
+// A count query over a reporting period.
+struct WindowCountMetric {
+    name: &'static str,
+    window: Window,
+    query: WindowQuery,
+}
+
+impl Metric for WindowCountMetric {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn compute(&self, conn: &Connection, repo_name_full: &str) -> Result<MetricValue> {
+        Ok(MetricValue::Count((self.query)(
+            conn,
+            repo_name_full,
+            &self.window,
+        )?))
+    }
+}
+
+/// Ordered collection of metrics the report iterates.
+#[derive(Default)]
+pub struct MetricRegistry {
+    metrics: Vec<Box<dyn Metric>>,
+}
+
+impl MetricRegistry {
+    /// A registry holding the built-in metrics. With `exclude_merges`, the
+    /// `commits` metric leaves out merge commits.
+    pub fn with_builtins(exclude_merges: bool) -> Self {
+        let mut registry = Self::default();
+        let commits: CountQuery = if exclude_merges {
+            queries::non_merge_commit_count
+        } else {
+            queries::commit_count
+        };
+        let builtins: [(&'static str, CountQuery); 5] = [
+            ("commits", commits),
+            ("merged_prs", queries::merged_pr_count),
+            ("commit_authors", queries::unique_authors),
+            ("resolved_threads", |conn, repo| {
+                Ok(queries::review_thread_totals(conn, repo)?.0)
+            }),
+            ("unresolved_threads", |conn, repo| {
+                Ok(queries::review_thread_totals(conn, repo)?.1)
+            }),
+        ];
+        for (name, query) in builtins {
+            registry.register(Box::new(CountMetric { name, query }));
+        }
+        registry
+    }
+
+    /// Adds the contributor growth and retention metrics for `window`.
+    pub fn register_contributor_metrics(&mut self, window: Window) {
+        let metrics: [(&'static str, WindowQuery); 2] = [
+            ("new_contributors", queries::new_contributors),
+            ("retained_contributors", queries::retained_contributors),
+        ];
+        for (name, query) in metrics {
+            self.register(Box::new(WindowCountMetric {
+                name,
+                window,
+                query,
+            }));
+        }
+    }
+
+    /// Adds a metric after the ones already registered.
+    pub fn register(&mut self, metric: Box<dyn Metric>) {
+        self.metrics.push(metric);
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.metrics.iter().map(|m| m.name()).collect()
+    }
+
+    /// The metrics named in `names`, in that order, or all of them if `names`
+    /// is empty. Unknown names are a configuration error.
+    pub fn select(&self, names: &[String]) -> Result<Vec<&dyn Metric>> {
+        if names.is_empty() {
+            return Ok(self.metrics.iter().map(|m| m.as_ref()).collect());
+        }
+        names
+            .iter()
+            .map(|name| {
+                self.metrics
+                    .iter()
+                    .find(|m| m.name() == name)
+                    .map(|m| m.as_ref())
+                    .ok_or_else(|| {
+                        DataError::ConfigError(format!(
+                            "Unknown metric '{}'; available: {}",
+                            name,
+                            self.names().join(", ")
+                        ))
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_tables;
+
+    struct Constant;
+
+    impl Metric for Constant {
+        fn name(&self) -> &str {
+            "constant"
+        }
+
+        fn compute(&self, _conn: &Connection, _repo: &str) -> Result<MetricValue> {
+            Ok(MetricValue::Ratio(0.5))
+        }
+    }
+
+    #[test]
+    fn selects_metrics_by_name_in_order() {
+        let mut registry = MetricRegistry::with_builtins(false);
+        registry.register(Box::new(Constant));
+        assert_eq!(registry.select(&[]).unwrap().len(), 6);
+
+        let names = ["constant".to_string(), "commits".to_string()];
+        let selected = registry.select(&names).unwrap();
+        let selected: Vec<&str> = selected.iter().map(|m| m.name()).collect();
+        assert_eq!(selected, ["constant", "commits"]);
+
+        assert!(registry.select(&["nope".to_string()]).is_err());
+    }
+
+    #[test]
+    fn builtins_compute_counts() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO github_commits (sha, repo_name, author_login) VALUES ('a', 'x/y', 'alice')",
+            [],
+        )
+        .unwrap();
+        let registry = MetricRegistry::with_builtins(false);
+        let commits = registry.select(&["commits".to_string()]).unwrap()[0]
+            .compute(&conn, "x/y")
+            .unwrap();
+        assert_eq!(commits, MetricValue::Count(1));
+        assert_eq!(MetricValue::Missing.to_string(), "-");
+    }
+}
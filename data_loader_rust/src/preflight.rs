@@ -0,0 +1,221 @@
+use crate::config::{Config, Entity, ReviewsScope};
+use crate::errors::{DataError, Result};
+use crate::github::GitHubClient;
+
+// Items per page on the list endpoints the fetch uses.
+const PAGE_SIZE: i64 = 100;
+
+/// Estimated number of API requests per fetch pass for one repository,
+/// derived from cheap search counts.
+#[derive(Debug, Clone)]
+pub struct Estimate {
+    pub repo_name: String,
+    /// (pass, REST requests)
+    pub passes: Vec<(&'static str, i64)>,
+    /// GraphQL requests, which draw on a separate budget
+    pub graphql: i64,
+}
+
+impl Estimate {
+    /// REST requests across all passes.
+    pub fn total(&self) -> i64 {
+        self.passes.iter().map(|(_, requests)| requests).sum()
+    }
+}
+
+fn pages(items: i64) -> i64 {
+    (items + PAGE_SIZE - 1) / PAGE_SIZE
+}
+
+/// Estimates the requests a fetch of `owner/name` will make with the enabled
+/// passes. Counts come from the search API over the `--since` window, so they
+/// are approximate: the commit search only covers the default branch, and
+/// the contributor list is assumed to fit on one page.
+pub fn estimate_repo(
+    client: &GitHubClient,
+    config: &Config,
+    owner: &str,
+    name: &str,
+) -> Result<Estimate> {
+    let repo = format!("repo:{}/{}", owner, name);
+    let since = config.since.format("%Y-%m-%d");
+    let labels: String = config
+        .labels
+        .iter()
+        .map(|label| format!(" label:\"{}\"", label))
+        .collect();
+    // One search per --author, summed, since the fetch runs one per author too
+    let authors: Vec<String> = if config.authors.is_empty() {
+        vec![String::new()]
+    } else {
+        config
+            .authors
+            .iter()
+            .map(|author| format!(" author:{}", author))
+            .collect()
+    };
+    let count = |kind: &str, query: String| {
+        let mut total = 0;
+        for author in &authors {
+            let query = format!("{}{}", query, author);
+            let matched = client.search_total_count(kind, &query)?;
+            log::debug!("Search {} '{}' matched {}", kind, query, matched);
+            total += matched;
+        }
+        Ok::<_, DataError>(total)
+    };
+
+    let mut estimate = Estimate {
+        repo_name: format!("{}/{}", owner, name),
+        passes: vec![("repository info", 1)],
+        graphql: 0,
+    };
+    if config.fetches(Entity::Commits) {
+        let mut commits = count("commits", format!("{} committer-date:>={}", repo, since))?;
+        if let Some(max) = config.max_commits_per_repo {
+            commits = commits.min(max as i64);
+        }
+        estimate.passes.push(("commits", pages(commits)));
+        if config.commit_statuses {
+            // One combined-status and one check-runs request per commit
+            estimate.passes.push(("commit statuses", 2 * commits));
+        }
+    }
+    if config.fetches(Entity::PullRequests) {
+        let mut prs = count("issues", format!("{} is:pr{}", repo, labels))?;
+        if let Some(limit) = config.limit_prs {
+            prs = prs.min(limit as i64);
+        }
+        if config.labels.is_empty() && config.authors.is_empty() {
+            estimate.passes.push(("pull requests", pages(prs)));
+        } else {
+            // Search pages, then one request per matching PR
+            estimate.passes.push(("pull requests", pages(prs) + prs));
+        }
+        if config.reviews {
+            let reviewed = match config.reviews_scope {
+                ReviewsScope::All => prs,
+                ReviewsScope::Merged => {
+                    count("issues", format!("{} is:pr is:merged{}", repo, labels))?
+                }
+                ReviewsScope::Closed => {
+                    count("issues", format!("{} is:pr is:closed{}", repo, labels))?
+                }
+            };
+            estimate.passes.push(("reviews", reviewed));
+        }
+        if config.comments {
+            estimate.passes.push(("PR comments", prs));
+        }
+        if config.review_threads {
+            estimate.graphql = pages(prs);
+        }
+        if config.integration_type {
+            // Assumes merge commits are stored and about one PR-commits
+            // request per merged PR
+            let merged = count("issues", format!("{} is:pr is:merged{}", repo, labels))?;
+            estimate.passes.push(("integration type", merged));
+        }
+    }
+    if config.fetches(Entity::Issues) {
+        // The issues endpoint returns PRs too
+        let mut issues = count("issues", format!("{} updated:>={}{}", repo, since, labels))?;
+        if let Some(limit) = config.limit_issues {
+            issues = issues.min(limit as i64);
+        }
+        estimate.passes.push(("issues", pages(issues)));
+        if config.comments {
+            // At most; only issues with comments are requested, and the
+            // count includes PRs
+            estimate.passes.push(("issue comments", issues));
+        }
+        if config.enrich_closed_by {
+            let closed = count(
+                "issues",
+                format!("{} is:issue is:closed updated:>={}{}", repo, since, labels),
+            )?;
+            estimate.passes.push(("closed_by", closed));
+        }
+    }
+    if config.fetches(Entity::Contributors) {
+        estimate.passes.push(("contributors", 1));
+    }
+    if config.collaborators {
+        estimate.passes.push(("collaborators", 1));
+    }
+    if config.events {
+        // At most 300 events, 100 per page
+        estimate.passes.push(("events", 3));
+    }
+    if config.fetches(Entity::Milestones) {
+        estimate.passes.push(("milestones", 1));
+    }
+    if config.fetches(Entity::Languages) {
+        estimate.passes.push(("languages", 1));
+    }
+    Ok(estimate)
+}
+
+/// Prints the per-pass estimate of one repository.
+pub fn print_estimate(estimate: &Estimate) {
+    println!("Estimated requests for {}:", estimate.repo_name);
+    for (pass, requests) in &estimate.passes {
+        println!("  {:<20} {:>8}", pass, requests);
+    }
+    println!("  {:<20} {:>8}", "total (REST)", estimate.total());
+    if estimate.graphql > 0 {
+        println!(
+            "  {:<20} {:>8}",
+            "review threads (GraphQL)", estimate.graphql
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rawdump::{self, RawResponses};
+    use clap::Parser;
+
+    #[test]
+    fn estimate_counts_requests_per_enabled_pass() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let search = |query: &str, total: i64| {
+            let url = format!(
+                "https://api.github.com/search/issues?q={}&per_page=1",
+                url::form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>()
+            );
+            let body = serde_json::json!({
+                "total_count": total, "incomplete_results": false, "items": []
+            });
+            rawdump::save(dir.path(), &url, &body.to_string(), None).unwrap();
+        };
+        search("repo:a/b is:pr", 250);
+        search("repo:a/b is:pr is:merged", 120);
+        let client = GitHubClient::new(None)
+            .unwrap()
+            .with_raw_responses(RawResponses::Replay(dir.path().to_path_buf()));
+        let config = Config::try_parse_from([
+            "data_loader_rust",
+            "--only",
+            "pull-requests,contributors",
+            "--reviews",
+            "--limit-prs",
+            "200",
+        ])
+        .unwrap();
+
+        let estimate = estimate_repo(&client, &config, "a", "b").unwrap();
+        assert_eq!(
+            estimate.passes,
+            [
+                ("repository info", 1),
+                ("pull requests", 2),
+                ("reviews", 120),
+                ("contributors", 1),
+            ]
+        );
+        assert_eq!(estimate.total(), 124);
+        assert_eq!(estimate.graphql, 0);
+    }
+}
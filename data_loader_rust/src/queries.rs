@@ -0,0 +1,1120 @@
+use crate::errors::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+
+/// A reporting period from `start` (inclusive) to `end` (exclusive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Window {
+    pub fn midpoint(&self) -> DateTime<Utc> {
+        self.start + (self.end - self.start) / 2
+    }
+}
+
+/// Number of GitHub commits stored for a repository.
+pub fn commit_count(conn: &Connection, repo_name_full: &str) -> Result<i64> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM github_commits WHERE repo_name = ?1",
+        params![repo_name_full],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Number of GitHub commits stored for a repository, leaving out merge commits.
+/// Commits stored before parent counts were recorded count as non-merges.
+pub fn non_merge_commit_count(conn: &Connection, repo_name_full: &str) -> Result<i64> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM github_commits WHERE repo_name = ?1 AND NOT COALESCE(is_merge, 0)",
+        params![repo_name_full],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Number of pull requests that were merged.
+pub fn merged_pr_count(conn: &Connection, repo_name_full: &str) -> Result<i64> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM github_pull_requests WHERE repo_name = ?1 AND merged_at IS NOT NULL",
+        params![repo_name_full],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Number of distinct GitHub users that authored commits.
+pub fn unique_authors(conn: &Connection, repo_name_full: &str) -> Result<i64> {
+    let count = conn.query_row(
+        r"
+        SELECT COUNT(DISTINCT author_login) FROM github_commits
+        WHERE repo_name = ?1 AND author_login IS NOT NULL
+        ",
+        params![repo_name_full],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Total resolved and unresolved review threads across a repository's PRs.
+pub fn review_thread_totals(conn: &Connection, repo_name_full: &str) -> Result<(i64, i64)> {
+    let totals = conn.query_row(
+        r"
+        SELECT COALESCE(SUM(resolved), 0), COALESCE(SUM(unresolved), 0)
+        FROM pr_review_threads WHERE repo_name = ?1
+        ",
+        params![repo_name_full],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(totals)
+}
+
+/// Local commit authors (by case-insensitive email) whose first commit in the
+/// repository's stored history falls within `window`.
+pub fn new_contributors(conn: &Connection, repo_name_full: &str, window: &Window) -> Result<i64> {
+    let count = conn.query_row(
+        r"
+        SELECT COUNT(*) FROM (
+            SELECT MIN(julianday(commit_timestamp)) AS first_seen
+            FROM git_commits
+            WHERE repo_name = ?1 AND author_email IS NOT NULL AND commit_timestamp IS NOT NULL
+            GROUP BY LOWER(author_email)
+        )
+        WHERE first_seen >= julianday(?2) AND first_seen < julianday(?3)
+        ",
+        params![
+            repo_name_full,
+            window.start.to_rfc3339(),
+            window.end.to_rfc3339()
+        ],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Local commit authors who committed in both the first and the second half of
+/// `window`.
+pub fn retained_contributors(
+    conn: &Connection,
+    repo_name_full: &str,
+    window: &Window,
+) -> Result<i64> {
+    let count = conn.query_row(
+        r"
+        SELECT COUNT(*) FROM (
+            SELECT LOWER(author_email)
+            FROM git_commits
+            WHERE repo_name = ?1 AND author_email IS NOT NULL
+              AND julianday(commit_timestamp) >= julianday(?2)
+              AND julianday(commit_timestamp) < julianday(?4)
+            GROUP BY LOWER(author_email)
+            HAVING MIN(julianday(commit_timestamp)) < julianday(?3)
+               AND MAX(julianday(commit_timestamp)) >= julianday(?3)
+        )
+        ",
+        params![
+            repo_name_full,
+            window.start.to_rfc3339(),
+            window.midpoint().to_rfc3339(),
+            window.end.to_rfc3339()
+        ],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Distinct people who touched each subsystem, counting commit authors and
+/// `Co-authored-by` co-authors by case-insensitive email. A low count marks a
+/// subsystem maintained by few people (a bus-factor proxy).
+pub fn subsystem_author_counts(
+    conn: &Connection,
+    repo_name_full: &str,
+) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT subsystem, COUNT(DISTINCT email) FROM (
+            SELECT s.subsystem, LOWER(c.author_email) AS email
+            FROM commit_subsystems s
+            JOIN git_commits c ON c.repo_name = s.repo_name AND c.sha = s.sha
+            WHERE s.repo_name = ?1
+            UNION
+            SELECT s.subsystem, LOWER(a.email)
+            FROM commit_subsystems s
+            JOIN commit_coauthors a ON a.repo_name = s.repo_name AND a.commit_sha = s.sha
+            WHERE s.repo_name = ?1
+        )
+        WHERE email IS NOT NULL
+        GROUP BY subsystem
+        ORDER BY subsystem
+        ",
+    )?;
+    let counts = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(counts)
+}
+
+/// Lines inserted and deleted per local commit author (by case-insensitive
+/// email), largest total churn first. Commits without line counts, such as
+/// those extracted from a partial clone, are left out, as are merge commits
+/// when `exclude_merges` is set (their diff repeats the merged work).
+pub fn author_churn(
+    conn: &Connection,
+    repo_name_full: &str,
+    exclude_merges: bool,
+) -> Result<Vec<(String, i64, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT LOWER(author_email), SUM(insertions), SUM(deletions)
+        FROM git_commits
+        WHERE repo_name = ?1 AND author_email IS NOT NULL AND insertions IS NOT NULL
+          AND NOT (?2 AND COALESCE(is_merge, 0))
+        GROUP BY LOWER(author_email)
+        ORDER BY SUM(insertions) + SUM(deletions) DESC, LOWER(author_email)
+        ",
+    )?;
+    let churn = stmt
+        .query_map(params![repo_name_full, exclude_merges], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(churn)
+}
+
+/// Commits with CI results, split into (green, red, other). A commit is red if
+/// any status or check failed, green if all succeeded (or were skipped/neutral),
+/// and other while results are still pending.
+pub fn ci_outcomes(conn: &Connection, repo_name_full: &str) -> Result<(i64, i64, i64)> {
+    let outcomes = conn.query_row(
+        r"
+        SELECT COALESCE(SUM(green AND NOT red), 0),
+               COALESCE(SUM(red), 0),
+               COALESCE(SUM(NOT green AND NOT red), 0)
+        FROM (
+            SELECT sha,
+                   MAX(state IN ('failure', 'error', 'timed_out', 'cancelled', 'action_required')) AS red,
+                   MIN(state IN ('success', 'neutral', 'skipped')) AS green
+            FROM commit_statuses
+            WHERE repo_name = ?1
+            GROUP BY sha
+        )
+        ",
+        params![repo_name_full],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    Ok(outcomes)
+}
+
+/// Hours from opening to first review for each reviewed PR, in ascending order.
+/// PRs without a recorded first review are left out.
+pub fn first_review_hours(conn: &Connection, repo_name_full: &str) -> Result<Vec<f64>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT (julianday(first_review_at) - julianday(created_at)) * 24.0 AS hours
+        FROM github_pull_requests
+        WHERE repo_name = ?1 AND first_review_at IS NOT NULL AND created_at IS NOT NULL
+        ORDER BY hours
+        ",
+    )?;
+    let hours = stmt
+        .query_map(params![repo_name_full], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<f64>>>()?;
+    Ok(hours)
+}
+
+/// A duration of pull requests, in hours, whose distribution is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrLatency {
+    /// From opening to the first review; unreviewed PRs are left out
+    FirstReview,
+    /// From opening to closing or merging; open PRs are left out
+    OpenDuration,
+}
+
+impl PrLatency {
+    fn hours_sql(self) -> &'static str {
+        match self {
+            PrLatency::FirstReview => "(julianday(first_review_at) - julianday(created_at)) * 24.0",
+            PrLatency::OpenDuration => "(julianday(closed_at) - julianday(created_at)) * 24.0",
+        }
+    }
+}
+
+/// The given percentiles (0.0 to 1.0) of a PR latency, or `None` each when no
+/// PR has one. SQLite has no percentile_cont, so the value is interpolated
+/// between the two rows around position `p * (n - 1)` of the ordered hours.
+pub fn pr_latency_percentiles(
+    conn: &Connection,
+    repo_name_full: &str,
+    latency: PrLatency,
+    percentiles: &[f64],
+) -> Result<Vec<Option<f64>>> {
+    let mut stmt = conn.prepare(&format!(
+        r"
+        WITH ranked AS (
+            SELECT hours,
+                   ROW_NUMBER() OVER (ORDER BY hours) - 1 AS pos,
+                   COUNT(*) OVER () AS n
+            FROM (SELECT {} AS hours FROM github_pull_requests WHERE repo_name = ?1)
+            WHERE hours IS NOT NULL
+        )
+        SELECT MIN(hours) + (MAX(hours) - MIN(hours))
+                   * (?2 * (n - 1) - CAST(?2 * (n - 1) AS INTEGER))
+        FROM ranked
+        WHERE pos IN (CAST(?2 * (n - 1) AS INTEGER), CAST(?2 * (n - 1) AS INTEGER) + 1)
+        ",
+        latency.hours_sql()
+    ))?;
+    percentiles
+        .iter()
+        .map(|p| Ok(stmt.query_row(params![repo_name_full, p], |row| row.get(0))?))
+        .collect()
+}
+
+/// Distinct stored commits (local or API), and how many of them carry a
+/// trailer with each of `keys` (compared case-insensitively). Trailers are
+/// parsed from the full message even when `--commit-message` trims it.
+pub fn trailer_usage(
+    conn: &Connection,
+    repo_name_full: &str,
+    keys: &[&str],
+) -> Result<(i64, Vec<i64>)> {
+    let commits = conn.query_row(
+        r"
+        SELECT COUNT(*) FROM (
+            SELECT sha FROM git_commits WHERE repo_name = ?1
+            UNION
+            SELECT sha FROM github_commits WHERE repo_name = ?1
+        )
+        ",
+        params![repo_name_full],
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(
+        r"
+        SELECT COUNT(DISTINCT commit_sha)
+        FROM commit_trailers
+        WHERE repo_name = ?1 AND LOWER(key) = LOWER(?2)
+        ",
+    )?;
+    let mut with_key = Vec::new();
+    for key in keys {
+        with_key.push(stmt.query_row(params![repo_name_full, key], |row| row.get(0))?);
+    }
+    Ok((commits, with_key))
+}
+
+/// Subjects (first lines) of the stored non-merge commits of a repository,
+/// from the local message when there is one and the API message otherwise.
+/// Empty when messages aren't stored (`--commit-message none`).
+pub fn commit_subjects(conn: &Connection, repo_name_full: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT message FROM git_commits
+        WHERE repo_name = ?1 AND message IS NOT NULL AND NOT COALESCE(is_merge, 0)
+        UNION ALL
+        SELECT message FROM github_commits AS api
+        WHERE repo_name = ?1 AND message IS NOT NULL AND NOT COALESCE(is_merge, 0)
+          AND NOT EXISTS (
+              SELECT 1 FROM git_commits AS local
+              WHERE local.repo_name = api.repo_name AND local.sha = api.sha
+                AND local.message IS NOT NULL
+          )
+        ",
+    )?;
+    let subjects = stmt
+        .query_map(params![repo_name_full], |row| row.get::<_, String>(0))?
+        .map(|message| message.map(|m| m.lines().next().unwrap_or("").to_string()))
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(subjects)
+}
+
+/// Hours from opening to the first response by someone other than the author,
+/// in ascending order, for issues or (with `pull_requests`) PRs. Items nobody
+/// responded to are left out.
+pub fn first_response_hours(
+    conn: &Connection,
+    repo_name_full: &str,
+    pull_requests: bool,
+) -> Result<Vec<f64>> {
+    let table = if pull_requests {
+        "github_pull_requests"
+    } else {
+        "github_issues"
+    };
+    let mut stmt = conn.prepare(&format!(
+        r"
+        SELECT first_response_seconds / 3600.0 AS hours
+        FROM {}
+        WHERE repo_name = ?1 AND first_response_seconds IS NOT NULL
+        ORDER BY hours
+        ",
+        table
+    ))?;
+    let hours = stmt
+        .query_map(params![repo_name_full], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<f64>>>()?;
+    Ok(hours)
+}
+
+/// Distinct stored commits (local or API) on and off the default branch, as
+/// (on, off). Commits whose reachability is unknown are not counted.
+pub fn default_branch_counts(conn: &Connection, repo_name_full: &str) -> Result<(i64, i64)> {
+    let counts = conn.query_row(
+        r"
+        SELECT COALESCE(SUM(on_default), 0), COALESCE(SUM(1 - on_default), 0)
+        FROM (
+            SELECT sha, MAX(on_default_branch) AS on_default
+            FROM (
+                SELECT sha, on_default_branch FROM git_commits WHERE repo_name = ?1
+                UNION ALL
+                SELECT sha, on_default_branch FROM github_commits WHERE repo_name = ?1
+            )
+            WHERE on_default_branch IS NOT NULL
+            GROUP BY sha
+        )
+        ",
+        params![repo_name_full],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(counts)
+}
+
+/// Merged PRs per user who merged them, most first. Only PRs with a known
+/// merger count; the PR list endpoint doesn't return `merged_by`.
+pub fn merger_counts(conn: &Connection, repo_name_full: &str) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT merged_by_login, COUNT(*) AS merged
+        FROM github_pull_requests
+        WHERE repo_name = ?1 AND merged_at IS NOT NULL AND merged_by_login IS NOT NULL
+        GROUP BY merged_by_login
+        ORDER BY merged DESC, merged_by_login
+        ",
+    )?;
+    let rows = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// A merged pull request, as matched across repositories by the backport report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedPr {
+    pub number: i64,
+    pub title: String,
+    pub merged_at: DateTime<Utc>,
+    pub merge_commit_sha: Option<String>,
+}
+
+/// Merged PRs of a repository, oldest merge first.
+pub fn merged_prs(conn: &Connection, repo_name_full: &str) -> Result<Vec<MergedPr>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT number, COALESCE(title, ''), merged_at, merge_commit_sha
+        FROM github_pull_requests
+        WHERE repo_name = ?1 AND merged_at IS NOT NULL
+        ORDER BY merged_at, number
+        ",
+    )?;
+    let rows = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok(MergedPr {
+                number: row.get(0)?,
+                title: row.get(1)?,
+                merged_at: row.get(2)?,
+                merge_commit_sha: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// How often a repository's merged PRs were merged by their own author.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SelfMergeStats {
+    pub merged: i64,
+    /// Merged PRs whose merger and author are both known
+    pub with_merger: i64,
+    pub self_merged: i64,
+}
+
+impl SelfMergeStats {
+    /// Self-merged share of the PRs with a known merger, in percent.
+    pub fn share(&self) -> Option<f64> {
+        (self.with_merger > 0).then(|| self.self_merged as f64 * 100.0 / self.with_merger as f64)
+    }
+}
+
+/// Counts merged PRs merged by their author. PRs without a recorded
+/// `merged_by` (list fetches don't return it) or author are left out of the
+/// share.
+pub fn self_merge_stats(conn: &Connection, repo_name_full: &str) -> Result<SelfMergeStats> {
+    let stats = conn.query_row(
+        r"
+        SELECT
+            COUNT(*),
+            COUNT(CASE WHEN merged_by_login IS NOT NULL AND user_login IS NOT NULL THEN 1 END),
+            COUNT(CASE WHEN merged_by_login = user_login THEN 1 END)
+        FROM github_pull_requests
+        WHERE repo_name = ?1 AND merged_at IS NOT NULL
+        ",
+        params![repo_name_full],
+        |row| {
+            Ok(SelfMergeStats {
+                merged: row.get(0)?,
+                with_merger: row.get(1)?,
+                self_merged: row.get(2)?,
+            })
+        },
+    )?;
+    Ok(stats)
+}
+
+/// Stored events of a repository per event type, counting only those created
+/// at or after `since` (RFC 3339) when given.
+pub fn event_type_counts(
+    conn: &Connection,
+    repo_name_full: &str,
+    since: Option<&str>,
+) -> Result<BTreeMap<String, i64>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT event_type, COUNT(*)
+        FROM repo_events
+        WHERE repo_name = ?1 AND (?2 IS NULL OR julianday(created_at) >= julianday(?2))
+        GROUP BY event_type
+        ",
+    )?;
+    let counts = stmt
+        .query_map(params![repo_name_full, since], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<BTreeMap<String, i64>>>()?;
+    Ok(counts)
+}
+
+/// Time of the oldest stored event of a repository (RFC 3339), or `None` when
+/// it has none.
+pub fn oldest_event_at(conn: &Connection, repo_name_full: &str) -> Result<Option<String>> {
+    let oldest = conn.query_row(
+        "SELECT MIN(created_at) FROM repo_events WHERE repo_name = ?1",
+        params![repo_name_full],
+        |row| row.get(0),
+    )?;
+    Ok(oldest)
+}
+
+/// Closed issues of a repository by the reason they were closed with. Pull
+/// requests the issues endpoint returned alongside them are left out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClosureReasons {
+    pub closed: i64,
+    pub completed: i64,
+    pub not_planned: i64,
+}
+
+impl ClosureReasons {
+    /// Share of closed issues with a known reason that were closed as not
+    /// planned, or `None` when no reasons were recorded.
+    pub fn not_planned_share(&self) -> Option<f64> {
+        let known = self.completed + self.not_planned;
+        (known > 0).then(|| self.not_planned as f64 / known as f64)
+    }
+}
+
+pub fn closure_reasons(conn: &Connection, repo_name_full: &str) -> Result<ClosureReasons> {
+    let reasons = conn.query_row(
+        r"
+        SELECT COUNT(*),
+               COALESCE(SUM(state_reason = 'completed'), 0),
+               COALESCE(SUM(state_reason = 'not_planned'), 0)
+        FROM github_issues
+        WHERE repo_name = ?1 AND state = 'closed' AND is_pull_request IS NOT 1
+        ",
+        params![repo_name_full],
+        |row| {
+            Ok(ClosureReasons {
+                closed: row.get(0)?,
+                completed: row.get(1)?,
+                not_planned: row.get(2)?,
+            })
+        },
+    )?;
+    Ok(reasons)
+}
+
+/// Number of stored collaborators of a repository by the access they have.
+/// Higher permissions include the lower ones, so admins also count as pushers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CollaboratorAccess {
+    pub collaborators: i64,
+    pub admin: i64,
+    pub maintain: i64,
+    pub push: i64,
+}
+
+pub fn collaborator_access(conn: &Connection, repo_name_full: &str) -> Result<CollaboratorAccess> {
+    let access = conn.query_row(
+        r"
+        SELECT COUNT(*), COALESCE(SUM(admin), 0), COALESCE(SUM(maintain), 0), COALESCE(SUM(push), 0)
+        FROM collaborators
+        WHERE repo_name = ?1
+        ",
+        params![repo_name_full],
+        |row| {
+            Ok(CollaboratorAccess {
+                collaborators: row.get(0)?,
+                admin: row.get(1)?,
+                maintain: row.get(2)?,
+                push: row.get(3)?,
+            })
+        },
+    )?;
+    Ok(access)
+}
+
+/// Items of one kind split by whether a maintainer authored them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaintainerSplit {
+    pub maintainer: i64,
+    pub community: i64,
+    /// Items with an unknown author, or stored without a maintainer list
+    pub untagged: i64,
+}
+
+impl MaintainerSplit {
+    /// Maintainer share of the tagged items, in percent.
+    pub fn share(&self) -> Option<f64> {
+        let tagged = self.maintainer + self.community;
+        (tagged > 0).then(|| self.maintainer as f64 * 100.0 / tagged as f64)
+    }
+}
+
+/// Commits, PRs and issues of a repository split by `is_maintainer`, as
+/// (kind, split).
+pub fn maintainer_splits(
+    conn: &Connection,
+    repo_name_full: &str,
+) -> Result<Vec<(&'static str, MaintainerSplit)>> {
+    let mut splits = Vec::new();
+    for (kind, table) in [
+        ("commits", "github_commits"),
+        ("pull requests", "github_pull_requests"),
+        ("issues", "github_issues"),
+    ] {
+        let split = conn.query_row(
+            &format!(
+                r"
+                SELECT
+                    COUNT(CASE WHEN is_maintainer = 1 THEN 1 END),
+                    COUNT(CASE WHEN is_maintainer = 0 THEN 1 END),
+                    COUNT(CASE WHEN is_maintainer IS NULL THEN 1 END)
+                FROM {}
+                WHERE repo_name = ?1
+                ",
+                table
+            ),
+            params![repo_name_full],
+            |row| {
+                Ok(MaintainerSplit {
+                    maintainer: row.get(0)?,
+                    community: row.get(1)?,
+                    untagged: row.get(2)?,
+                })
+            },
+        )?;
+        splits.push((kind, split));
+    }
+    Ok(splits)
+}
+
+/// Number of commits in the changelog leading up to each release tag.
+pub fn changelog_sizes(conn: &Connection, repo_name_full: &str) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT to_tag, COUNT(*)
+        FROM tag_changelog
+        WHERE repo_name = ?1
+        GROUP BY to_tag
+        ORDER BY to_tag
+        ",
+    )?;
+    let rows = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Each language's share of a repository's code, in percent, as of one fetch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LanguageSnapshot {
+    pub fetched_at: String,
+    pub shares: BTreeMap<String, f64>,
+}
+
+/// Language snapshots of a repository, oldest first, optionally only those
+/// fetched at or after `since`.
+pub fn language_snapshots(
+    conn: &Connection,
+    repo_name_full: &str,
+    since: Option<&DateTime<Utc>>,
+) -> Result<Vec<LanguageSnapshot>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT fetched_at, language, bytes
+        FROM repo_languages
+        WHERE repo_name = ?1 AND (?2 IS NULL OR fetched_at >= ?2)
+        ORDER BY fetched_at, language
+        ",
+    )?;
+    let rows = stmt
+        .query_map(
+            params![repo_name_full, since.map(|dt| dt.to_rfc3339())],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut snapshots: Vec<(String, Vec<(String, i64)>)> = Vec::new();
+    for (fetched_at, language, bytes) in rows {
+        match snapshots.last_mut() {
+            Some((last, languages)) if *last == fetched_at => languages.push((language, bytes)),
+            _ => snapshots.push((fetched_at, vec![(language, bytes)])),
+        }
+    }
+    Ok(snapshots
+        .into_iter()
+        .map(|(fetched_at, languages)| {
+            let total: i64 = languages.iter().map(|(_, bytes)| bytes).sum();
+            let shares = languages
+                .into_iter()
+                .map(|(language, bytes)| {
+                    let share = if total > 0 {
+                        bytes as f64 * 100.0 / total as f64
+                    } else {
+                        0.0
+                    };
+                    (language, share)
+                })
+                .collect();
+            LanguageSnapshot { fetched_at, shares }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_tables;
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn.execute_batch(
+            r"
+            INSERT INTO github_commits (sha, repo_name, author_login) VALUES
+                ('a1', 'bitcoin/bitcoin', 'alice'),
+                ('a2', 'bitcoin/bitcoin', 'alice'),
+                ('a3', 'bitcoin/bitcoin', 'bob'),
+                ('a4', 'bitcoin/bitcoin', NULL),
+                ('b1', 'bitcoinknots/bitcoin', 'carol');
+            INSERT INTO github_pull_requests (id, number, repo_name, merged_at) VALUES
+                (1, 1, 'bitcoin/bitcoin', '2024-01-01T00:00:00+00:00'),
+                (2, 2, 'bitcoin/bitcoin', NULL),
+                (3, 1, 'bitcoinknots/bitcoin', '2024-02-01T00:00:00+00:00');
+            ",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn counts_commits_per_repo() {
+        let conn = test_conn();
+        assert_eq!(commit_count(&conn, "bitcoin/bitcoin").unwrap(), 4);
+        assert_eq!(commit_count(&conn, "bitcoinknots/bitcoin").unwrap(), 1);
+        assert_eq!(commit_count(&conn, "unknown/repo").unwrap(), 0);
+    }
+
+    #[test]
+    fn non_merge_count_skips_flagged_merges() {
+        let conn = test_conn();
+        conn.execute(
+            "UPDATE github_commits SET is_merge = 1 WHERE sha = 'a2'",
+            [],
+        )
+        .unwrap();
+        assert_eq!(non_merge_commit_count(&conn, "bitcoin/bitcoin").unwrap(), 3);
+    }
+
+    #[test]
+    fn counts_only_merged_prs() {
+        let conn = test_conn();
+        assert_eq!(merged_pr_count(&conn, "bitcoin/bitcoin").unwrap(), 1);
+        assert_eq!(merged_pr_count(&conn, "bitcoinknots/bitcoin").unwrap(), 1);
+    }
+
+    #[test]
+    fn subsystem_authors_include_coauthors() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO git_commits (sha, repo_name, author_email) VALUES
+                ('c1', 'bitcoin/bitcoin', 'alice@example.com'),
+                ('c2', 'bitcoin/bitcoin', 'ALICE@example.com'),
+                ('c3', 'bitcoin/bitcoin', 'bob@example.com');
+            INSERT INTO commit_subsystems (repo_name, sha, subsystem) VALUES
+                ('bitcoin/bitcoin', 'c1', 'wallet'),
+                ('bitcoin/bitcoin', 'c2', 'wallet'),
+                ('bitcoin/bitcoin', 'c2', 'gui'),
+                ('bitcoin/bitcoin', 'c3', 'consensus');
+            INSERT INTO commit_coauthors (repo_name, commit_sha, name, email) VALUES
+                ('bitcoin/bitcoin', 'c3', 'Carol', 'carol@example.com');
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            subsystem_author_counts(&conn, "bitcoin/bitcoin").unwrap(),
+            [
+                ("consensus".to_string(), 2),
+                ("gui".to_string(), 1),
+                ("wallet".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn sums_churn_per_author_email() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO git_commits
+                (sha, repo_name, author_email, insertions, deletions, is_merge) VALUES
+                ('c1', 'bitcoin/bitcoin', 'alice@example.com', 10, 2, 0),
+                ('c2', 'bitcoin/bitcoin', 'Alice@example.com', 5, 1, 0),
+                ('c3', 'bitcoin/bitcoin', 'bob@example.com', 100, 50, 1),
+                ('c4', 'bitcoin/bitcoin', 'carol@example.com', NULL, NULL, 0);
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            author_churn(&conn, "bitcoin/bitcoin", false).unwrap(),
+            [
+                ("bob@example.com".to_string(), 100, 50),
+                ("alice@example.com".to_string(), 15, 3),
+            ]
+        );
+        assert_eq!(
+            author_churn(&conn, "bitcoin/bitcoin", true).unwrap(),
+            [("alice@example.com".to_string(), 15, 3)]
+        );
+    }
+
+    #[test]
+    fn counts_new_and_retained_contributors() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO git_commits (sha, repo_name, author_email, commit_timestamp) VALUES
+                ('c1', 'bitcoin/bitcoin', 'old@example.com', '2023-06-01T00:00:00+00:00'),
+                ('c2', 'bitcoin/bitcoin', 'OLD@example.com', '2024-11-01T00:00:00+00:00'),
+                ('c3', 'bitcoin/bitcoin', 'new@example.com', '2024-02-01T00:00:00+00:00'),
+                ('c4', 'bitcoin/bitcoin', 'new@example.com', '2024-10-01T00:00:00+00:00'),
+                ('c5', 'bitcoin/bitcoin', 'once@example.com', '2024-03-01T00:00:00+00:00');
+            ",
+        )
+        .unwrap();
+        let window = Window {
+            start: "2024-01-01T00:00:00Z".parse().unwrap(),
+            end: "2025-01-01T00:00:00Z".parse().unwrap(),
+        };
+        assert_eq!(
+            new_contributors(&conn, "bitcoin/bitcoin", &window).unwrap(),
+            2
+        );
+        assert_eq!(
+            retained_contributors(&conn, "bitcoin/bitcoin", &window).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn unique_authors_ignores_missing_logins() {
+        let conn = test_conn();
+        assert_eq!(unique_authors(&conn, "bitcoin/bitcoin").unwrap(), 2);
+        assert_eq!(unique_authors(&conn, "bitcoinknots/bitcoin").unwrap(), 1);
+    }
+
+    #[test]
+    fn counts_merged_prs_per_merger() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            UPDATE github_pull_requests SET merged_by_login = 'maintainer' WHERE id = 1;
+            INSERT INTO github_pull_requests (id, number, repo_name, merged_at, merged_by_login)
+            VALUES
+                (4, 3, 'bitcoin/bitcoin', '2024-03-01T00:00:00+00:00', 'maintainer'),
+                (5, 4, 'bitcoin/bitcoin', '2024-03-02T00:00:00+00:00', 'other'),
+                (6, 5, 'bitcoin/bitcoin', NULL, 'nobody');
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            merger_counts(&conn, "bitcoin/bitcoin").unwrap(),
+            [("maintainer".to_string(), 2), ("other".to_string(), 1)]
+        );
+        assert!(merger_counts(&conn, "bitcoinknots/bitcoin")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn self_merges_only_count_prs_with_a_known_merger() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO github_pull_requests
+                (id, number, repo_name, user_login, merged_at, merged_by_login)
+            VALUES
+                (11, 11, 'x/y', 'alice', '2024-03-01T00:00:00+00:00', 'alice'),
+                (12, 12, 'x/y', 'bob', '2024-03-02T00:00:00+00:00', 'alice'),
+                (13, 13, 'x/y', 'carol', '2024-03-03T00:00:00+00:00', NULL),
+                (14, 14, 'x/y', 'alice', NULL, 'alice');
+            ",
+        )
+        .unwrap();
+        let stats = self_merge_stats(&conn, "x/y").unwrap();
+        assert_eq!(
+            stats,
+            SelfMergeStats {
+                merged: 3,
+                with_merger: 2,
+                self_merged: 1
+            }
+        );
+        assert_eq!(stats.share(), Some(50.0));
+        assert_eq!(self_merge_stats(&conn, "a/b").unwrap().share(), None);
+    }
+
+    #[test]
+    fn first_response_is_the_earlier_of_comment_and_review() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO github_pull_requests
+                (id, number, repo_name, created_at, first_comment_at, first_review_at)
+            VALUES
+                (31, 31, 'x/y', '2024-03-01T00:00:00+00:00',
+                    '2024-03-01T06:00:00+00:00', '2024-03-01T03:00:00+00:00'),
+                (32, 32, 'x/y', '2024-03-01T00:00:00+00:00', '2024-03-02T00:00:00+00:00', NULL),
+                (33, 33, 'x/y', '2024-03-01T00:00:00+00:00', NULL, NULL);
+            INSERT INTO github_issues (id, number, repo_name, created_at, first_comment_at) VALUES
+                (34, 34, 'x/y', '2024-03-01T00:00:00+00:00', '2024-03-01T01:30:00+00:00');
+            ",
+        )
+        .unwrap();
+        crate::db::derive_first_response(&conn, "x/y").unwrap();
+        assert_eq!(
+            first_response_hours(&conn, "x/y", true).unwrap(),
+            vec![3.0, 24.0]
+        );
+        assert_eq!(
+            first_response_hours(&conn, "x/y", false).unwrap(),
+            vec![1.5]
+        );
+    }
+
+    #[test]
+    fn latency_percentiles_interpolate_between_ranks() {
+        let conn = test_conn();
+        for hours in 1..=10 {
+            conn.execute(
+                "INSERT INTO github_pull_requests (id, number, repo_name, created_at, first_review_at)
+                 VALUES (?1, ?1, 'x/y', '2024-01-01T00:00:00+00:00', ?2)",
+                params![20 + hours, format!("2024-01-01T{:02}:00:00+00:00", hours)],
+            )
+            .unwrap();
+        }
+        let found =
+            pr_latency_percentiles(&conn, "x/y", PrLatency::FirstReview, &[0.5, 0.9, 0.99, 1.0])
+                .unwrap();
+        let expected = [5.5, 9.1, 9.91, 10.0];
+        for (found, expected) in found.iter().zip(expected) {
+            assert!((found.unwrap() - expected).abs() < 1e-6, "{:?}", found);
+        }
+        assert_eq!(
+            pr_latency_percentiles(&conn, "x/y", PrLatency::OpenDuration, &[0.5]).unwrap(),
+            [None]
+        );
+    }
+
+    #[test]
+    fn commit_subjects_prefer_local_messages_and_skip_merges() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO git_commits (sha, repo_name, message, is_merge) VALUES
+                ('m1', 'm/n', 'fix: local subject' || char(10) || char(10) || 'body', 0),
+                ('m2', 'm/n', 'Merge #1: thing', 1);
+            INSERT INTO github_commits (sha, repo_name, message, is_merge) VALUES
+                ('m1', 'm/n', 'api subject', 0),
+                ('m3', 'm/n', 'feat(ui): api only', NULL),
+                ('m4', 'm/n', NULL, 0);
+            ",
+        )
+        .unwrap();
+        let mut subjects = commit_subjects(&conn, "m/n").unwrap();
+        subjects.sort();
+        assert_eq!(subjects, ["feat(ui): api only", "fix: local subject"]);
+    }
+
+    #[test]
+    fn closure_reasons_count_closed_issues_but_not_pull_requests() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO github_issues
+                (id, number, repo_name, state, state_reason, is_pull_request)
+            VALUES
+                (21, 21, 'x/y', 'closed', 'completed', 0),
+                (22, 22, 'x/y', 'closed', 'not_planned', 0),
+                (23, 23, 'x/y', 'closed', NULL, NULL),
+                (24, 24, 'x/y', 'open', 'reopened', 0),
+                (25, 25, 'a/b', 'closed', 'not_planned', 0),
+                (26, 26, 'x/y', 'closed', 'completed', 1),
+                (27, 27, 'x/y', 'closed', NULL, 1);
+            ",
+        )
+        .unwrap();
+        let reasons = closure_reasons(&conn, "x/y").unwrap();
+        assert_eq!(
+            reasons,
+            ClosureReasons {
+                closed: 3,
+                completed: 1,
+                not_planned: 1
+            }
+        );
+        assert_eq!(reasons.not_planned_share(), Some(0.5));
+        assert_eq!(
+            closure_reasons(&conn, "c/d").unwrap().not_planned_share(),
+            None
+        );
+    }
+
+    #[test]
+    fn collaborators_are_replaced_and_counted_by_access() {
+        let conn = test_conn();
+        let collaborators: Vec<crate::github::GitHubCollaborator> =
+            serde_json::from_value(serde_json::json!([
+                { "login": "alice", "id": 1, "role_name": "admin",
+                  "permissions": { "admin": true, "maintain": true, "push": true, "triage": true, "pull": true } },
+                { "login": "bob", "id": 2, "role_name": "write",
+                  "permissions": { "admin": false, "push": true, "pull": true } },
+                { "login": "carol", "id": 3, "role_name": "read",
+                  "permissions": { "admin": false, "push": false, "pull": true } }
+            ]))
+            .unwrap();
+        let now = chrono::Utc::now();
+        crate::db::replace_collaborators(&conn, &collaborators, "x/y", &now).unwrap();
+        crate::db::replace_collaborators(&conn, &collaborators[..2], "x/y", &now).unwrap();
+        assert_eq!(
+            collaborator_access(&conn, "x/y").unwrap(),
+            CollaboratorAccess {
+                collaborators: 2,
+                admin: 1,
+                maintain: 1,
+                push: 2
+            }
+        );
+    }
+
+    #[test]
+    fn maintainer_tags_split_activity_by_author() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO github_pull_requests (id, number, repo_name, user_login) VALUES
+                (21, 21, 'x/y', 'Alice'),
+                (22, 22, 'x/y', 'bob'),
+                (23, 23, 'x/y', NULL),
+                (24, 24, 'a/b', 'alice');
+            ",
+        )
+        .unwrap();
+        let maintainers = ["alice".to_string()].into_iter().collect();
+        crate::db::tag_maintainers(&conn, "x/y", &maintainers).unwrap();
+
+        let splits = maintainer_splits(&conn, "x/y").unwrap();
+        let prs = MaintainerSplit {
+            maintainer: 1,
+            community: 1,
+            untagged: 1,
+        };
+        assert_eq!(splits[1], ("pull requests", prs));
+        assert_eq!(prs.share(), Some(50.0));
+        assert_eq!(splits[0].1.share(), None);
+        // Other repositories keep their tags
+        assert_eq!(maintainer_splits(&conn, "a/b").unwrap()[1].1.untagged, 1);
+    }
+
+    #[test]
+    fn counts_changelog_commits_per_release() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO tag_changelog (repo_name, from_tag, to_tag, commit_sha) VALUES
+                ('bitcoin/bitcoin', 'v26.0', 'v27.0', 'a1'),
+                ('bitcoin/bitcoin', 'v26.0', 'v27.0', 'a2'),
+                ('bitcoin/bitcoin', 'v27.0', 'v27.1', 'a3'),
+                ('bitcoinknots/bitcoin', 'v26.1.knots20240325', 'v27.1.knots20240801', 'b1');
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            changelog_sizes(&conn, "bitcoin/bitcoin").unwrap(),
+            [("v27.0".to_string(), 2), ("v27.1".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn language_shares_per_snapshot() {
+        let conn = test_conn();
+        conn.execute_batch(
+            r"
+            INSERT INTO repo_languages (repo_name, fetched_at, language, bytes) VALUES
+                ('bitcoin/bitcoin', '2024-01-01T00:00:00+00:00', 'C++', 750),
+                ('bitcoin/bitcoin', '2024-01-01T00:00:00+00:00', 'Python', 250),
+                ('bitcoin/bitcoin', '2024-06-01T00:00:00+00:00', 'C++', 600),
+                ('bitcoin/bitcoin', '2024-06-01T00:00:00+00:00', 'Python', 400),
+                ('bitcoinknots/bitcoin', '2024-06-01T00:00:00+00:00', 'C++', 1);
+            ",
+        )
+        .unwrap();
+        let snapshots = language_snapshots(&conn, "bitcoin/bitcoin", None).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].shares["C++"], 75.0);
+        assert_eq!(snapshots[1].shares["Python"], 40.0);
+
+        let since = "2024-03-01T00:00:00Z".parse().unwrap();
+        let recent = language_snapshots(&conn, "bitcoin/bitcoin", Some(&since)).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].fetched_at, "2024-06-01T00:00:00+00:00");
+    }
+}
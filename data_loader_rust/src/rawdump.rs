@@ -0,0 +1,179 @@
+use crate::errors::{DataError, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Longest file stem derived from a URL before it is shortened with a hash.
+const MAX_STEM_LEN: usize = 150;
+
+/// Where raw API response bodies are written to or read back from.
+#[derive(Debug, Clone)]
+pub enum RawResponses {
+    /// Save every response body before it is parsed.
+    Dump(PathBuf),
+    /// Serve responses from a directory written by `Dump` instead of the network.
+    Replay(PathBuf),
+}
+
+/// A response body and the next-page URL from its Link header.
+pub struct RawPage {
+    pub body: String,
+    pub next: Option<String>,
+    /// File the body was saved to or replayed from.
+    pub path: Option<PathBuf>,
+}
+
+impl RawPage {
+    /// Deserializes the body, naming the saved file when that fails so the
+    /// offending response can be inspected.
+    pub fn parse<T: for<'de> serde::Deserialize<'de>>(&self) -> Result<T> {
+        serde_json::from_str(&self.body).map_err(|e| {
+            if let Some(path) = &self.path {
+                log::error!("Could not parse the response saved at {:?}: {}", path, e);
+            }
+            e.into()
+        })
+    }
+
+    /// Deserializes a JSON array body, returning the items and how many
+    /// records were dropped. When `lenient`, elements are parsed one by one and
+    /// those that don't fit `T` are logged and dropped, so a single malformed
+    /// record doesn't fail the whole page.
+    pub fn parse_items<T: for<'de> serde::Deserialize<'de>>(
+        &self,
+        lenient: bool,
+    ) -> Result<(Vec<T>, usize)> {
+        if !lenient {
+            return Ok((self.parse()?, 0));
+        }
+        let values = self.parse::<Vec<serde_json::Value>>()?;
+        let total = values.len();
+        let items: Vec<T> = values
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, value)| match serde_json::from_value(value) {
+                Ok(item) => Some(item),
+                Err(e) => {
+                    log::warn!("Skipping malformed record {} of {}: {}", index, total, e);
+                    None
+                }
+            })
+            .collect();
+        if items.len() < total {
+            match &self.path {
+                Some(path) => log::warn!(
+                    "Kept {} of {} records from the response saved at {:?}",
+                    items.len(),
+                    total,
+                    path
+                ),
+                None => log::warn!("Kept {} of {} records from a response", items.len(), total),
+            }
+        }
+        let skipped = total - items.len();
+        Ok((items, skipped))
+    }
+}
+
+// File stem for a request key (URL, plus the body for POSTs): the key with
+// anything but ASCII alphanumerics replaced, so the endpoint and page number
+// stay readable. Overly long keys are cut and suffixed with a hash.
+fn file_stem(key: &str) -> String {
+    let key = key
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let stem: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if stem.len() <= MAX_STEM_LEN {
+        return stem;
+    }
+    let digest = hex::encode(Sha256::digest(key.as_bytes()));
+    format!("{}_{}", &stem[..MAX_STEM_LEN], &digest[..16])
+}
+
+/// Writes a response body to `<dir>/<stem>.json`, and its next-page link to
+/// `<dir>/<stem>.next` when there is one. Returns the body's path.
+pub fn save(dir: &Path, key: &str, body: &str, next: Option<&str>) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let stem = file_stem(key);
+    let path = dir.join(format!("{}.json", stem));
+    fs::write(&path, body)?;
+    let next_path = dir.join(format!("{}.next", stem));
+    match next {
+        Some(next) => fs::write(next_path, next)?,
+        None if next_path.exists() => fs::remove_file(next_path)?,
+        None => {}
+    }
+    log::debug!("Saved raw response for {} to {:?}", key, path);
+    Ok(path)
+}
+
+/// Reads back a response saved by [`save`].
+pub fn load(dir: &Path, key: &str) -> Result<RawPage> {
+    let stem = file_stem(key);
+    let path = dir.join(format!("{}.json", stem));
+    let body = fs::read_to_string(&path).map_err(|e| {
+        DataError::Other(format!(
+            "No recorded response for {} at {:?}: {}",
+            key, path, e
+        ))
+    })?;
+    let next = fs::read_to_string(dir.join(format!("{}.next", stem)))
+        .ok()
+        .map(|next| next.trim().to_string());
+    Ok(RawPage {
+        body,
+        next,
+        path: Some(path),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn saved_pages_replay_with_their_next_link() {
+        let dir = TempDir::new().unwrap();
+        let first = "https://api.github.com/repos/a/b/pulls?state=all&per_page=100";
+        let second = "https://api.github.com/repos/a/b/pulls?state=all&per_page=100&page=2";
+        save(dir.path(), first, "[1]", Some(second)).unwrap();
+        save(dir.path(), second, "[2]", None).unwrap();
+
+        let page = load(dir.path(), first).unwrap();
+        assert_eq!(page.parse::<Vec<i32>>().unwrap(), [1]);
+        assert_eq!(page.next.as_deref(), Some(second));
+        let page = load(dir.path(), second).unwrap();
+        assert_eq!(page.next, None);
+        assert!(load(dir.path(), "https://api.github.com/other").is_err());
+    }
+
+    #[test]
+    fn lenient_parsing_drops_only_malformed_records() {
+        let page = RawPage {
+            body: r#"[{"id": 1}, {"id": null}, {"id": 3}]"#.to_string(),
+            next: None,
+            path: None,
+        };
+        #[derive(serde::Deserialize)]
+        struct Item {
+            id: i64,
+        }
+        assert!(page.parse_items::<Item>(false).is_err());
+        let (items, skipped) = page.parse_items::<Item>(true).unwrap();
+        let ids: Vec<i64> = items.into_iter().map(|item| item.id).collect();
+        assert_eq!(ids, [1, 3]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn long_keys_are_shortened_uniquely() {
+        let a = format!("https://api.github.com/{}", "x".repeat(300));
+        let b = format!("{}y", a);
+        assert!(file_stem(&a).len() < 200);
+        assert_ne!(file_stem(&a), file_stem(&b));
+    }
+}
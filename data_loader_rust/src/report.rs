@@ -0,0 +1,1414 @@
+use crate::config::Bucket;
+use crate::errors::Result;
+use crate::metrics::Metric;
+use crate::queries::{self, MergedPr};
+use crate::table::{Table, TableStyle};
+use chrono::Duration;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Activity counts for a single time bucket of one repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActivityBucket {
+    pub period: String,
+    pub commits: i64,
+    pub pull_requests: i64,
+    pub issues: i64,
+}
+
+impl Bucket {
+    // SQL deriving the bucket key from an ISO 8601 timestamp column. Weeks are
+    // keyed by the date of their Monday, so a week spanning New Year stays one
+    // bucket and sorts correctly.
+    fn period_sql(self, column: &str) -> String {
+        match self {
+            Bucket::Day => format!("strftime('%Y-%m-%d', {})", column),
+            Bucket::Week => format!("date({}, 'weekday 0', '-6 days')", column),
+            Bucket::Month => format!("strftime('%Y-%m', {})", column),
+        }
+    }
+}
+
+/// Groups a repository's commits, opened PRs and opened issues into time buckets.
+/// Rows without a parseable timestamp are left out, as are merge commits when
+/// `exclude_merges` is set. Pull requests the issues endpoint returned are only
+/// counted as PRs.
+pub fn activity_by_bucket(
+    conn: &Connection,
+    repo_name_full: &str,
+    bucket: Bucket,
+    exclude_merges: bool,
+) -> Result<Vec<ActivityBucket>> {
+    let mut stmt = conn.prepare(&format!(
+        r"
+        SELECT period, SUM(commits), SUM(pull_requests), SUM(issues)
+        FROM (
+            SELECT {} AS period, 1 AS commits, 0 AS pull_requests, 0 AS issues
+            FROM github_commits WHERE repo_name = ?1 AND NOT (?2 AND COALESCE(is_merge, 0))
+            UNION ALL
+            SELECT {}, 0, 1, 0
+            FROM github_pull_requests WHERE repo_name = ?1
+            UNION ALL
+            SELECT {}, 0, 0, 1
+            FROM github_issues WHERE repo_name = ?1 AND is_pull_request IS NOT 1
+        )
+        WHERE period IS NOT NULL
+        GROUP BY period
+        ORDER BY period
+        ",
+        bucket.period_sql("commit_timestamp"),
+        bucket.period_sql("created_at"),
+        bucket.period_sql("created_at"),
+    ))?;
+    let rows = stmt.query_map(params![repo_name_full, exclude_merges], |row| {
+        Ok(ActivityBucket {
+            period: row.get(0)?,
+            commits: row.get(1)?,
+            pull_requests: row.get(2)?,
+            issues: row.get(3)?,
+        })
+    })?;
+
+    let mut buckets = Vec::new();
+    for row in rows {
+        buckets.push(row?);
+    }
+    Ok(buckets)
+}
+
+/// The given metrics for two repositories side by side.
+pub fn totals_table(
+    conn: &Connection,
+    metrics: &[&dyn Metric],
+    repo_a: &str,
+    repo_b: &str,
+) -> Result<Table> {
+    let mut table = Table::new(
+        format!("Totals: A = {}, B = {}", repo_a, repo_b),
+        &["metric", "A", "B"],
+    );
+    for metric in metrics {
+        table.row(
+            metric.name(),
+            &[
+                &metric.compute(conn, repo_a)?,
+                &metric.compute(conn, repo_b)?,
+            ],
+        );
+    }
+    Ok(table)
+}
+
+/// Prints the given metrics for two repositories side by side.
+pub fn print_totals_comparison(
+    conn: &Connection,
+    metrics: &[&dyn Metric],
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    totals_table(conn, metrics, repo_a, repo_b)?.print(style);
+    Ok(())
+}
+
+/// Counts open pull requests per `mergeable_state` (`clean`, `dirty`,
+/// `blocked`, `behind`, ...). The list endpoint does not return mergeability,
+/// so PRs loaded only from it are counted as `unknown`.
+pub fn mergeable_state_breakdown(
+    conn: &Connection,
+    repo_name_full: &str,
+) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT COALESCE(mergeable_state, 'unknown') AS merge_state, COUNT(*)
+        FROM github_pull_requests
+        WHERE repo_name = ?1 AND state = 'open'
+        GROUP BY merge_state
+        ORDER BY merge_state
+        ",
+    )?;
+    let rows = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Prints the open-PR mergeable state breakdown of two repositories side by side.
+pub fn print_mergeable_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let mut states: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    for (state, count) in mergeable_state_breakdown(conn, repo_a)? {
+        states.entry(state).or_default().0 = count;
+    }
+    for (state, count) in mergeable_state_breakdown(conn, repo_b)? {
+        states.entry(state).or_default().1 = count;
+    }
+
+    let mut table = Table::new(
+        format!(
+            "Open PRs by mergeable state: A = {}, B = {}",
+            repo_a, repo_b
+        ),
+        &["state", "A", "B"],
+    );
+    for (state, (a, b)) in states {
+        table.row(state, &[&a, &b]);
+    }
+    table.print(style);
+    Ok(())
+}
+
+/// Counts merged pull requests per integration type (`merge`, `fast-forward`,
+/// `rebase`, `squash`). PRs loaded without `--integration-type` count as
+/// `unknown`.
+pub fn integration_type_breakdown(
+    conn: &Connection,
+    repo_name_full: &str,
+) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT COALESCE(integration_type, 'unknown') AS integration, COUNT(*)
+        FROM github_pull_requests
+        WHERE repo_name = ?1 AND merged_at IS NOT NULL
+        GROUP BY integration
+        ORDER BY integration
+        ",
+    )?;
+    let rows = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Prints how the merged PRs of two repositories were integrated, side by side.
+pub fn print_integration_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let mut types: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    for (integration, count) in integration_type_breakdown(conn, repo_a)? {
+        types.entry(integration).or_default().0 = count;
+    }
+    for (integration, count) in integration_type_breakdown(conn, repo_b)? {
+        types.entry(integration).or_default().1 = count;
+    }
+
+    let mut table = Table::new(
+        format!(
+            "Merged PRs by integration type: A = {}, B = {}",
+            repo_a, repo_b
+        ),
+        &["integration", "A", "B"],
+    );
+    for (integration, (a, b)) in types {
+        table.row(integration, &[&a, &b]);
+    }
+    table.print(style);
+    Ok(())
+}
+
+/// Labels of two repositories split into those used by only one of them and
+/// those used by both, each with the number of issues/PRs carrying it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelComparison {
+    pub only_a: Vec<(String, i64)>,
+    pub only_b: Vec<(String, i64)>,
+    pub shared: Vec<(String, i64, i64)>,
+}
+
+// Number of issues/PRs per label.
+fn label_usage(conn: &Connection, repo_name_full: &str) -> Result<BTreeMap<String, i64>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT label, COUNT(*) FROM issue_labels
+        WHERE repo_name = ?1
+        GROUP BY label
+        ",
+    )?;
+    let usage = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<BTreeMap<_, _>>>()?;
+    Ok(usage)
+}
+
+/// Compares the label taxonomies of two repositories. Labels are matched by exact name.
+pub fn label_comparison(conn: &Connection, repo_a: &str, repo_b: &str) -> Result<LabelComparison> {
+    let usage_a = label_usage(conn, repo_a)?;
+    let mut usage_b = label_usage(conn, repo_b)?;
+    let mut comparison = LabelComparison::default();
+    for (label, count_a) in usage_a {
+        match usage_b.remove(&label) {
+            Some(count_b) => comparison.shared.push((label, count_a, count_b)),
+            None => comparison.only_a.push((label, count_a)),
+        }
+    }
+    comparison.only_b = usage_b.into_iter().collect();
+    Ok(comparison)
+}
+
+/// Prints the label taxonomy comparison of two repositories.
+pub fn print_label_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let comparison = label_comparison(conn, repo_a, repo_b)?;
+
+    let mut table = Table::new(
+        format!("Labels: A = {}, B = {}", repo_a, repo_b),
+        &["label", "A", "B"],
+    );
+    for (label, a, b) in &comparison.shared {
+        table.row(label, &[a, b]);
+    }
+    for (label, a) in &comparison.only_a {
+        table.row(label, &[a, &"only A"]);
+    }
+    for (label, b) in &comparison.only_b {
+        table.row(label, &[&"only B", b]);
+    }
+    table.print(style);
+    println!(
+        "{} shared, {} only in A, {} only in B",
+        comparison.shared.len(),
+        comparison.only_a.len(),
+        comparison.only_b.len()
+    );
+    println!();
+    Ok(())
+}
+
+// Label used for issues and PRs that aren't in any milestone.
+const NO_MILESTONE: &str = "(no milestone)";
+
+/// Open and closed item counts per milestone title. Milestone counts come from
+/// GitHub and cover all items; the no-milestone row counts the stored issues
+/// (PRs included) that have none.
+pub fn milestone_counts(
+    conn: &Connection,
+    repo_name_full: &str,
+) -> Result<BTreeMap<String, (i64, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT title, open_issues, closed_issues FROM milestones WHERE repo_name = ?1
+        UNION ALL
+        SELECT ?2, COALESCE(SUM(state = 'open'), 0), COALESCE(SUM(state = 'closed'), 0)
+        FROM github_issues
+        WHERE repo_name = ?1 AND milestone_number IS NULL
+        ",
+    )?;
+    let mut counts: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    let rows = stmt.query_map(params![repo_name_full, NO_MILESTONE], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (title, open, closed) = row?;
+        // Titles are unique per repository on GitHub; sum defensively anyway
+        let entry = counts.entry(title).or_insert((0, 0));
+        entry.0 += open;
+        entry.1 += closed;
+    }
+    Ok(counts)
+}
+
+/// Prints open/closed counts per milestone of two repositories side by side,
+/// matching milestones by title.
+pub fn print_milestone_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let counts_a = milestone_counts(conn, repo_a)?;
+    let counts_b = milestone_counts(conn, repo_b)?;
+    let mut titles: Vec<&String> = counts_a.keys().chain(counts_b.keys()).collect();
+    titles.sort();
+    titles.dedup();
+
+    let cell = |counts: &BTreeMap<String, (i64, i64)>, title: &String| match counts.get(title) {
+        Some((open, closed)) => (open.to_string(), closed.to_string()),
+        None => ("-".to_string(), "-".to_string()),
+    };
+    let mut table = Table::new(
+        format!("Milestones: A = {}, B = {}", repo_a, repo_b),
+        &["milestone", "open A", "closed A", "open B", "closed B"],
+    );
+    for title in titles {
+        let (open_a, closed_a) = cell(&counts_a, title);
+        let (open_b, closed_b) = cell(&counts_b, title);
+        table.row(title.as_str(), &[&open_a, &closed_a, &open_b, &closed_b]);
+    }
+    table.print(style);
+    Ok(())
+}
+
+// Reviewer pairs listed per fork in the report.
+const TOP_REVIEW_PAIRS: usize = 10;
+
+/// Reviewer->author pairs with the number of PRs reviewed, most frequent first.
+pub fn review_pairs(conn: &Connection, repo_name_full: &str) -> Result<Vec<(String, String, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT reviewer_login, author_login, COUNT(*) AS prs
+        FROM review_edges
+        WHERE repo_name = ?1
+        GROUP BY reviewer_login, author_login
+        ORDER BY prs DESC, reviewer_login, author_login
+        ",
+    )?;
+    let rows = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Prints the most frequent reviewer->author pairs of each repository.
+pub fn print_review_pair_comparison(conn: &Connection, repo_a: &str, repo_b: &str) -> Result<()> {
+    for repo in [repo_a, repo_b] {
+        let pairs = review_pairs(conn, repo)?;
+        println!("Top reviewer -> author pairs: {}", repo);
+        if pairs.is_empty() {
+            println!("  (no review edges; fetch with --reviews)");
+        }
+        for (reviewer, author, prs) in pairs.iter().take(TOP_REVIEW_PAIRS) {
+            println!("  {:<24} -> {:<24} {:>6}", reviewer, author, prs);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+// Mergers listed per repository in the merge authority report.
+const TOP_MERGERS: usize = 10;
+
+/// Prints who merges PRs in each repository and how concentrated merging
+/// is: the number of distinct mergers and the top merger's share.
+pub fn print_merger_comparison(conn: &Connection, repo_a: &str, repo_b: &str) -> Result<()> {
+    for repo in [repo_a, repo_b] {
+        let mergers = queries::merger_counts(conn, repo)?;
+        println!("Who merges PRs: {}", repo);
+        let total: i64 = mergers.iter().map(|(_, merged)| merged).sum();
+        if total == 0 {
+            println!("  (no mergers recorded; merged_by comes from single-PR fetches)");
+            println!();
+            continue;
+        }
+        let share = |merged: i64| merged as f64 * 100.0 / total as f64;
+        println!(
+            "  {} distinct mergers of {} PRs; top merger {:.1}%",
+            mergers.len(),
+            total,
+            share(mergers[0].1)
+        );
+        for (login, merged) in mergers.iter().take(TOP_MERGERS) {
+            println!("  {:<24} {:>6} {:>6.1}%", login, merged, share(*merged));
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Prints how often PRs were merged by their own author in two repositories.
+pub fn print_self_merge_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let a = queries::self_merge_stats(conn, repo_a)?;
+    let b = queries::self_merge_stats(conn, repo_b)?;
+    let share = |stats: &queries::SelfMergeStats| {
+        stats
+            .share()
+            .map_or("-".to_string(), |share| format!("{:.1}%", share))
+    };
+
+    let mut table = Table::new(
+        format!("Self-merged PRs: A = {}, B = {}", repo_a, repo_b),
+        &["metric", "A", "B"],
+    );
+    table.row("merged PRs", &[&a.merged, &b.merged]);
+    table.row("merger known", &[&a.with_merger, &b.with_merger]);
+    table.row("self-merged", &[&a.self_merged, &b.self_merged]);
+    table.row("share", &[&share(&a), &share(&b)]);
+    table.print(style);
+    Ok(())
+}
+
+/// Prints how much of the activity of two repositories comes from their
+/// maintainers rather than the wider community.
+pub fn print_maintainer_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let a = queries::maintainer_splits(conn, repo_a)?;
+    let b = queries::maintainer_splits(conn, repo_b)?;
+    let share = |split: &queries::MaintainerSplit| {
+        split
+            .share()
+            .map_or("-".to_string(), |share| format!("{:.1}%", share))
+    };
+
+    let mut table = Table::new(
+        format!("Maintainers vs community: A = {}, B = {}", repo_a, repo_b),
+        &["metric", "A", "B"],
+    );
+    for ((kind, a), (_, b)) in a.iter().zip(&b) {
+        table.row(
+            format!("{} by maintainers", kind),
+            &[&a.maintainer, &b.maintainer],
+        );
+        table.row(
+            format!("{} by community", kind),
+            &[&a.community, &b.community],
+        );
+        table.row(format!("{} untagged", kind), &[&a.untagged, &b.untagged]);
+        table.row(
+            format!("{} maintainer share", kind),
+            &[&share(a), &share(b)],
+        );
+    }
+    table.print(style);
+    Ok(())
+}
+
+/// Prints how many users have admin, maintain and push access to two
+/// repositories, as stored by `--collaborators`.
+pub fn print_collaborator_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let a = queries::collaborator_access(conn, repo_a)?;
+    let b = queries::collaborator_access(conn, repo_b)?;
+
+    let mut table = Table::new(
+        format!("Collaborators by access: A = {}, B = {}", repo_a, repo_b),
+        &["access", "A", "B"],
+    );
+    table.row("collaborators", &[&a.collaborators, &b.collaborators]);
+    table.row("admin", &[&a.admin, &b.admin]);
+    table.row("maintain", &[&a.maintain, &b.maintain]);
+    table.row("push", &[&a.push, &b.push]);
+    table.print(style);
+    Ok(())
+}
+
+/// Prints commit hygiene of two repositories: how long commit subjects are
+/// and how many carry a prefix matching `prefix` (e.g. conventional commits).
+/// Skipped with a note when no commit messages are stored.
+pub fn print_commit_message_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    prefix: &Regex,
+    style: TableStyle,
+) -> Result<()> {
+    let a = queries::commit_subjects(conn, repo_a)?;
+    let b = queries::commit_subjects(conn, repo_b)?;
+    let title = format!("Commit messages: A = {}, B = {}", repo_a, repo_b);
+    if a.is_empty() && b.is_empty() {
+        println!("{}", title);
+        println!("(no commit messages stored; fetch without --commit-message none)");
+        println!();
+        return Ok(());
+    }
+    let mean_length = |subjects: &[String]| {
+        if subjects.is_empty() {
+            return "-".to_string();
+        }
+        let total: usize = subjects.iter().map(|s| s.chars().count()).sum();
+        format!("{:.1}", total as f64 / subjects.len() as f64)
+    };
+    let prefixed = |subjects: &[String]| {
+        if subjects.is_empty() {
+            return "-".to_string();
+        }
+        let matching = subjects.iter().filter(|s| prefix.is_match(s)).count();
+        format!("{:.1}%", 100.0 * matching as f64 / subjects.len() as f64)
+    };
+
+    let mut table = Table::new(title, &["metric", "A", "B"]);
+    table.row("non-merge commits", &[&a.len(), &b.len()]);
+    table.row("mean subject length", &[&mean_length(&a), &mean_length(&b)]);
+    table.row("prefixed subjects", &[&prefixed(&a), &prefixed(&b)]);
+    table.print(style);
+    Ok(())
+}
+
+/// Prints the recent activity feeds of two repositories by event type. The
+/// feeds hold at most 300 events, so a busy repository's covers fewer days;
+/// both are counted from the later of the two oldest events, so the columns
+/// cover the same period.
+pub fn print_event_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let since =
+        queries::oldest_event_at(conn, repo_a)?.max(queries::oldest_event_at(conn, repo_b)?);
+    let a = queries::event_type_counts(conn, repo_a, since.as_deref())?;
+    let b = queries::event_type_counts(conn, repo_b, since.as_deref())?;
+    let day: String = since
+        .as_deref()
+        .map_or("-".to_string(), |t| t.chars().take(10).collect());
+
+    let mut table = Table::new(
+        format!(
+            "Recent events since {}: A = {}, B = {}",
+            day, repo_a, repo_b
+        ),
+        &["event", "A", "B"],
+    );
+    let types: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    for event_type in types {
+        table.row(
+            event_type.as_str(),
+            &[
+                a.get(event_type).unwrap_or(&0),
+                b.get(event_type).unwrap_or(&0),
+            ],
+        );
+    }
+    table.print(style);
+    Ok(())
+}
+
+/// Prints how the closed issues of two repositories were closed: as completed
+/// or as not planned, a sign of how strictly each triages.
+pub fn print_closure_reason_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let a = queries::closure_reasons(conn, repo_a)?;
+    let b = queries::closure_reasons(conn, repo_b)?;
+    let share = |reasons: &queries::ClosureReasons| {
+        reasons
+            .not_planned_share()
+            .map_or("-".to_string(), |s| format!("{:.1}%", 100.0 * s))
+    };
+
+    let mut table = Table::new(
+        format!("Issue close reasons: A = {}, B = {}", repo_a, repo_b),
+        &["metric", "A", "B"],
+    );
+    table.row("closed issues", &[&a.closed, &b.closed]);
+    table.row("completed", &[&a.completed, &b.completed]);
+    table.row("not planned", &[&a.not_planned, &b.not_planned]);
+    table.row(
+        "no reason recorded",
+        &[
+            &(a.closed - a.completed - a.not_planned),
+            &(b.closed - b.completed - b.not_planned),
+        ],
+    );
+    table.row("not planned share", &[&share(&a), &share(&b)]);
+    table.print(style);
+    Ok(())
+}
+
+/// Writes the weighted reviewer->author edge list of the given repositories
+/// as CSV (`repo_name,reviewer,author,prs`).
+pub fn write_review_edges_csv(conn: &Connection, path: &str, repos: &[&str]) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "repo_name,reviewer,author,prs")?;
+    for repo_name_full in repos {
+        for (reviewer, author, prs) in review_pairs(conn, repo_name_full)? {
+            writeln!(out, "{},{},{},{}", repo_name_full, reviewer, author, prs)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Authors listed per fork in the churn report.
+const TOP_CHURN_AUTHORS: usize = 10;
+
+/// Prints the authors with the most lines changed in each repository's local
+/// history.
+pub fn print_churn_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    exclude_merges: bool,
+) -> Result<()> {
+    for repo in [repo_a, repo_b] {
+        let churn = queries::author_churn(conn, repo, exclude_merges)?;
+        println!("Top authors by lines changed: {}", repo);
+        println!("  {:<40} {:>10} {:>10}", "author", "added", "removed");
+        if churn.is_empty() {
+            println!("  (no line counts; extract local commits from a full clone)");
+        }
+        for (author, insertions, deletions) in churn.iter().take(TOP_CHURN_AUTHORS) {
+            println!("  {:<40} {:>10} {:>10}", author, insertions, deletions);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Local commit count per subsystem.
+pub fn subsystem_activity(conn: &Connection, repo_name_full: &str) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT subsystem, COUNT(*)
+        FROM commit_subsystems
+        WHERE repo_name = ?1
+        GROUP BY subsystem
+        ORDER BY subsystem
+        ",
+    )?;
+    let rows = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Prints per-subsystem commit and author counts of two repositories side by
+/// side. Subsystems touched by a single author in a fork are marked `solo`,
+/// a rough bus-factor signal.
+pub fn print_subsystem_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    // subsystem -> [(commits, authors) of A, (commits, authors) of B]
+    let mut subsystems: BTreeMap<String, [(i64, i64); 2]> = BTreeMap::new();
+    for (side, repo) in [repo_a, repo_b].into_iter().enumerate() {
+        for (subsystem, commits) in subsystem_activity(conn, repo)? {
+            subsystems.entry(subsystem).or_default()[side].0 = commits;
+        }
+        for (subsystem, authors) in queries::subsystem_author_counts(conn, repo)? {
+            subsystems.entry(subsystem).or_default()[side].1 = authors;
+        }
+    }
+    let authors = |count: i64| match count {
+        1 => "1 solo".to_string(),
+        n => n.to_string(),
+    };
+
+    let mut table = Table::new(
+        format!("Local commits by subsystem: A = {}, B = {}", repo_a, repo_b),
+        &[
+            "subsystem",
+            "commits A",
+            "commits B",
+            "authors A",
+            "authors B",
+        ],
+    );
+    for (subsystem, [a, b]) in subsystems {
+        table.row(subsystem, &[&a.0, &b.0, &authors(a.1), &authors(b.1)]);
+    }
+    table.print(style);
+    Ok(())
+}
+
+/// Prints the share of green and red commits (by CI result) of two repositories.
+pub fn print_ci_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let a = queries::ci_outcomes(conn, repo_a)?;
+    let b = queries::ci_outcomes(conn, repo_b)?;
+    let rate = |count: i64, (green, red, other): (i64, i64, i64)| {
+        let total = green + red + other;
+        if total == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.1}%", 100.0 * count as f64 / total as f64)
+        }
+    };
+
+    let mut table = Table::new(
+        format!("CI results per commit: A = {}, B = {}", repo_a, repo_b),
+        &["metric", "A", "B"],
+    );
+    table.row("commits with CI", &[&(a.0 + a.1 + a.2), &(b.0 + b.1 + b.2)]);
+    table.row("green", &[&rate(a.0, a), &rate(b.0, b)]);
+    table.row("red", &[&rate(a.1, a), &rate(b.1, b)]);
+    table.row("pending", &[&rate(a.2, a), &rate(b.2, b)]);
+    table.print(style);
+    Ok(())
+}
+
+// Trailers compared between the repositories: DCO sign-off and review
+// attribution in the commit message.
+const COMPARED_TRAILERS: &[&str] = &["Signed-off-by", "Reviewed-by"];
+
+/// Prints how often the commits of two repositories carry sign-off and
+/// review trailers.
+pub fn print_trailer_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let (commits_a, a) = queries::trailer_usage(conn, repo_a, COMPARED_TRAILERS)?;
+    let (commits_b, b) = queries::trailer_usage(conn, repo_b, COMPARED_TRAILERS)?;
+    let share = |count: i64, commits: i64| {
+        if commits == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.1}%", 100.0 * count as f64 / commits as f64)
+        }
+    };
+
+    let mut table = Table::new(
+        format!("Commit trailers: A = {}, B = {}", repo_a, repo_b),
+        &["trailer", "A", "B"],
+    );
+    table.row("commits", &[&commits_a, &commits_b]);
+    for (i, key) in COMPARED_TRAILERS.iter().enumerate() {
+        table.row(*key, &[&share(a[i], commits_a), &share(b[i], commits_b)]);
+    }
+    table.print(style);
+    Ok(())
+}
+
+/// Prints the share of stored commits that never reached the default branch,
+/// such as work on long-lived side branches.
+pub fn print_default_branch_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let a = queries::default_branch_counts(conn, repo_a)?;
+    let b = queries::default_branch_counts(conn, repo_b)?;
+    let share = |(on, off): (i64, i64)| {
+        if on + off == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.1}%", 100.0 * off as f64 / (on + off) as f64)
+        }
+    };
+
+    let mut table = Table::new(
+        format!(
+            "Commits off the default branch: A = {}, B = {}",
+            repo_a, repo_b
+        ),
+        &["metric", "A", "B"],
+    );
+    table.row("on default", &[&a.0, &b.0]);
+    table.row("off default", &[&a.1, &b.1]);
+    table.row("off share", &[&share(a), &share(b)]);
+    table.print(style);
+    Ok(())
+}
+
+// Median of an ascending, non-empty slice.
+fn median(sorted: &[f64]) -> f64 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Prints median and mean time to first review (in hours) of two repositories.
+pub fn print_first_review_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let hours_a = queries::first_review_hours(conn, repo_a)?;
+    let hours_b = queries::first_review_hours(conn, repo_b)?;
+    let stat = |hours: &[f64], f: fn(&[f64]) -> f64| {
+        if hours.is_empty() {
+            "-".to_string()
+        } else {
+            format!("{:.1}", f(hours))
+        }
+    };
+    let mean = |hours: &[f64]| hours.iter().sum::<f64>() / hours.len() as f64;
+
+    let mut table = Table::new(
+        format!(
+            "Time to first review (hours): A = {}, B = {}",
+            repo_a, repo_b
+        ),
+        &["metric", "A", "B"],
+    );
+    table.row("reviewed PRs", &[&hours_a.len(), &hours_b.len()]);
+    table.row(
+        "median",
+        &[&stat(&hours_a, median), &stat(&hours_b, median)],
+    );
+    table.row("mean", &[&stat(&hours_a, mean), &stat(&hours_b, mean)]);
+    table.print(style);
+    Ok(())
+}
+
+/// Prints the median time to first response (in hours) to issues and PRs of
+/// two repositories.
+pub fn print_first_response_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let stat = |hours: &[f64]| {
+        if hours.is_empty() {
+            "-".to_string()
+        } else {
+            format!("{:.1}", median(hours))
+        }
+    };
+
+    let mut table = Table::new(
+        format!(
+            "Time to first response (hours): A = {}, B = {}",
+            repo_a, repo_b
+        ),
+        &["metric", "A", "B"],
+    );
+    for (kind, pull_requests) in [("issues", false), ("PRs", true)] {
+        let a = queries::first_response_hours(conn, repo_a, pull_requests)?;
+        let b = queries::first_response_hours(conn, repo_b, pull_requests)?;
+        table.row(format!("{} responded", kind), &[&a.len(), &b.len()]);
+        table.row(format!("{} median", kind), &[&stat(&a), &stat(&b)]);
+    }
+    table.print(style);
+    Ok(())
+}
+
+// Percentiles of PR latencies compared in the report.
+const LATENCY_PERCENTILES: &[(&str, f64)] =
+    &[("p50", 0.5), ("p75", 0.75), ("p90", 0.9), ("p99", 0.99)];
+
+/// Prints the distribution of time to first review and of how long PRs stay
+/// open (in hours) in two repositories, where medians hide the tails.
+pub fn print_latency_percentile_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let percentiles: Vec<f64> = LATENCY_PERCENTILES.iter().map(|(_, p)| *p).collect();
+    let hours = |value: &Option<f64>| value.map_or("-".to_string(), |h| format!("{:.1}", h));
+
+    let mut table = Table::new(
+        format!(
+            "PR latency percentiles (hours): A = {}, B = {}",
+            repo_a, repo_b
+        ),
+        &["metric", "A", "B"],
+    );
+    for (kind, latency) in [
+        ("first review", queries::PrLatency::FirstReview),
+        ("open duration", queries::PrLatency::OpenDuration),
+    ] {
+        let a = queries::pr_latency_percentiles(conn, repo_a, latency, &percentiles)?;
+        let b = queries::pr_latency_percentiles(conn, repo_b, latency, &percentiles)?;
+        for (i, (label, _)) in LATENCY_PERCENTILES.iter().enumerate() {
+            table.row(
+                format!("{} {}", kind, label),
+                &[&hours(&a[i]), &hours(&b[i])],
+            );
+        }
+    }
+    table.print(style);
+    Ok(())
+}
+
+// Backport pairs listed in the report, most recent first.
+const TOP_BACKPORTS: usize = 20;
+
+// Title words that only mark a PR as a backport.
+const BACKPORT_MARKERS: &[&str] = &["backport", "backports", "bp"];
+
+/// A PR of repository B that likely carries the same change as a PR of
+/// repository A, such as a Knots cherry-pick of a Core PR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackportMatch {
+    pub number_a: i64,
+    pub number_b: i64,
+    pub title_b: String,
+    /// Both PRs have the same merge commit
+    pub same_commit: bool,
+    /// Normalized title similarity, from 0 to 1
+    pub similarity: f64,
+    /// From A's merge to B's; negative when B merged first
+    pub lag: Duration,
+}
+
+// Lowercase words of a title, without leading bracketed tags such as `[28.x]`
+// or backport markers, so a backport's title compares equal to the original.
+fn title_words(title: &str) -> Vec<String> {
+    let mut rest = title.trim();
+    while let Some((_, after)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+        rest = after.trim_start();
+    }
+    rest.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| !BACKPORT_MARKERS.contains(&word.as_str()))
+        .collect()
+}
+
+// Sorted, distinct character bigrams of a normalized title.
+fn title_bigrams(title: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = title_words(title).join(" ").chars().collect();
+    let mut bigrams: Vec<_> = chars.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    bigrams.sort_unstable();
+    bigrams.dedup();
+    bigrams
+}
+
+// Dice coefficient of two sorted bigram sets.
+fn dice(a: &[(char, char)], b: &[(char, char)]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    2.0 * shared as f64 / (a.len() + b.len()) as f64
+}
+
+/// Similarity of two PR titles from 0 to 1 (the Dice coefficient of their
+/// character bigrams), ignoring case, punctuation, `[tag]` prefixes and
+/// backport markers.
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    dice(&title_bigrams(a), &title_bigrams(b))
+}
+
+/// Pairs merged PRs of B with the merged PRs of A carrying the same change:
+/// those with the same merge commit first, then by descending title
+/// similarity down to `threshold`. Each PR is paired at most once. Matches
+/// are returned most recent B merge first.
+pub fn match_backports(a: &[MergedPr], b: &[MergedPr], threshold: f64) -> Vec<BackportMatch> {
+    let by_sha: HashMap<&str, usize> = a
+        .iter()
+        .enumerate()
+        .filter_map(|(i, pr)| Some((pr.merge_commit_sha.as_deref()?, i)))
+        .collect();
+    let bigrams_a: Vec<_> = a.iter().map(|pr| title_bigrams(&pr.title)).collect();
+
+    // (same commit, similarity, index in a, index in b)
+    let mut candidates = Vec::new();
+    for (j, pr_b) in b.iter().enumerate() {
+        let bigrams_b = title_bigrams(&pr_b.title);
+        let shared = pr_b
+            .merge_commit_sha
+            .as_deref()
+            .and_then(|sha| by_sha.get(sha));
+        for (i, bigrams) in bigrams_a.iter().enumerate() {
+            let similarity = dice(bigrams, &bigrams_b);
+            let same_commit = shared == Some(&i);
+            if same_commit || similarity >= threshold {
+                candidates.push((same_commit, similarity, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| {
+        (y.0, y.1)
+            .partial_cmp(&(x.0, x.1))
+            .unwrap_or(Ordering::Equal)
+            .then((x.2, x.3).cmp(&(y.2, y.3)))
+    });
+
+    let mut paired_a = vec![false; a.len()];
+    let mut paired_b = vec![false; b.len()];
+    let mut matches = Vec::new();
+    for (same_commit, similarity, i, j) in candidates {
+        if paired_a[i] || paired_b[j] {
+            continue;
+        }
+        paired_a[i] = true;
+        paired_b[j] = true;
+        matches.push((
+            j,
+            BackportMatch {
+                number_a: a[i].number,
+                number_b: b[j].number,
+                title_b: b[j].title.clone(),
+                same_commit,
+                similarity,
+                lag: b[j].merged_at - a[i].merged_at,
+            },
+        ));
+    }
+    // `b` is ordered by merge time
+    matches.sort_by_key(|(j, _)| Reverse(*j));
+    matches.into_iter().map(|(_, m)| m).collect()
+}
+
+/// Prints how many merged PRs of B correspond to a PR of A (see
+/// [`match_backports`]) and the lag between the two merges, in days.
+pub fn print_backport_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    threshold: f64,
+) -> Result<()> {
+    let prs_a = queries::merged_prs(conn, repo_a)?;
+    let prs_b = queries::merged_prs(conn, repo_b)?;
+    let matches = match_backports(&prs_a, &prs_b, threshold);
+    let days = |lag: Duration| lag.num_seconds() as f64 / 86_400.0;
+
+    println!(
+        "Likely backports (same merge commit or title similarity >= {:.2}): A = {}, B = {}",
+        threshold, repo_a, repo_b
+    );
+    println!(
+        "  {} of {} merged PRs of B match a PR of A ({} by merge commit)",
+        matches.len(),
+        prs_b.len(),
+        matches.iter().filter(|m| m.same_commit).count()
+    );
+    if matches.is_empty() {
+        println!();
+        return Ok(());
+    }
+    let mut lags: Vec<f64> = matches.iter().map(|m| days(m.lag)).collect();
+    lags.sort_by(f64::total_cmp);
+    println!(
+        "  lag (days): median {:.1}, mean {:.1}",
+        median(&lags),
+        lags.iter().sum::<f64>() / lags.len() as f64
+    );
+    println!(
+        "  {:>7} {:>7} {:>8} {:>5}  title (B)",
+        "A", "B", "lag (d)", "sim"
+    );
+    for m in matches.iter().take(TOP_BACKPORTS) {
+        println!(
+            "  {:>7} {:>7} {:>8.1} {:>5.2}  {}",
+            format!("#{}", m.number_a),
+            format!("#{}", m.number_b),
+            days(m.lag),
+            m.similarity,
+            m.title_b
+        );
+    }
+    println!();
+    Ok(())
+}
+
+/// Headline repository counts recorded by one fetch run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoSnapshot {
+    pub fetched_at: String,
+    pub stargazers: i64,
+    pub forks: i64,
+    pub open_issues: i64,
+    /// SPDX id (or key) of the detected license
+    pub license: Option<String>,
+}
+
+/// All snapshots recorded for a repository, oldest first.
+pub fn repo_snapshots(conn: &Connection, repo_name_full: &str) -> Result<Vec<RepoSnapshot>> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT fetched_at, stargazers_count, forks_count, open_issues_count,
+            COALESCE(license_spdx_id, license_key)
+        FROM repo_snapshots
+        WHERE repo_name = ?1
+        ORDER BY fetched_at
+        ",
+    )?;
+    let snapshots = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok(RepoSnapshot {
+                fetched_at: row.get(0)?,
+                stargazers: row.get(1)?,
+                forks: row.get(2)?,
+                open_issues: row.get(3)?,
+                license: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(snapshots)
+}
+
+// Release a tag stands for, so Core's v27.1 and Knots' v27.1.knots20240801
+// line up: the leading dotted version, as numbers for sorting.
+fn release_version(tag: &str) -> Option<Vec<u64>> {
+    let version: String = tag
+        .trim_start_matches('v')
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    version
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()
+        .filter(|parts| !parts.is_empty())
+}
+
+/// Prints the number of commits each release of two repositories included
+/// since the previous release tag, matching releases by version.
+pub fn print_changelog_comparison(
+    conn: &Connection,
+    repo_a: &str,
+    repo_b: &str,
+    style: TableStyle,
+) -> Result<()> {
+    let mut releases: BTreeMap<Vec<u64>, (Option<i64>, Option<i64>)> = BTreeMap::new();
+    for (tag, commits) in queries::changelog_sizes(conn, repo_a)? {
+        if let Some(version) = release_version(&tag) {
+            releases.entry(version).or_default().0 = Some(commits);
+        }
+    }
+    for (tag, commits) in queries::changelog_sizes(conn, repo_b)? {
+        if let Some(version) = release_version(&tag) {
+            releases.entry(version).or_default().1 = Some(commits);
+        }
+    }
+
+    let title = format!(
+        "Commits per release since the previous tag: A = {}, B = {}",
+        repo_a, repo_b
+    );
+    if releases.is_empty() {
+        println!("{}", title);
+        println!("(no changelogs recorded; fetch with --tag-changelogs)");
+        println!();
+        return Ok(());
+    }
+    let cell = |commits: Option<i64>| commits.map_or("-".to_string(), |c| c.to_string());
+    let mut table = Table::new(title, &["release", "A", "B"]);
+    for (version, (a, b)) in releases {
+        let version: Vec<String> = version.iter().map(u64::to_string).collect();
+        table.row(version.join("."), &[&cell(a), &cell(b)]);
+    }
+    table.print(style);
+    Ok(())
+}
+
+// Languages shown as their own column in the language trend; the rest are
+// summed as "other".
+const TOP_LANGUAGES: usize = 5;
+
+/// Prints each language's share of a repository's code across snapshots,
+/// for the languages largest in the latest snapshot.
+pub fn print_language_trend(repo_name_full: &str, snapshots: &[queries::LanguageSnapshot]) {
+    println!("Language mix (% of bytes): {}", repo_name_full);
+    let Some(latest) = snapshots.last() else {
+        println!("(no language snapshots recorded yet)");
+        println!();
+        return;
+    };
+    let mut languages: Vec<(&String, &f64)> = latest.shares.iter().collect();
+    languages.sort_by(|a, b| b.1.total_cmp(a.1));
+    let columns: Vec<&String> = languages
+        .into_iter()
+        .take(TOP_LANGUAGES)
+        .map(|(language, _)| language)
+        .collect();
+
+    print!("{:<25}", "fetched at");
+    for language in &columns {
+        print!(" {:>12}", language);
+    }
+    println!(" {:>12}", "other");
+    for snapshot in snapshots {
+        print!("{:<25}", snapshot.fetched_at);
+        let mut shown = 0.0;
+        for language in &columns {
+            let share = snapshot.shares.get(*language).copied().unwrap_or(0.0);
+            shown += share;
+            print!(" {:>12.1}", share);
+        }
+        let total: f64 = snapshot.shares.values().sum();
+        println!(" {:>12.1}", (total - shown).max(0.0));
+    }
+    println!();
+}
+
+/// Prints the open issues, stars and forks of a repository across snapshots,
+/// with the change since the previous snapshot.
+pub fn print_snapshot_trend(repo_name_full: &str, snapshots: &[RepoSnapshot]) {
+    println!("Snapshots: {}", repo_name_full);
+    if snapshots.is_empty() {
+        println!("(no snapshots recorded yet)");
+        println!();
+        return;
+    }
+    println!(
+        "{:<25} {:>14} {:>14} {:>14}  license",
+        "fetched at", "open issues", "stars", "forks"
+    );
+    let mut previous: Option<&RepoSnapshot> = None;
+    for snapshot in snapshots {
+        let delta = |current: i64, before: Option<i64>| match before {
+            Some(before) => format!("{} ({:+})", current, current - before),
+            None => current.to_string(),
+        };
+        println!(
+            "{:<25} {:>14} {:>14} {:>14}  {}",
+            snapshot.fetched_at,
+            delta(snapshot.open_issues, previous.map(|p| p.open_issues)),
+            delta(snapshot.stargazers, previous.map(|p| p.stargazers)),
+            delta(snapshot.forks, previous.map(|p| p.forks)),
+            snapshot.license.as_deref().unwrap_or("-")
+        );
+        previous = Some(snapshot);
+    }
+    println!();
+}
+
+/// Prints the activity of two repositories side by side, one row per bucket.
+pub fn print_activity_comparison(
+    repo_a: &str,
+    activity_a: &[ActivityBucket],
+    repo_b: &str,
+    activity_b: &[ActivityBucket],
+) {
+    let mut periods: BTreeMap<&str, (Option<&ActivityBucket>, Option<&ActivityBucket>)> =
+        BTreeMap::new();
+    for bucket in activity_a {
+        periods.entry(&bucket.period).or_default().0 = Some(bucket);
+    }
+    for bucket in activity_b {
+        periods.entry(&bucket.period).or_default().1 = Some(bucket);
+    }
+
+    println!("Activity trend: A = {}, B = {}", repo_a, repo_b);
+    println!(
+        "{:<10} {:>9} {:>9} {:>7} {:>7} {:>8} {:>8}",
+        "period", "commits A", "commits B", "PRs A", "PRs B", "issues A", "issues B"
+    );
+    let empty = ActivityBucket::default();
+    for (period, (a, b)) in periods {
+        let a = a.unwrap_or(&empty);
+        let b = b.unwrap_or(&empty);
+        println!(
+            "{:<10} {:>9} {:>9} {:>7} {:>7} {:>8} {:>8}",
+            period, a.commits, b.commits, a.pull_requests, b.pull_requests, a.issues, b.issues
+        );
+    }
+}
+
+/// Writes bucketed activity for any number of repositories as tidy CSV
+/// (one row per repository and bucket).
+pub fn write_activity_csv(path: &str, series: &[(&str, &[ActivityBucket])]) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "repo_name,period,commits,pull_requests,issues")?;
+    for (repo_name_full, buckets) in series {
+        for bucket in buckets.iter() {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                repo_name_full, bucket.period, bucket.commits, bucket.pull_requests, bucket.issues
+            )?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn pr(number: i64, title: &str, day: u32, sha: Option<&str>) -> MergedPr {
+        MergedPr {
+            number,
+            title: title.to_string(),
+            merged_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            merge_commit_sha: sha.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn backports_match_by_commit_then_title() {
+        let core = [
+            pr(100, "wallet: Fix fee bump of descendants", 1, Some("c1")),
+            pr(101, "net: Drop v1 handshake fallback", 2, Some("c2")),
+            pr(102, "doc: Update release notes", 3, Some("c3")),
+        ];
+        let knots = [
+            pr(
+                7,
+                "[28.x] Backport: wallet: fix fee-bump of descendants",
+                4,
+                Some("k1"),
+            ),
+            pr(8, "Merge core PR", 5, Some("c2")),
+            pr(9, "gui: New icon", 6, Some("k3")),
+        ];
+        assert_eq!(title_similarity(&core[0].title, &knots[0].title), 1.0);
+
+        let matches = match_backports(&core, &knots, 0.85);
+        let pairs: Vec<_> = matches
+            .iter()
+            .map(|m| (m.number_a, m.number_b, m.same_commit, m.lag.num_days()))
+            .collect();
+        assert_eq!(pairs, [(101, 8, true, 3), (100, 7, false, 3)]);
+    }
+
+    #[test]
+    fn weekly_activity_keys_by_monday_and_skips_prs_in_issues() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::create_tables(&conn).unwrap();
+        conn.execute_batch(
+            r"
+            INSERT INTO github_issues (id, number, repo_name, created_at, is_pull_request) VALUES
+                (1, 1, 'x/y', '2024-12-30T10:00:00Z', 0),
+                (2, 2, 'x/y', '2025-01-05T23:00:00Z', 0),
+                (3, 3, 'x/y', '2025-01-01T00:00:00Z', 1),
+                (4, 4, 'x/y', '2025-01-06T00:00:00Z', NULL);
+            INSERT INTO github_pull_requests (id, number, repo_name, created_at) VALUES
+                (3, 3, 'x/y', '2025-01-01T00:00:00Z');
+            ",
+        )
+        .unwrap();
+        let activity = activity_by_bucket(&conn, "x/y", Bucket::Week, false).unwrap();
+        let weeks: Vec<_> = activity
+            .iter()
+            .map(|b| (b.period.as_str(), b.pull_requests, b.issues))
+            .collect();
+        assert_eq!(weeks, [("2024-12-30", 1, 2), ("2025-01-06", 0, 1)]);
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Caps the total time spent waiting before retries across a whole run, so
+/// retry loops can't add up to an unbounded runtime. Clients of the same run
+/// share one budget; once it is spent, retries fail instead of waiting.
+pub struct RetryBudget {
+    max_total_wait: Option<Duration>,
+    // (retries granted, wait granted)
+    spent: Mutex<(usize, Duration)>,
+}
+
+impl RetryBudget {
+    pub fn new(max_total_wait: Option<Duration>) -> Self {
+        Self {
+            max_total_wait,
+            spent: Mutex::new((0, Duration::ZERO)),
+        }
+    }
+
+    /// A budget that grants every retry.
+    pub fn unlimited() -> Self {
+        Self::new(None)
+    }
+
+    /// Reserves `wait` for one retry. Returns false, reserving nothing, if
+    /// that would take the run past its total.
+    pub fn try_spend(&self, wait: Duration) -> bool {
+        let mut spent = self.spent.lock().unwrap();
+        if self.max_total_wait.is_some_and(|max| spent.1 + wait > max) {
+            log::warn!(
+                "Retry budget exhausted after {} retries and {}s of waiting",
+                spent.0,
+                spent.1.as_secs()
+            );
+            return false;
+        }
+        spent.0 += 1;
+        spent.1 += wait;
+        true
+    }
+
+    /// Retries granted so far and the wait they reserved.
+    pub fn spent(&self) -> (usize, Duration) {
+        *self.spent.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_retries_past_the_total_wait() {
+        let budget = RetryBudget::new(Some(Duration::from_secs(60)));
+        assert!(budget.try_spend(Duration::from_secs(40)));
+        assert!(!budget.try_spend(Duration::from_secs(30)));
+        assert!(budget.try_spend(Duration::from_secs(20)));
+        assert_eq!(budget.spent(), (2, Duration::from_secs(60)));
+        assert!(RetryBudget::unlimited().try_spend(Duration::MAX));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::db;
+use crate::errors::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// One issue or pull request matching a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    /// `issue` or `pr`
+    pub kind: String,
+    pub number: i64,
+    pub title: String,
+}
+
+// Selects every issue and PR of a repository as (repo_name, kind, number,
+// title, body). The issues list includes PRs, which are taken from the PR
+// table instead.
+const DISCUSSIONS_SQL: &str = r"
+    SELECT repo_name, 'pr' AS kind, number, title, body
+    FROM github_pull_requests
+    WHERE repo_name = ?1
+    UNION ALL
+    SELECT i.repo_name, 'issue', i.number, i.title, i.body
+    FROM github_issues i
+    WHERE i.repo_name = ?1
+      AND NOT EXISTS (
+          SELECT 1 FROM github_pull_requests p
+          WHERE p.repo_name = i.repo_name AND p.number = i.number
+      )
+";
+
+/// Creates the FTS5 index over issue and PR titles and bodies. Returns false,
+/// leaving search to fall back to `LIKE`, when SQLite was built without FTS5.
+pub fn ensure_index(conn: &Connection) -> Result<bool> {
+    let created = conn.execute_batch(
+        r"
+        CREATE VIRTUAL TABLE IF NOT EXISTS discussion_fts USING fts5(
+            repo_name UNINDEXED, kind UNINDEXED, number UNINDEXED, title, body
+        );
+        ",
+    );
+    match created {
+        Ok(()) => Ok(true),
+        Err(e) if e.to_string().contains("no such module") => {
+            log::warn!("SQLite lacks FTS5; search will scan titles and bodies with LIKE");
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Rebuilds the index entries of a repository from its stored issues and PRs.
+/// Does nothing without FTS5.
+pub fn refresh_index(conn: &Connection, repo_name_full: &str) -> Result<()> {
+    if !ensure_index(conn)? {
+        return Ok(());
+    }
+    let tx = db::write_transaction(conn)?;
+    conn.execute(
+        "DELETE FROM discussion_fts WHERE repo_name = ?1",
+        params![repo_name_full],
+    )?;
+    let indexed = conn.execute(
+        &format!(
+            "INSERT INTO discussion_fts (repo_name, kind, number, title, body) {}",
+            DISCUSSIONS_SQL
+        ),
+        params![repo_name_full],
+    )?;
+    tx.commit()?;
+    log::info!(
+        "Indexed {} issues and PRs of {} for search",
+        indexed,
+        repo_name_full
+    );
+    Ok(())
+}
+
+/// Rebuilds the index entries of every repository with issues or PRs.
+pub fn refresh_all(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        r"
+        SELECT repo_name FROM github_pull_requests
+        UNION
+        SELECT repo_name FROM github_issues
+        ",
+    )?;
+    let repos = stmt
+        .query_map([], |row| row.get::<_, Option<String>>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for repo in repos.into_iter().flatten() {
+        refresh_index(conn, &repo)?;
+    }
+    Ok(())
+}
+
+// True if the FTS index exists, which it doesn't for databases never fetched
+// into by a build with FTS5 (or opened read-only before a fetch created it).
+fn has_index(conn: &Connection) -> Result<bool> {
+    let found = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'discussion_fts'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()?;
+    Ok(found.is_some())
+}
+
+/// Issues and PRs of a repository mentioning `query`, best matches first. With
+/// the FTS index `query` uses FTS5 syntax (`RBF`, `"full rbf"`, `rbf OR
+/// replace*`); without it, it is matched as a case-insensitive substring.
+pub fn search(
+    conn: &Connection,
+    repo_name_full: &str,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchHit>> {
+    let (sql, pattern) = if has_index(conn)? {
+        (
+            r"
+            SELECT kind, number, title FROM discussion_fts
+            WHERE discussion_fts MATCH ?2 AND repo_name = ?1
+            ORDER BY rank
+            LIMIT ?3
+            "
+            .to_string(),
+            query.to_string(),
+        )
+    } else {
+        (
+            format!(
+                r"
+                SELECT kind, number, title FROM ({})
+                WHERE title LIKE ?2 OR body LIKE ?2
+                ORDER BY number DESC
+                LIMIT ?3
+                ",
+                DISCUSSIONS_SQL
+            ),
+            format!("%{}%", query),
+        )
+    };
+    let mut stmt = conn.prepare(&sql)?;
+    let hits = stmt
+        .query_map(params![repo_name_full, pattern, limit as i64], |row| {
+            Ok(SearchHit {
+                kind: row.get(0)?,
+                number: row.get(1)?,
+                title: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_tables;
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn.execute_batch(
+            r"
+            INSERT INTO github_pull_requests (id, number, repo_name, title, body) VALUES
+                (1, 10, 'x/y', 'Enable full RBF by default', 'Replace-by-fee for all'),
+                (2, 11, 'x/y', 'Fix typo', NULL);
+            INSERT INTO github_issues (id, number, repo_name, title, body) VALUES
+                (3, 10, 'x/y', 'Enable full RBF by default', 'Replace-by-fee for all'),
+                (4, 12, 'x/y', 'Mempool policy question', 'What about rbf?'),
+                (5, 1, 'other/repo', 'RBF elsewhere', NULL);
+            ",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn numbers(hits: &[SearchHit]) -> Vec<(String, i64)> {
+        let mut numbers: Vec<_> = hits.iter().map(|h| (h.kind.clone(), h.number)).collect();
+        numbers.sort();
+        numbers
+    }
+
+    #[test]
+    fn finds_issues_and_prs_with_and_without_fts() {
+        let conn = test_conn();
+        let expected = [("issue".to_string(), 12), ("pr".to_string(), 10)];
+        assert_eq!(numbers(&search(&conn, "x/y", "rbf", 10).unwrap()), expected);
+
+        refresh_index(&conn, "x/y").unwrap();
+        assert!(has_index(&conn).unwrap());
+        assert_eq!(numbers(&search(&conn, "x/y", "rbf", 10).unwrap()), expected);
+        assert_eq!(
+            search(&conn, "x/y", "typo", 10).unwrap()[0].title,
+            "Fix typo"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::classify::CommitClassifier;
+use crate::config::{CommitDate, CommitMessage};
+use crate::db::{self, RunFilters};
+use crate::errors::Result;
+use crate::github::{GitHubCommit, GitHubContributor, GitHubIssue, GitHubPullRequest};
+use crate::summary::RunSummary;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use std::ops::Deref;
+use std::time::Duration;
+
+/// The writes and run bookkeeping a fetch performs. SQLite is the only
+/// backend: there is no Postgres implementation, and enrichment passes and
+/// reports run their SQL against SQLite through [`SqliteStorage`]'s `Deref`.
+pub trait Storage {
+    /// Creates missing tables and columns.
+    fn create_tables(&self) -> Result<()>;
+
+    fn insert_github_commits(
+        &self,
+        commits: &[GitHubCommit],
+        repo_name_full: &str,
+        batch_size: usize,
+        classifier: &CommitClassifier,
+        message_mode: CommitMessage,
+        date_mode: CommitDate,
+    ) -> Result<()>;
+
+    fn insert_github_pull_requests(
+        &self,
+        prs: &[GitHubPullRequest],
+        repo_name_full: &str,
+        batch_size: usize,
+    ) -> Result<()>;
+
+    fn insert_github_issues(
+        &self,
+        issues: &[GitHubIssue],
+        repo_name_full: &str,
+        batch_size: usize,
+    ) -> Result<()>;
+
+    fn insert_github_contributors(
+        &self,
+        contributors: &[GitHubContributor],
+        repo_name_full: &str,
+        batch_size: usize,
+    ) -> Result<()>;
+
+    /// Records a finished fetch in `runs`.
+    fn record_run(&self, summary: &RunSummary, filters: &RunFilters) -> Result<()>;
+
+    /// When the last fetch that loaded a repository completely, with at most
+    /// `filters`, finished, for `--since-last-run`.
+    fn last_successful_run_finished_at(
+        &self,
+        repo_name_full: &str,
+        filters: &RunFilters,
+    ) -> Result<Option<DateTime<Utc>>>;
+}
+
+/// A SQLite database file (or `:memory:`). Passes the enrichment steps and
+/// reports still run as SQL against SQLite reach the connection through
+/// `Deref`.
+pub struct SqliteStorage {
+    conn: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(db_path: &str, busy_timeout: Duration) -> Result<Self> {
+        Ok(SqliteStorage {
+            conn: db::establish_connection(db_path, busy_timeout)?,
+        })
+    }
+
+    /// Opens an existing database without taking write locks.
+    pub fn open_readonly(db_path: &str, busy_timeout: Duration) -> Result<Self> {
+        Ok(SqliteStorage {
+            conn: db::establish_readonly_connection(db_path, busy_timeout)?,
+        })
+    }
+}
+
+impl Deref for SqliteStorage {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn create_tables(&self) -> Result<()> {
+        db::create_tables(&self.conn)
+    }
+
+    fn insert_github_commits(
+        &self,
+        commits: &[GitHubCommit],
+        repo_name_full: &str,
+        batch_size: usize,
+        classifier: &CommitClassifier,
+        message_mode: CommitMessage,
+        date_mode: CommitDate,
+    ) -> Result<()> {
+        db::insert_github_commits(
+            &self.conn,
+            commits,
+            repo_name_full,
+            batch_size,
+            classifier,
+            message_mode,
+            date_mode,
+        )
+    }
+
+    fn insert_github_pull_requests(
+        &self,
+        prs: &[GitHubPullRequest],
+        repo_name_full: &str,
+        batch_size: usize,
+    ) -> Result<()> {
+        db::insert_github_pull_requests(&self.conn, prs, repo_name_full, batch_size)
+    }
+
+    fn insert_github_issues(
+        &self,
+        issues: &[GitHubIssue],
+        repo_name_full: &str,
+        batch_size: usize,
+    ) -> Result<()> {
+        db::insert_github_issues(&self.conn, issues, repo_name_full, batch_size)
+    }
+
+    fn insert_github_contributors(
+        &self,
+        contributors: &[GitHubContributor],
+        repo_name_full: &str,
+        batch_size: usize,
+    ) -> Result<()> {
+        db::insert_github_contributors(&self.conn, contributors, repo_name_full, batch_size)
+    }
+
+    fn record_run(&self, summary: &RunSummary, filters: &RunFilters) -> Result<()> {
+        db::record_run(&self.conn, summary, filters)
+    }
+
+    fn last_successful_run_finished_at(
+        &self,
+        repo_name_full: &str,
+        filters: &RunFilters,
+    ) -> Result<Option<DateTime<Utc>>> {
+        db::last_successful_run_finished_at(&self.conn, repo_name_full, filters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sqlite_storage_records_runs_through_the_trait() {
+        let sqlite = SqliteStorage::open(":memory:", Duration::from_secs(1)).unwrap();
+        let storage: &dyn Storage = &sqlite;
+        storage.create_tables().unwrap();
+        let filters = RunFilters::default();
+        let last_run = || storage.last_successful_run_finished_at("x/y", &filters);
+        assert_eq!(last_run().unwrap(), None);
+
+        let mut summary = RunSummary::new();
+        summary.repos.push(crate::summary::RepoSummary::new("x/y"));
+        summary.finish();
+        storage.record_run(&summary, &filters).unwrap();
+        assert!(last_run().unwrap().is_some());
+        let runs: i64 = sqlite
+            .query_row("SELECT COUNT(*) FROM runs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(runs, 1);
+    }
+}
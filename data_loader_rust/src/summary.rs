@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+/// What a fetch run loaded for one repository.
+#[derive(Debug, Clone, Default)]
+pub struct RepoSummary {
+    pub repo_name: String,
+    pub commits: usize,
+    pub pull_requests: usize,
+    pub issues: usize,
+    pub contributors: usize,
+    pub local_commits: usize,
+    /// Set when --max-commits-per-repo stopped commit fetching or extraction early.
+    pub truncated: bool,
+    /// Set when commits and PRs were skipped because nothing was pushed since the last run.
+    pub unchanged: bool,
+    /// Set when the repository failed part-way; counts reflect what was stored before.
+    pub error: Option<String>,
+    /// List records `--lenient` dropped because they failed to deserialize.
+    pub skipped_records: usize,
+    /// Set when GitHub no longer serves the repository (410/451); not a failure.
+    pub skipped: Option<String>,
+    /// Problems found by `--verify`; empty when verification passed or was skipped.
+    pub anomalies: Vec<String>,
+}
+
+impl RepoSummary {
+    pub fn new(repo_name: &str) -> Self {
+        Self {
+            repo_name: repo_name.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Aggregated result of a fetch run across all repositories.
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub repos: Vec<RepoSummary>,
+}
+
+impl RunSummary {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            finished_at: None,
+            repos: Vec::new(),
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.finished_at = Some(Utc::now());
+    }
+
+    /// Number of repositories that recorded an error.
+    pub fn failed_repos(&self) -> usize {
+        self.repos.iter().filter(|r| r.error.is_some()).count()
+    }
+
+    /// Total verification anomalies across all repositories.
+    pub fn anomaly_count(&self) -> usize {
+        self.repos.iter().map(|r| r.anomalies.len()).sum()
+    }
+}
+
+impl Default for RunSummary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<28} {:>8} {:>8} {:>8} {:>8} {:>8}  status",
+            "repository", "commits", "PRs", "issues", "contribs", "local"
+        )?;
+        for repo in &self.repos {
+            let mut status = if let Some(error) = &repo.error {
+                error.clone()
+            } else if let Some(reason) = &repo.skipped {
+                format!("skipped: {}", reason)
+            } else if repo.truncated {
+                "ok (commits truncated)".to_string()
+            } else if repo.unchanged {
+                "ok (no new pushes)".to_string()
+            } else {
+                "ok".to_string()
+            };
+            if repo.skipped_records > 0 {
+                status.push_str(&format!(
+                    " ({} malformed records skipped)",
+                    repo.skipped_records
+                ));
+            }
+            writeln!(
+                f,
+                "{:<28} {:>8} {:>8} {:>8} {:>8} {:>8}  {}",
+                repo.repo_name,
+                repo.commits,
+                repo.pull_requests,
+                repo.issues,
+                repo.contributors,
+                repo.local_commits,
+                status
+            )?;
+        }
+        for repo in self.repos.iter().filter(|r| !r.anomalies.is_empty()) {
+            writeln!(f, "Verification of {} failed:", repo.repo_name)?;
+            for anomaly in &repo.anomalies {
+                writeln!(f, "  - {}", anomaly)?;
+            }
+        }
+        if let Some(finished_at) = self.finished_at {
+            write!(
+                f,
+                "Finished at {} ({}s)",
+                finished_at.to_rfc3339(),
+                (finished_at - self.started_at).num_seconds()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The status column of each repository row.
+    fn statuses(summary: &RunSummary) -> Vec<String> {
+        summary
+            .to_string()
+            .lines()
+            .skip(1)
+            .take(summary.repos.len())
+            .map(|line| line.split("  ").last().unwrap().trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn statuses_prefer_errors_and_note_skipped_records() {
+        let mut summary = RunSummary::new();
+        summary.repos = vec![
+            RepoSummary {
+                error: Some("boom".to_string()),
+                truncated: true,
+                ..RepoSummary::new("a/failed")
+            },
+            RepoSummary {
+                skipped: Some("gone".to_string()),
+                ..RepoSummary::new("a/skipped")
+            },
+            RepoSummary {
+                truncated: true,
+                unchanged: true,
+                skipped_records: 3,
+                ..RepoSummary::new("a/truncated")
+            },
+            RepoSummary {
+                unchanged: true,
+                ..RepoSummary::new("a/unchanged")
+            },
+            RepoSummary::new("a/ok"),
+        ];
+        assert_eq!(
+            statuses(&summary),
+            [
+                "boom",
+                "skipped: gone",
+                "ok (commits truncated) (3 malformed records skipped)",
+                "ok (no new pushes)",
+                "ok",
+            ]
+        );
+        assert_eq!(summary.failed_repos(), 1);
+        assert!(!summary.to_string().contains("Finished at"));
+    }
+
+    #[test]
+    fn anomalies_are_listed_per_repository() {
+        let mut summary = RunSummary::new();
+        summary.repos = vec![
+            RepoSummary {
+                anomalies: vec!["missing commit abc".to_string(), "gap".to_string()],
+                ..RepoSummary::new("a/b")
+            },
+            RepoSummary::new("c/d"),
+        ];
+        summary.finish();
+        assert_eq!(summary.anomaly_count(), 2);
+        let text = summary.to_string();
+        assert!(text.contains("Verification of a/b failed:\n  - missing commit abc\n  - gap\n"));
+        assert!(!text.contains("Verification of c/d"));
+        assert!(text.lines().last().unwrap().starts_with("Finished at "));
+    }
+}
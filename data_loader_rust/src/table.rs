@@ -0,0 +1,193 @@
+use crate::config::TableFormat;
+use std::io::IsTerminal;
+
+// ANSI escapes for bold header cells.
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// How the report renders its tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableStyle {
+    pub format: TableFormat,
+    /// Bold header cells; only used by the boxed table format
+    pub color: bool,
+}
+
+impl TableStyle {
+    /// Colors only when asked to, writing to a terminal and `NO_COLOR` is unset.
+    pub fn new(format: TableFormat, no_color: bool) -> Self {
+        let color =
+            !no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+        Self { format, color }
+    }
+}
+
+/// A titled table of text cells. The first column is left-aligned and holds
+/// row labels; the others are right-aligned values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Table {
+    pub title: String,
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(title: impl Into<String>, header: &[&str]) -> Self {
+        Self {
+            title: title.into(),
+            header: header.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Appends a row: its label, then one value per remaining column.
+    pub fn row(&mut self, label: impl Into<String>, values: &[&dyn ToString]) {
+        let mut cells = vec![label.into()];
+        cells.extend(values.iter().map(|value| value.to_string()));
+        self.rows.push(cells);
+    }
+
+    // Width of each column: its widest cell, in characters.
+    fn widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.header.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (i, cell) in row.iter().enumerate() {
+                if i < widths.len() {
+                    widths[i] = widths[i].max(cell.chars().count());
+                }
+            }
+        }
+        widths
+    }
+
+    // A row of cells padded to `widths`, labels left and values right.
+    fn aligned(cells: &[String], widths: &[usize]) -> Vec<String> {
+        widths
+            .iter()
+            .enumerate()
+            .map(|(i, width)| {
+                let cell = cells.get(i).map_or("", String::as_str);
+                if i == 0 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                }
+            })
+            .collect()
+    }
+
+    /// The table with its title, ending in a blank line.
+    pub fn render(&self, style: TableStyle) -> String {
+        let widths = self.widths();
+        let mut out = String::new();
+        match style.format {
+            TableFormat::Table => {
+                let rule = |left: &str, mid: &str, right: &str| {
+                    let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+                    format!("{}{}{}\n", left, segments.join(mid), right)
+                };
+                let line = |cells: Vec<String>| format!("│ {} │\n", cells.join(" │ "));
+                out.push_str(&format!("{}\n", self.title));
+                out.push_str(&rule("┌", "┬", "┐"));
+                let mut header = Self::aligned(&self.header, &widths);
+                if style.color {
+                    header = header
+                        .into_iter()
+                        .map(|cell| format!("{}{}{}", BOLD, cell, RESET))
+                        .collect();
+                }
+                out.push_str(&line(header));
+                out.push_str(&rule("├", "┼", "┤"));
+                for row in &self.rows {
+                    out.push_str(&line(Self::aligned(row, &widths)));
+                }
+                out.push_str(&rule("└", "┴", "┘"));
+            }
+            TableFormat::Plain => {
+                out.push_str(&format!("{}\n", self.title));
+                for cells in std::iter::once(&self.header).chain(&self.rows) {
+                    out.push_str(&format!("{}\n", Self::aligned(cells, &widths).join(" ")));
+                }
+            }
+            TableFormat::Tsv => {
+                out.push_str(&format!("# {}\n", self.title));
+                for cells in std::iter::once(&self.header).chain(&self.rows) {
+                    let cells: Vec<String> = cells.iter().map(|c| c.replace('\t', " ")).collect();
+                    out.push_str(&format!("{}\n", cells.join("\t")));
+                }
+            }
+            TableFormat::Markdown => {
+                let escape = |cell: &String| cell.replace('|', "\\|");
+                out.push_str(&format!("### {}\n\n", self.title));
+                let header: Vec<String> = self.header.iter().map(escape).collect();
+                out.push_str(&format!("| {} |\n", header.join(" | ")));
+                let separators: Vec<&str> = (0..widths.len())
+                    .map(|i| if i == 0 { ":---" } else { "---:" })
+                    .collect();
+                out.push_str(&format!("| {} |\n", separators.join(" | ")));
+                for row in &self.rows {
+                    let cells: Vec<String> = row.iter().map(escape).collect();
+                    out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                }
+            }
+        }
+        out.push('\n');
+        out
+    }
+
+    pub fn print(&self, style: TableStyle) {
+        print!("{}", self.render(style));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Table {
+        let mut table = Table::new("Totals: A = a/b, B = c/d", &["metric", "A", "B"]);
+        table.row("commits", &[&1200, &35]);
+        table.row("merge | rate", &[&"12.5%", &"-"]);
+        table
+    }
+
+    fn style(format: TableFormat) -> TableStyle {
+        TableStyle {
+            format,
+            color: false,
+        }
+    }
+
+    #[test]
+    fn renders_each_format() {
+        assert_eq!(
+            sample().render(style(TableFormat::Table)),
+            "Totals: A = a/b, B = c/d\n\
+             ┌──────────────┬───────┬────┐\n\
+             │ metric       │     A │  B │\n\
+             ├──────────────┼───────┼────┤\n\
+             │ commits      │  1200 │ 35 │\n\
+             │ merge | rate │ 12.5% │  - │\n\
+             └──────────────┴───────┴────┘\n\n"
+        );
+        assert_eq!(
+            sample().render(style(TableFormat::Plain)),
+            "Totals: A = a/b, B = c/d\n\
+             metric           A  B\n\
+             commits       1200 35\n\
+             merge | rate 12.5%  -\n\n"
+        );
+        assert_eq!(
+            sample().render(style(TableFormat::Tsv)),
+            "# Totals: A = a/b, B = c/d\nmetric\tA\tB\ncommits\t1200\t35\nmerge | rate\t12.5%\t-\n\n"
+        );
+        assert_eq!(
+            sample().render(style(TableFormat::Markdown)),
+            "### Totals: A = a/b, B = c/d\n\n\
+             | metric | A | B |\n\
+             | :--- | ---: | ---: |\n\
+             | commits | 1200 | 35 |\n\
+             | merge \\| rate | 12.5% | - |\n\n"
+        );
+    }
+}
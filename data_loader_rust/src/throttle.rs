@@ -0,0 +1,127 @@
+use rand::Rng;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Paces outgoing API requests to stay clear of GitHub's secondary rate
+/// limits: at most `concurrency` requests in flight, optionally no more than
+/// `requests_per_second` started, and a small random delay before each one.
+pub struct Throttle {
+    concurrency: usize,
+    in_flight: Mutex<usize>,
+    slot_freed: Condvar,
+    min_interval: Option<Duration>,
+    next_start: Mutex<Instant>,
+    max_jitter: Duration,
+}
+
+/// An in-flight request slot; released when dropped.
+pub struct ThrottlePermit<'a> {
+    throttle: &'a Throttle,
+}
+
+impl Throttle {
+    pub fn new(concurrency: usize, requests_per_second: Option<f64>, max_jitter: Duration) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            in_flight: Mutex::new(0),
+            slot_freed: Condvar::new(),
+            min_interval: requests_per_second
+                .filter(|rps| *rps > 0.0)
+                .map(|rps| Duration::from_secs_f64(1.0 / rps)),
+            next_start: Mutex::new(Instant::now()),
+            max_jitter,
+        }
+    }
+
+    /// A throttle that never delays requests.
+    pub fn unlimited() -> Self {
+        Self::new(usize::MAX, None, Duration::ZERO)
+    }
+
+    /// Blocks until a request may start and returns its slot.
+    pub fn acquire(&self) -> ThrottlePermit<'_> {
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            while *in_flight >= self.concurrency {
+                in_flight = self.slot_freed.wait(in_flight).unwrap();
+            }
+            *in_flight += 1;
+        }
+
+        if let Some(interval) = self.min_interval {
+            // Reserve the next start time, then sleep outside the lock
+            let start = {
+                let mut next_start = self.next_start.lock().unwrap();
+                let start = (*next_start).max(Instant::now());
+                *next_start = start + interval;
+                start
+            };
+            let now = Instant::now();
+            if start > now {
+                thread::sleep(start - now);
+            }
+        }
+
+        if !self.max_jitter.is_zero() {
+            let jitter_ms = rand::rng().random_range(0..=self.max_jitter.as_millis() as u64);
+            thread::sleep(Duration::from_millis(jitter_ms));
+        }
+
+        ThrottlePermit { throttle: self }
+    }
+}
+
+impl Drop for ThrottlePermit<'_> {
+    fn drop(&mut self) {
+        *self.throttle.in_flight.lock().unwrap() -= 1;
+        self.throttle.slot_freed.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn caps_requests_in_flight() {
+        let throttle = Throttle::new(2, None, Duration::ZERO);
+        let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        thread::scope(|scope| {
+            for _ in 0..6 {
+                scope.spawn(|| {
+                    let _permit = throttle.acquire();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(*throttle.in_flight.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn spaces_request_starts() {
+        let throttle = Throttle::new(1, Some(50.0), Duration::ZERO);
+        let started = Instant::now();
+        for _ in 0..5 {
+            drop(throttle.acquire());
+        }
+        // The first request starts at once, the other four 20ms apart
+        assert!(started.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn degenerate_limits_are_ignored() {
+        let throttle = Throttle::new(0, Some(0.0), Duration::ZERO);
+        assert_eq!(throttle.concurrency, 1);
+        assert_eq!(throttle.min_interval, None);
+        assert_eq!(
+            Throttle::new(1, Some(-1.0), Duration::ZERO).min_interval,
+            None
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::errors::{DataError, Result};
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
+
+/// Parses a date argument. Accepts:
+/// - `now`
+/// - RFC 3339 / ISO 8601 timestamps (`2024-03-01T12:00:00Z`, with any offset)
+/// - plain dates (`2024-03-01`), taken as midnight UTC
+/// - relative spans back from now: `30d` (days), `2w` (weeks), `12m` (calendar
+///   months) and `2y` (calendar years)
+///
+/// Month and year spans that land on a day the target month lacks are clamped
+/// to its last day, so `1m` before March 31 is the last day of February.
+pub fn parse_date_arg(s: &str) -> Result<DateTime<Utc>> {
+    parse_date_arg_at(s, Utc::now())
+}
+
+/// Like [`parse_date_arg`], with relative spans counted back from `now`.
+pub fn parse_date_arg_at(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("now") {
+        return Ok(now);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    parse_relative(s, now).ok_or_else(|| {
+        DataError::ConfigError(format!(
+            "invalid date '{}'; use now, YYYY-MM-DD, an ISO 8601 timestamp or a span like 30d, 2w, 12m, 2y",
+            s
+        ))
+    })
+}
+
+fn parse_relative(s: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = s.split_at(split);
+    let value: u32 = number.parse().ok()?;
+    match unit {
+        "d" => now.checked_sub_days(Days::new(value.into())),
+        "w" => now.checked_sub_days(Days::new(u64::from(value) * 7)),
+        "m" => now.checked_sub_months(Months::new(value)),
+        "y" => now.checked_sub_months(Months::new(value.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn parse(s: &str, now: &str) -> String {
+        parse_date_arg_at(s, at(now)).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn parses_now() {
+        assert_eq!(
+            parse("now", "2024-05-06T07:08:09Z"),
+            "2024-05-06T07:08:09+00:00"
+        );
+        assert_eq!(
+            parse(" NOW ", "2024-05-06T07:08:09Z"),
+            "2024-05-06T07:08:09+00:00"
+        );
+    }
+
+    #[test]
+    fn parses_absolute_timestamps_in_utc() {
+        let now = "2000-01-01T00:00:00Z";
+        assert_eq!(
+            parse("2024-03-01T12:30:00Z", now),
+            "2024-03-01T12:30:00+00:00"
+        );
+        assert_eq!(
+            parse("2024-03-01T12:30:00+02:00", now),
+            "2024-03-01T10:30:00+00:00"
+        );
+        assert_eq!(parse("2024-03-01", now), "2024-03-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn parses_day_and_week_spans() {
+        let now = "2024-03-01T12:00:00Z";
+        assert_eq!(parse("0d", now), "2024-03-01T12:00:00+00:00");
+        // 2024 is a leap year, so one day back from March 1st is February 29th
+        assert_eq!(parse("1d", now), "2024-02-29T12:00:00+00:00");
+        assert_eq!(parse("30d", now), "2024-01-31T12:00:00+00:00");
+        assert_eq!(parse("2w", now), "2024-02-16T12:00:00+00:00");
+        assert_eq!(
+            parse("1d", "2023-03-01T12:00:00Z"),
+            "2023-02-28T12:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn month_spans_clamp_to_month_end() {
+        assert_eq!(
+            parse("1m", "2024-03-31T00:00:00Z"),
+            "2024-02-29T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse("1m", "2023-03-31T00:00:00Z"),
+            "2023-02-28T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse("1m", "2024-05-31T00:00:00Z"),
+            "2024-04-30T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse("12m", "2024-01-15T00:00:00Z"),
+            "2023-01-15T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse("2m", "2024-01-31T00:00:00Z"),
+            "2023-11-30T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn year_spans_handle_leap_days() {
+        assert_eq!(
+            parse("1y", "2024-02-29T00:00:00Z"),
+            "2023-02-28T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse("4y", "2024-02-29T00:00:00Z"),
+            "2020-02-29T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse("2y", "2024-06-01T00:00:00Z"),
+            "2022-06-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let now = at("2024-01-01T00:00:00Z");
+        for bad in [
+            "",
+            "d",
+            "30",
+            "30x",
+            "-3d",
+            "2024-02-30",
+            "2024/01/01",
+            "yesterday",
+        ] {
+            assert!(parse_date_arg_at(bad, now).is_err(), "accepted {:?}", bad);
+        }
+    }
+}
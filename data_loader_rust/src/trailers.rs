@@ -0,0 +1,163 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+// `Co-authored-by: Name <email>` on a line of its own. GitHub matches the key
+// case-insensitively, so this does too.
+static CO_AUTHOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?mi)^[ \t]*co-authored-by:[ \t]*(.*?)[ \t]*<([^<>\s]+)>[ \t]*$").unwrap()
+});
+
+// `Key: value`, where the key is a single token of letters, digits and dashes.
+static TRAILER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z0-9][A-Za-z0-9-]*)[ \t]*:[ \t]*(.*?)[ \t]*$").unwrap());
+
+/// A `Key: value` line of a commit message's trailer block, such as
+/// `Signed-off-by: Name <email>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+/// Extracts the trailers of a commit message, in order of appearance.
+///
+/// As with `git interpret-trailers`, the trailer block is the last paragraph
+/// of the message, provided it is not the subject and every line in it is
+/// either a trailer or an indented continuation of the previous one; folded
+/// values are joined with a space. Repeated keys are kept.
+pub fn parse_trailers(message: &str) -> Vec<Trailer> {
+    let lines: Vec<&str> = message.trim_end().lines().collect();
+    let Some(blank) = lines.iter().rposition(|line| line.trim().is_empty()) else {
+        return Vec::new();
+    };
+    let mut trailers: Vec<Trailer> = Vec::new();
+    for line in &lines[blank + 1..] {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with([' ', '\t']) {
+            let Some(last) = trailers.last_mut() else {
+                return Vec::new();
+            };
+            if !last.value.is_empty() {
+                last.value.push(' ');
+            }
+            last.value.push_str(line.trim());
+        } else if let Some(caps) = TRAILER.captures(line) {
+            trailers.push(Trailer {
+                key: caps[1].to_string(),
+                value: caps[2].to_string(),
+            });
+        } else {
+            return Vec::new();
+        }
+    }
+    trailers
+}
+
+/// A person credited through a `Co-authored-by` trailer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+/// Extracts the co-authors credited in a commit message, in order of appearance.
+/// A co-author listed twice (by email, ignoring case) is returned once.
+pub fn parse_coauthors(message: &str) -> Vec<CoAuthor> {
+    let mut coauthors: Vec<CoAuthor> = Vec::new();
+    for caps in CO_AUTHOR.captures_iter(message) {
+        let email = caps[2].to_string();
+        if coauthors
+            .iter()
+            .any(|c| c.email.eq_ignore_ascii_case(&email))
+        {
+            continue;
+        }
+        coauthors.push(CoAuthor {
+            name: caps[1].to_string(),
+            email,
+        });
+    }
+    coauthors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_multiple_coauthor_trailers() {
+        let message = "\
+wallet: fix fee bumping
+
+Longer description mentioning Co-authored-by: in passing.
+
+Co-authored-by: Alice Example <alice@example.com>
+co-authored-by: bob <bob@example.org>
+Signed-off-by: Carol <carol@example.net>
+Co-Authored-By: Alice Example <ALICE@example.com>
+";
+        assert_eq!(
+            parse_coauthors(message),
+            vec![
+                CoAuthor {
+                    name: "Alice Example".to_string(),
+                    email: "alice@example.com".to_string(),
+                },
+                CoAuthor {
+                    name: "bob".to_string(),
+                    email: "bob@example.org".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_the_trailer_block_with_folded_values() {
+        let message = "\
+doc: mention the new RPC
+
+Signed-off-by: in the body is not a trailer.
+
+Signed-off-by: Alice <alice@example.com>
+Reviewed-by: Bob <bob@example.org>,
+  Carol <carol@example.net>
+Signed-off-by: Dave <dave@example.com>
+";
+        let trailers: Vec<(String, String)> = parse_trailers(message)
+            .into_iter()
+            .map(|t| (t.key, t.value))
+            .collect();
+        assert_eq!(
+            trailers,
+            [
+                ("Signed-off-by", "Alice <alice@example.com>"),
+                (
+                    "Reviewed-by",
+                    "Bob <bob@example.org>, Carol <carol@example.net>"
+                ),
+                ("Signed-off-by", "Dave <dave@example.com>"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    #[test]
+    fn prose_and_subjects_are_not_trailer_blocks() {
+        assert!(parse_trailers("Fixes: a subject that looks like one").is_empty());
+        assert!(
+            parse_trailers("net: tidy\n\nSigned-off-by: A <a@x>\nSee https://example.com")
+                .is_empty()
+        );
+        assert!(parse_trailers("net: tidy\n\n  indented first line").is_empty());
+        assert_eq!(
+            parse_trailers("net: tidy\r\n\r\nGithub-Pull: #123\r\nRebased-From: abc\r\n").len(),
+            2
+        );
+    }
+
+    #[test]
+    fn ignores_messages_without_trailers() {
+        assert!(parse_coauthors("net: tidy up\n\nNo trailers here.").is_empty());
+        assert!(parse_coauthors("Co-authored-by: missing email").is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::errors::Result;
+use crate::github::{GitHubCommit, GitHubIssue, GitHubPullRequest};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+// Tables whose key columns must not repeat within a repository
+const UNIQUE_KEYS: &[(&str, &str)] = &[
+    ("github_commits", "sha"),
+    ("github_pull_requests", "id"),
+    ("github_pull_requests", "number"),
+    ("github_issues", "id"),
+    ("github_issues", "number"),
+    ("git_commits", "sha"),
+];
+
+/// The span of commit times the stored API commits of a repository are
+/// expected to cover after a fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitWindow {
+    pub since: DateTime<Utc>,
+    /// `--until`; open-ended when `None`
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl CommitWindow {
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        at >= self.since && self.until.is_none_or(|until| at <= until)
+    }
+}
+
+/// Cross-checks what was just fetched for a repository against what ended up
+/// in the database. Returns one message per anomaly; an empty list means the
+/// data is consistent. Merge commits are only expected for PRs merged inside
+/// `commit_window`, which is `None` when no commits were fetched.
+pub fn verify_repo(
+    conn: &Connection,
+    repo_name_full: &str,
+    commit_window: Option<&CommitWindow>,
+    commits: &[GitHubCommit],
+    prs: &[GitHubPullRequest],
+    issues: &[GitHubIssue],
+) -> Result<Vec<String>> {
+    let mut anomalies = Vec::new();
+
+    let missing = count_missing(
+        conn,
+        "SELECT 1 FROM github_commits WHERE repo_name = ?1 AND sha = ?2",
+        repo_name_full,
+        commits.iter().map(|c| c.sha.as_str()),
+    )?;
+    if missing > 0 {
+        anomalies.push(format!(
+            "{} of {} fetched commits are not stored",
+            missing,
+            commits.len()
+        ));
+    }
+    let missing = count_missing(
+        conn,
+        "SELECT 1 FROM github_pull_requests WHERE repo_name = ?1 AND id = ?2",
+        repo_name_full,
+        prs.iter().map(|pr| pr.id),
+    )?;
+    if missing > 0 {
+        anomalies.push(format!(
+            "{} of {} fetched PRs are not stored",
+            missing,
+            prs.len()
+        ));
+    }
+    let missing = count_missing(
+        conn,
+        "SELECT 1 FROM github_issues WHERE repo_name = ?1 AND id = ?2",
+        repo_name_full,
+        issues.iter().map(|issue| issue.id),
+    )?;
+    if missing > 0 {
+        anomalies.push(format!(
+            "{} of {} fetched issues are not stored",
+            missing,
+            issues.len()
+        ));
+    }
+
+    for (table, column) in UNIQUE_KEYS {
+        let duplicates: i64 = conn.query_row(
+            &format!(
+                r"
+                SELECT COUNT(*) FROM (
+                    SELECT {column} FROM {table} WHERE repo_name = ?1
+                    GROUP BY {column} HAVING COUNT(*) > 1
+                )
+                ",
+                table = table,
+                column = column
+            ),
+            params![repo_name_full],
+            |row| row.get(0),
+        )?;
+        if duplicates > 0 {
+            anomalies.push(format!(
+                "{} duplicate {} values in {}",
+                duplicates, column, table
+            ));
+        }
+    }
+
+    // Merge commits of PRs merged inside the commit window should have been
+    // fetched too; others are expected to be missing.
+    let Some(window) = commit_window else {
+        return Ok(anomalies);
+    };
+    let mut stmt = conn.prepare(
+        r"
+        SELECT p.number, p.merge_commit_sha, p.merged_at
+        FROM github_pull_requests p
+        LEFT JOIN github_commits c
+            ON c.repo_name = p.repo_name AND c.sha = p.merge_commit_sha
+        WHERE p.repo_name = ?1 AND p.merged_at IS NOT NULL
+            AND p.merge_commit_sha IS NOT NULL AND c.sha IS NULL
+        ",
+    )?;
+    let unmatched = stmt
+        .query_map(params![repo_name_full], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut out_of_window = 0;
+    let mut in_window = 0;
+    for (number, sha, merged_at) in unmatched {
+        let merged_at = DateTime::parse_from_rfc3339(&merged_at).map(|dt| dt.with_timezone(&Utc));
+        match merged_at {
+            Ok(merged_at) if !window.contains(merged_at) => out_of_window += 1,
+            _ => {
+                in_window += 1;
+                log::warn!(
+                    "PR #{} of {} was merged as {}, which is not among the stored commits",
+                    number,
+                    repo_name_full,
+                    sha
+                );
+            }
+        }
+    }
+    if out_of_window > 0 {
+        log::info!(
+            "{} merged PRs of {} were merged outside the fetched commit window",
+            out_of_window,
+            repo_name_full
+        );
+    }
+    if in_window > 0 {
+        anomalies.push(format!(
+            "{} PRs merged within the fetch window have no stored merge commit",
+            in_window
+        ));
+    }
+
+    Ok(anomalies)
+}
+
+// Number of `keys` for which `exists_sql` (bound to repo name and key) finds no row.
+fn count_missing<K: rusqlite::ToSql>(
+    conn: &Connection,
+    exists_sql: &str,
+    repo_name_full: &str,
+    keys: impl Iterator<Item = K>,
+) -> Result<usize> {
+    let mut stmt = conn.prepare(exists_sql)?;
+    let mut missing = 0;
+    for key in keys {
+        if !stmt.exists(params![repo_name_full, key])? {
+            missing += 1;
+        }
+    }
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::CommitClassifier;
+    use crate::config::{CommitDate, CommitMessage};
+    use crate::db::{create_tables, insert_github_commits, insert_github_pull_requests};
+
+    fn commit(sha: &str) -> GitHubCommit {
+        serde_json::from_value(serde_json::json!({
+            "sha": sha,
+            "commit": {
+                "author": null,
+                "committer": {"name": "c", "email": "c@x", "date": "2024-03-01T00:00:00Z"},
+                "message": "Merge",
+                "url": "",
+                "comment_count": 0,
+                "verification": null
+            },
+            "url": "",
+            "html_url": "",
+            "comments_url": "",
+            "author": null,
+            "committer": null
+        }))
+        .unwrap()
+    }
+
+    fn merged_pr(number: i64, merged_at: &str) -> GitHubPullRequest {
+        serde_json::from_value(serde_json::json!({
+            "id": number * 100,
+            "number": number,
+            "html_url": "",
+            "state": "closed",
+            "title": "",
+            "user": null,
+            "body": null,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": merged_at,
+            "closed_at": merged_at,
+            "merged_at": merged_at,
+            "merge_commit_sha": format!("merge{}", number),
+            "head": null,
+            "base": null,
+            "merged": true,
+            "mergeable": null,
+            "rebaseable": null,
+            "mergeable_state": null,
+            "merged_by": null,
+            "milestone": null,
+            "comments_url": "",
+            "review_comments_url": "",
+            "statuses_url": ""
+        }))
+        .unwrap()
+    }
+
+    fn window(since: &str, until: Option<&str>) -> CommitWindow {
+        CommitWindow {
+            since: since.parse().unwrap(),
+            until: until.map(|until| until.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn fetched_items_missing_from_the_database_are_anomalies() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let commits = [commit("a1"), commit("a2")];
+        insert_github_commits(
+            &conn,
+            &commits[..1],
+            "x/y",
+            10,
+            &CommitClassifier::new("^Revert", "backport", &[]).unwrap(),
+            CommitMessage::Full,
+            CommitDate::Committer,
+        )
+        .unwrap();
+
+        let anomalies = verify_repo(&conn, "x/y", None, &commits, &[], &[]).unwrap();
+        assert_eq!(anomalies, ["1 of 2 fetched commits are not stored"]);
+    }
+
+    #[test]
+    fn merge_commits_are_only_expected_inside_the_commit_window() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let prs = [
+            merged_pr(1, "2023-06-01T00:00:00Z"),
+            merged_pr(2, "2024-02-01T00:00:00Z"),
+            merged_pr(3, "2024-05-01T00:00:00Z"),
+        ];
+        insert_github_pull_requests(&conn, &prs, "x/y", 10).unwrap();
+        let verify = |window: Option<&CommitWindow>| {
+            verify_repo(&conn, "x/y", window, &[], &prs, &[]).unwrap()
+        };
+
+        let expected = ["2 PRs merged within the fetch window have no stored merge commit"];
+        assert_eq!(
+            verify(Some(&window("2024-01-01T00:00:00Z", None))),
+            expected
+        );
+        // --until: the merge after it wasn't fetched on purpose
+        let until = window("2024-01-01T00:00:00Z", Some("2024-03-01T00:00:00Z"));
+        assert_eq!(
+            verify(Some(&until)),
+            ["1 PRs merged within the fetch window have no stored merge commit"]
+        );
+        // No commits fetched (e.g. --only pull-requests)
+        assert!(verify(None).is_empty());
+    }
+}